@@ -0,0 +1,405 @@
+use std::{cmp::Ordering, fmt, ops::{Add, Mul, Neg, Sub}};
+
+//符号と絶対値で表す任意精度の整数。絶対値は2^32進で下位の桁から並べる
+//上位に0の桁を残さず、0は常に非負として持つ
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, mut digits: Vec<u32>) -> Self {
+        trim(&mut digits);
+        Self { negative: negative && !digits.is_empty(), digits }
+    }
+
+    pub fn zero() -> Self {
+        Self { negative: false, digits: vec![] }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    //先頭の+と-を受け付ける。桁が1つもないか、radixで読めない文字があればNone
+    pub fn parse(text: &str, radix: u32) -> Option<Self> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        if text.is_empty() {
+            return None;
+        }
+
+        let mut digits = vec![];
+        for ch in text.chars() {
+            mul_small_add(&mut digits, radix, ch.to_digit(radix)?);
+        }
+
+        Some(Self::from_parts(negative, digits))
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.digits.len() > 2 {
+            return None;
+        }
+        let magnitude = self.digits.iter().rev().fold(0u64, |acc, &digit| (acc << 32) | digit as u64);
+
+        match self.negative {
+            true if magnitude <= 1 << 63 => Some((magnitude as i64).wrapping_neg()),
+            false if magnitude <= i64::MAX as u64 => Some(magnitude as i64),
+            _ => None,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self.digits.iter().rev().fold(0.0, |acc, &digit| acc * 4294967296.0 + digit as f64);
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    //小数部のない有限の値だけを丸めずに変換する
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() || value.fract() != 0.0 {
+            return None;
+        }
+
+        let bits = value.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64;
+        if exponent == 0 {
+            return Some(Self::zero());
+        }
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let shift = exponent - 1075;
+
+        let magnitude = match shift >= 0 {
+            true => shl(&[mantissa as u32, (mantissa >> 32) as u32], shift as usize),
+            false => {
+                let mantissa = mantissa >> -shift;
+                vec![mantissa as u32, (mantissa >> 32) as u32]
+            },
+        };
+
+        Some(Self::from_parts(value < 0.0, magnitude))
+    }
+
+    //0方向に切り捨てる商と、selfと同じ符号の余り。0で割ればNone
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = div_rem_magnitude(&self.digits, &other.digits);
+        Some((
+            Self::from_parts(self.negative != other.negative, quotient),
+            Self::from_parts(self.negative, remainder),
+        ))
+    }
+
+    //絶対値を2進で書いたときの桁数。0は0桁
+    pub fn bits(&self) -> u64 {
+        match self.digits.last() {
+            Some(top) => self.digits.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> BigInt {
+        let mut base = self.clone();
+        let mut result = BigInt::from(1);
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+
+        result
+    }
+
+    //radixは2から36まで。10より上の桁は小文字で書く
+    pub fn to_string_radix(&self, radix: u32) -> String {
+        assert!((2..=36).contains(&radix), "radix must be between 2 and 36");
+        if self.is_zero() {
+            return "0".to_string();
+        }
+
+        //u32に収まる範囲でradixの累乗ごとにまとめて割る
+        let mut chunk = radix;
+        let mut chunk_len = 1;
+        while let Some(next) = chunk.checked_mul(radix) {
+            chunk = next;
+            chunk_len += 1;
+        }
+
+        let mut magnitude = self.digits.clone();
+        let mut reversed = vec![];
+        while !magnitude.is_empty() {
+            let (quotient, mut remainder) = div_rem_small(&magnitude, chunk);
+            magnitude = quotient;
+            for _ in 0..chunk_len {
+                if magnitude.is_empty() && remainder == 0 {
+                    break;
+                }
+                reversed.push(char::from_digit(remainder % radix, radix).unwrap());
+                remainder /= radix;
+            }
+        }
+
+        if self.negative {
+            reversed.push('-');
+        }
+        reversed.iter().rev().collect()
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        Self::from_parts(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_string_radix(10))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.digits, &other.digits),
+            (true, true) => cmp_magnitude(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.digits.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_magnitude(&self.digits, &other.digits));
+        }
+
+        //符号が違えば絶対値の大きい方から小さい方を引く
+        match cmp_magnitude(&self.digits, &other.digits) {
+            Ordering::Less => BigInt::from_parts(other.negative, sub_magnitude(&other.digits, &self.digits)),
+            _ => BigInt::from_parts(self.negative, sub_magnitude(&self.digits, &other.digits)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(self.negative != other.negative, mul_magnitude(&self.digits, &other.digits))
+    }
+}
+
+fn trim(digits: &mut Vec<u32>) {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+
+    for (i, &digit) in long.iter().enumerate() {
+        let sum = digit as u64 + short.get(i).copied().unwrap_or(0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    result.push(carry as u32);
+
+    result
+}
+
+//a >= bであること
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+
+    for (i, &digit) in a.iter().enumerate() {
+        let diff = digit as i64 - b.get(i).copied().unwrap_or(0) as i64 - borrow;
+        result.push(diff as u32);
+        borrow = (diff < 0) as i64;
+    }
+
+    result
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; a.len() + b.len()];
+
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let product = x as u64 * y as u64 + result[i + j] as u64 + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+
+    result
+}
+
+//digits = digits * multiplier + addend
+fn mul_small_add(digits: &mut Vec<u32>, multiplier: u32, addend: u32) {
+    let mut carry = addend as u64;
+    for digit in digits.iter_mut() {
+        let product = *digit as u64 * multiplier as u64 + carry;
+        *digit = product as u32;
+        carry = product >> 32;
+    }
+    if carry > 0 {
+        digits.push(carry as u32);
+    }
+}
+
+fn div_rem_small(digits: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; digits.len()];
+    let mut remainder = 0u64;
+
+    for (i, &digit) in digits.iter().enumerate().rev() {
+        let current = (remainder << 32) | digit as u64;
+        quotient[i] = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+
+    trim(&mut quotient);
+    (quotient, remainder as u32)
+}
+
+//bitsだけ左にずらす
+fn shl(digits: &[u32], bits: usize) -> Vec<u32> {
+    let (limbs, bits) = (bits / 32, bits % 32);
+    let mut result = vec![0u32; limbs];
+
+    let mut carry = 0u32;
+    for &digit in digits {
+        match bits {
+            0 => result.push(digit),
+            _ => {
+                result.push((digit << bits) | carry);
+                carry = digit >> (32 - bits);
+            },
+        }
+    }
+    result.push(carry);
+
+    trim(&mut result);
+    result
+}
+
+//Knuthのアルゴリズム D。除数の最上位桁の最上位ビットが立つようにずらしてから商を1桁ずつ見積もる
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(a, b) == Ordering::Less {
+        return (vec![], a.to_vec());
+    }
+    if b.len() == 1 {
+        let (quotient, remainder) = div_rem_small(a, b[0]);
+        let mut remainder = vec![remainder];
+        trim(&mut remainder);
+        return (quotient, remainder);
+    }
+
+    const BASE: u64 = 1 << 32;
+    let shift = b.last().unwrap().leading_zeros() as usize;
+    let v = shl(b, shift);
+    let mut u = shl(a, shift);
+    u.resize(a.len() + 1, 0);
+
+    let n = v.len();
+    let m = u.len() - n - 1;
+    let mut quotient = vec![0u32; m + 1];
+
+    for j in (0..=m).rev() {
+        let numerator = ((u[j + n] as u64) << 32) | u[j + n - 1] as u64;
+        let mut q_hat = numerator / v[n - 1] as u64;
+        let mut r_hat = numerator % v[n - 1] as u64;
+
+        while q_hat >= BASE || q_hat * v[n - 2] as u64 > ((r_hat << 32) | u[j + n - 2] as u64) {
+            q_hat -= 1;
+            r_hat += v[n - 1] as u64;
+            if r_hat >= BASE {
+                break;
+            }
+        }
+
+        //u[j..=j+n]からq_hat * vを引く
+        let mut borrow = 0i64;
+        for i in 0..n {
+            let product = q_hat * v[i] as u64;
+            let diff = u[i + j] as i64 - borrow - (product & 0xffff_ffff) as i64;
+            u[i + j] = diff as u32;
+            borrow = (product >> 32) as i64 - (diff >> 32);
+        }
+        let diff = u[j + n] as i64 - borrow;
+        u[j + n] = diff as u32;
+
+        //見積もりが1大きすぎたときはvを1回足し戻す
+        if diff < 0 {
+            q_hat -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let sum = u[i + j] as u64 + v[i] as u64 + carry;
+                u[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as u32);
+        }
+
+        quotient[j] = q_hat as u32;
+    }
+
+    //余りはずらした分を戻す
+    let mut remainder = (0..n)
+        .map(|i| match shift {
+            0 => u[i],
+            _ => (u[i] >> shift) | (u[i + 1] << (32 - shift)),
+        })
+        .collect::<Vec<u32>>();
+
+    trim(&mut quotient);
+    trim(&mut remainder);
+    (quotient, remainder)
+}
@@ -0,0 +1,6 @@
+#[cfg(test)]
+mod tests;
+
+pub mod bigint;
+
+pub use bigint::BigInt;
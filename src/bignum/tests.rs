@@ -0,0 +1,166 @@
+use std::cmp::Ordering;
+
+use super::*;
+
+const ITERATIONS: usize = 5000;
+
+//シードを固定したxorshift64*。外部クレートなしで毎回同じ入力を作る
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    //ビット幅もばらつかせて、2^32進で1桁から4桁の値がまんべんなく出るようにする
+    fn next_i128(&mut self, max_bits: u64) -> i128 {
+        let bits = 1 + self.next_u64() % max_bits;
+        let raw = ((self.next_u64() as u128) << 64) | self.next_u64() as u128;
+        let magnitude = (raw >> (128 - bits)) as i128;
+
+        match self.next_u64() % 2 {
+            0 => magnitude,
+            _ => -magnitude,
+        }
+    }
+}
+
+fn big(value: i128) -> BigInt {
+    BigInt::parse(&value.to_string(), 10).unwrap()
+}
+
+fn to_radix(value: i128, radix: u32) -> String {
+    if value == 0 {
+        return "0".to_string();
+    }
+
+    let mut magnitude = value.unsigned_abs();
+    let mut reversed = vec![];
+    while magnitude > 0 {
+        reversed.push(char::from_digit((magnitude % radix as u128) as u32, radix).unwrap());
+        magnitude /= radix as u128;
+    }
+    if value < 0 {
+        reversed.push('-');
+    }
+    reversed.iter().rev().collect()
+}
+
+#[test]
+fn prop_add_sub_match_i128() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+
+    for _ in 0..ITERATIONS {
+        let (a, b) = (rng.next_i128(126), rng.next_i128(126));
+        assert_eq!((&big(a) + &big(b)).to_string(), (a + b).to_string(), "{} + {}", a, b);
+        assert_eq!((&big(a) - &big(b)).to_string(), (a - b).to_string(), "{} - {}", a, b);
+        assert_eq!((-&big(a)).to_string(), (-a).to_string());
+    }
+}
+
+#[test]
+fn prop_mul_matches_i128() {
+    let mut rng = Rng(0x0123_4567_89ab_cdef);
+
+    for _ in 0..ITERATIONS {
+        let (a, b) = (rng.next_i128(63), rng.next_i128(63));
+        assert_eq!((&big(a) * &big(b)).to_string(), (a * b).to_string(), "{} * {}", a, b);
+    }
+}
+
+#[test]
+fn prop_div_rem_matches_i128() {
+    let mut rng = Rng(0xdead_beef_cafe_f00d);
+
+    for _ in 0..ITERATIONS {
+        let (a, b) = (rng.next_i128(126), rng.next_i128(126));
+        if b == 0 {
+            continue;
+        }
+
+        let (quotient, remainder) = big(a).div_rem(&big(b)).unwrap();
+        assert_eq!(quotient.to_string(), (a / b).to_string(), "{} / {}", a, b);
+        assert_eq!(remainder.to_string(), (a % b).to_string(), "{} % {}", a, b);
+    }
+}
+
+#[test]
+fn prop_cmp_matches_i128() {
+    let mut rng = Rng(0x5555_aaaa_3333_cccc);
+
+    for _ in 0..ITERATIONS {
+        let (a, b) = (rng.next_i128(126), rng.next_i128(126));
+        assert_eq!(big(a).cmp(&big(b)), a.cmp(&b), "{} <=> {}", a, b);
+        assert_eq!(big(a).cmp(&big(a)), Ordering::Equal);
+    }
+}
+
+#[test]
+fn prop_radix_round_trip() {
+    let mut rng = Rng(0x1357_9bdf_2468_ace0);
+
+    for _ in 0..ITERATIONS {
+        let a = rng.next_i128(126);
+        let radix = 2 + (rng.next_u64() % 35) as u32;
+        let text = big(a).to_string_radix(radix);
+
+        assert_eq!(text, to_radix(a, radix), "{} in radix {}", a, radix);
+        assert_eq!(BigInt::parse(&text, radix), Some(big(a)));
+    }
+}
+
+#[test]
+fn prop_conversions_match_i128() {
+    let mut rng = Rng(0x0f0f_0f0f_f0f0_f0f0);
+
+    for _ in 0..ITERATIONS {
+        let a = rng.next_i128(126);
+        assert_eq!(big(a).to_i64(), i64::try_from(a).ok(), "{}", a);
+        //i128に収まる整数値のf64はi128へ丸めずに変換できる
+        assert_eq!(BigInt::from_f64(a as f64), Some(big((a as f64) as i128)), "{}", a);
+
+        let small = rng.next_i128(53);
+        assert_eq!(big(small).to_f64(), small as f64);
+        assert_eq!(BigInt::from(small as i64), big(small));
+    }
+}
+
+#[test]
+fn prop_pow_matches_i128() {
+    let mut rng = Rng(0x2468_1357_fdb9_eca8);
+
+    for _ in 0..ITERATIONS {
+        let base = rng.next_i128(20);
+        let exponent = (rng.next_u64() % 20) as u32;
+        if let Some(expected) = base.checked_pow(exponent) {
+            assert_eq!(big(base).pow(exponent).to_string(), expected.to_string(), "{} ** {}", base, exponent);
+        }
+    }
+}
+
+#[test]
+fn bigint_edge_cases() {
+    assert_eq!(BigInt::parse("-0", 10), Some(BigInt::zero()));
+    assert_eq!(BigInt::parse("", 10), None);
+    assert_eq!(BigInt::parse("-", 10), None);
+    assert_eq!(BigInt::parse("12a", 10), None);
+    assert_eq!(BigInt::parse("ff", 16), Some(big(255)));
+
+    assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+    assert_eq!((&BigInt::from(i64::MIN) - &BigInt::from(1)).to_i64(), None);
+    assert_eq!(BigInt::from(5).div_rem(&BigInt::zero()), None);
+    assert_eq!(BigInt::from_f64(1.5), None);
+    assert_eq!(BigInt::from_f64(f64::INFINITY), None);
+    assert_eq!(BigInt::from_f64(1e30).unwrap().to_string(), "1000000000000000019884624838656");
+
+    assert_eq!(BigInt::zero().bits(), 0);
+    assert_eq!(BigInt::from(-1).bits(), 1);
+    assert_eq!(BigInt::from(u32::MAX as i64 + 1).bits(), 33);
+
+    let big = BigInt::from(2).pow(200);
+    assert_eq!(big.to_string(), "1606938044258990275541962092341162602522202993782792835301376");
+    assert_eq!((&big * &big).div_rem(&big).unwrap(), (big.clone(), BigInt::zero()));
+}
@@ -0,0 +1,46 @@
+use std::fmt;
+
+use crate::syntax::Span;
+
+//ソース上の位置。行と列は表示するときにSourceMapでspanから求める
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Label {
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Note {
+    pub message: String,
+    pub label: Option<Label>,
+}
+
+//エラーを表示するための共通の形式。各エラーはこれを作り、Displayもこれを通す
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub label: Option<Label>,
+    pub notes: Vec<Note>,
+}
+
+impl Diagnostic {
+    pub fn new(message: String, span: Span) -> Self {
+        Self { message, label: Some(Label { span }), notes: vec![] }
+    }
+
+    //ファイルが読めないときのように、ソース上の位置を持たないエラー
+    pub fn unlabeled(message: String) -> Self {
+        Self { message, label: None, notes: vec![] }
+    }
+
+    pub fn with_note(mut self, message: String, span: Span) -> Self {
+        self.notes.push(Note { message, label: Some(Label { span }) });
+        self
+    }
+}
+
+//位置はソースがないと行と列にできないので、メッセージだけを表示する
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
@@ -0,0 +1,8 @@
+#[cfg(test)]
+mod tests;
+
+pub mod diagnostic;
+pub mod renderer;
+
+pub use diagnostic::Diagnostic;
+pub use renderer::Renderer;
@@ -0,0 +1,94 @@
+use std::{fmt::Write, io::IsTerminal};
+
+use crate::syntax::span::SourceMap;
+
+use super::diagnostic::{Diagnostic, Label};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const CYAN: &str = "\x1b[1;36m";
+const BLUE: &str = "\x1b[1;34m";
+
+//エラーメッセージに該当行の抜粋と下線を付けて表示する
+#[derive(Debug, Clone, Copy)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    pub fn new(color: bool) -> Self {
+        Self { color }
+    }
+
+    //エラーは標準エラー出力に書くので、そちらが端末のときだけ色を付ける
+    pub fn for_stderr() -> Self {
+        Self::new(std::io::stderr().is_terminal())
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic, source: &str) -> String {
+        let source_map = SourceMap::new(source);
+
+        //行番号の欄の幅はすべての抜粋で揃える
+        let max_line = diagnostic.notes
+            .iter()
+            .filter_map(|note| note.label)
+            .chain(diagnostic.label)
+            .map(|label| source_map.position(label.span.start).line)
+            .max()
+            .unwrap_or(0);
+        let gutter = max_line.to_string().len();
+
+        let mut out = String::new();
+        self.header(&mut out, "error", RED, &diagnostic.message);
+        if let Some(label) = diagnostic.label {
+            self.snippet(&mut out, &source_map, label, gutter, RED);
+        }
+
+        for note in &diagnostic.notes {
+            self.header(&mut out, "note", CYAN, &note.message);
+            if let Some(label) = note.label {
+                self.snippet(&mut out, &source_map, label, gutter, CYAN);
+            }
+        }
+
+        out
+    }
+
+    fn paint(&self, text: &str, color: &str) -> String {
+        match self.color {
+            true => format!("{}{}{}", color, text, RESET),
+            false => text.to_string(),
+        }
+    }
+
+    fn header(&self, out: &mut String, kind: &str, color: &str, message: &str) {
+        writeln!(out, "{}{}", self.paint(kind, color), self.paint(&format!(": {}", message), BOLD)).unwrap();
+    }
+
+    fn snippet(&self, out: &mut String, source_map: &SourceMap, label: Label, gutter: usize, color: &str) {
+        let start = source_map.position(label.span.start);
+        let end = source_map.position(label.span.end);
+        let text = source_map.line_text(start.line).unwrap_or("");
+        let bar = self.paint("|", BLUE);
+
+        writeln!(out, "{:gutter$}{} [{}:{}]", "", self.paint("-->", BLUE), start.line, start.column).unwrap();
+        writeln!(out, "{:gutter$} {}", "", bar).unwrap();
+        writeln!(out, "{} {} {}", self.paint(&format!("{:>gutter$}", start.line), BLUE), bar, text).unwrap();
+
+        //タブはそのまま残して下線の位置を揃える
+        let indent = text
+            .chars()
+            .take(start.column)
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        //spanの範囲に下線を引く。複数行にまたがる場合は行末まで
+        let end_column = match end.line == start.line {
+            true => end.column,
+            false => text.chars().count(),
+        };
+        let carets = "^".repeat(end_column.saturating_sub(start.column).max(1));
+        writeln!(out, "{:gutter$} {} {}{}", "", bar, indent, self.paint(&carets, color)).unwrap();
+    }
+}
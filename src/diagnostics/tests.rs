@@ -0,0 +1,194 @@
+use crate::{
+    errors::CompileError,
+    rloxs_eval::Interpreter,
+    rloxs_lexer::Lexer,
+    rloxs_parser::parser::Parser,
+    rloxs_resolver::Resolver,
+    rloxs_vm::{Compiler, Vm},
+    syntax::Span,
+    test_support::{summary, SharedBuffer},
+};
+
+use super::*;
+
+fn parse_error_helper(input: &str) -> Diagnostic {
+    let tokens = Lexer::new(input).lex().unwrap();
+    let err = Parser::new(tokens).parse_program().unwrap_err();
+    CompileError::from(err).diagnostics().remove(0)
+}
+
+fn summaries(err: &CompileError, source: &str) -> Vec<String> {
+    err.diagnostics().iter().map(|d| summary(d, source)).collect()
+}
+
+#[test]
+fn render_snippet_with_caret() {
+    let source = "let a = 1;\nprint a + \"x\";";
+    let diagnostic = Diagnostic::new("Mismatched types".to_string(), Span::new(21, 24));
+
+    assert_eq!(
+        Renderer::new(false).render(&diagnostic, source),
+        concat!(
+            "error: Mismatched types\n",
+            " --> [2:10]\n",
+            "  |\n",
+            "2 | print a + \"x\";\n",
+            "  |           ^^^\n",
+        ),
+    );
+}
+
+#[test]
+fn caret_width_follows_span() {
+    let render = |start, end| {
+        let diagnostic = Diagnostic::new("e".to_string(), Span::new(start, end));
+        let out = Renderer::new(false).render(&diagnostic, "foo_1 <= 12.5 + x;");
+        out.lines().last().unwrap().trim_start_matches(' ').trim_start_matches('|').to_string()
+    };
+
+    assert_eq!(render(0, 5), " ^^^^^");
+    assert_eq!(render(6, 8), "       ^^");
+    assert_eq!(render(9, 13), "          ^^^^");
+    assert_eq!(render(0, 13), " ^^^^^^^^^^^^^");
+    //空の範囲(Eofなど)にも1文字分は引く
+    assert_eq!(render(18, 18), "                   ^");
+}
+
+#[test]
+fn caret_keeps_tabs() {
+    let diagnostic = Diagnostic::new("e".to_string(), Span::new(2, 5));
+    let out = Renderer::new(false).render(&diagnostic, "\t\tfoo;");
+    assert_eq!(out.lines().last().unwrap(), "  | \t\t^^^");
+}
+
+#[test]
+fn note_points_at_opener() {
+    let source = "fn f() {\n  print (1 + 2;\n}";
+    let diagnostic = parse_error_helper(source);
+
+    assert_eq!(summary(&diagnostic, source), "Unexpected token: ';', expected: ')' at [2:14]");
+    assert_eq!(
+        Renderer::new(false).render(&diagnostic, source),
+        concat!(
+            "error: Unexpected token: ';', expected: ')'\n",
+            " --> [2:14]\n",
+            "  |\n",
+            "2 |   print (1 + 2;\n",
+            "  |               ^\n",
+            "note: expected `)` to close `(` opened here\n",
+            " --> [2:8]\n",
+            "  |\n",
+            "2 |   print (1 + 2;\n",
+            "  |         ^\n",
+        ),
+    );
+}
+
+#[test]
+fn unclosed_brace_note() {
+    let diagnostic = parse_error_helper("{\n  print 1;\n");
+    let note = &diagnostic.notes[0];
+
+    assert_eq!(note.message, "expected `}` to close `{` opened here");
+    assert_eq!(note.label.map(|label| label.span), Some(Span::new(0, 1)));
+}
+
+#[test]
+fn unclosed_bracket_note() {
+    let diagnostic = parse_error_helper("print xs[[1, 2];");
+    let note = &diagnostic.notes[0];
+
+    assert_eq!(note.message, "expected `]` to close `[` opened here");
+    assert_eq!(note.label.map(|label| label.span), Some(Span::new(8, 9)));
+}
+
+#[test]
+fn render_with_color() {
+    let diagnostic = Diagnostic::new("bad".to_string(), Span::new(0, 1));
+    let out = Renderer::new(true).render(&diagnostic, "x;");
+
+    assert!(out.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: bad\x1b[0m\n"));
+    assert!(out.contains("\x1b[1;31m^\x1b[0m"));
+}
+
+#[test]
+fn parse_errors_are_sorted_by_position() {
+    let source = "let = 1;\nprint (2;";
+    let tokens = Lexer::new(source).lex().unwrap();
+    let err = CompileError::from(Parser::new(tokens).parse_program().unwrap_err());
+    let starts = err.diagnostics()
+        .iter()
+        .map(|d| d.label.map(|label| label.span.start))
+        .collect::<Vec<_>>();
+
+    assert_eq!(starts, vec![Some(4), Some(17)]);
+    assert_eq!(summaries(&err, source), [
+        "Unexpected token: '=' at [1:4]",
+        "Unexpected token: ';', expected: ')' at [2:8]",
+    ]);
+}
+
+#[test]
+fn lexer_and_parser_errors_are_merged() {
+    let source = "let = @;\nprint \"a\\q\"; print +;";
+    let (tokens, lexer) = Lexer::new(source).tokenize();
+    let (_, parser) = Parser::new(tokens).parse();
+    let err = CompileError::Syntax { lexer, parser };
+
+    //Errorトークンでの構文エラーは字句エラーと重ねて出さない
+    assert_eq!(summaries(&err, source), [
+        "Unexpected token: '=' at [1:4]",
+        "Unexpected character: @ at [1:6]",
+        "Invalid escape sequence: \\q at [2:8]",
+        "Unexpected token: '+' at [2:19]",
+    ]);
+}
+
+#[test]
+fn caret_follows_span() {
+    let source = "print 1;\nprint \"abc;";
+    let errors = Lexer::new(source).lex().unwrap_err();
+    let out = Renderer::new(false).render(&CompileError::from(errors).diagnostics()[0], source);
+
+    assert_eq!(out.lines().last().unwrap(), "  |       ^^^^^");
+}
+
+#[test]
+fn caret_spans_whole_statement() {
+    let source = "return 1 +\n  2;";
+    let program = Parser::new(Lexer::new(source).lex().unwrap()).parse_program().unwrap();
+    let err = Resolver::new().resolve(&program).unwrap_err();
+    let out = Renderer::new(false).render(&err.diagnostic(), source);
+
+    //複数行にまたがる範囲は行末まで
+    assert_eq!(out.lines().last().unwrap(), "  | ^^^^^^^^^^");
+}
+
+#[test]
+fn runtime_error_caret_follows_span() {
+    let source = "let n = 1;\nfor x in n + 2 { print x; }";
+    let program = Parser::new(Lexer::new(source).lex().unwrap()).parse_program().unwrap();
+    Resolver::new().resolve(&program).unwrap();
+
+    let tree = Interpreter::with_output(Box::new(SharedBuffer::default())).interpret(&program).unwrap_err();
+    let mut vm = Vm::new(Interpreter::with_output(Box::new(SharedBuffer::default())));
+    let vm = vm.interpret(Compiler::new().compile(&program).unwrap()).unwrap_err();
+
+    //反復する式全体に下線を引く。tree-walkerとVMで同じ
+    for err in [tree, vm] {
+        let out = Renderer::new(false).render(&err.diagnostic(), source);
+        assert_eq!(out.lines().last().unwrap(), "  |          ^^^^^");
+    }
+}
+
+#[test]
+fn io_error_has_no_snippet() {
+    let error = std::io::Error::new(std::io::ErrorKind::NotFound, "not found");
+    let err = CompileError::Io { path: "missing.rloxs".to_string(), error };
+
+    assert_eq!(err.to_string(), "Cannot read missing.rloxs: not found");
+    assert_eq!(
+        Renderer::new(false).render(&err.diagnostics()[0], ""),
+        "error: Cannot read missing.rloxs: not found\n",
+    );
+}
@@ -1,33 +1,76 @@
-use std::fmt;
+use std::{fmt, io};
 
-use crate::{rloxs_eval::EvalError, rloxs_lexer::LexerError, rloxs_parser::ParseError};
+use crate::{diagnostics::Diagnostic, rloxs_eval::EvalError, rloxs_lexer::LexerError, rloxs_parser::ParseError, rloxs_resolver::ResolveError, rloxs_vm::CodegenError};
 
 #[derive(Debug)]
 pub enum CompileError {
-    Lexer(LexerError),
-    Parse(ParseError),
+    //字句エラーと構文エラーは1回の解析でまとめて見つける
+    Syntax { lexer: Vec<LexerError>, parser: Vec<ParseError> },
+    Resolve(ResolveError),
+    Codegen(CodegenError),
     Eval(EvalError),
+    //スクリプトのファイルを読めなかった
+    Io { path: String, error: io::Error },
+}
+
+impl CompileError {
+    //字句エラーと構文エラーは複数まとめて報告するので、位置順に並べたリストで返す
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = match self {
+            CompileError::Syntax { lexer, parser } => lexer
+                .iter()
+                .map(|e| e.diagnostic())
+                .chain(parser.iter().map(|e| e.diagnostic()))
+                .collect(),
+            CompileError::Resolve(e) => vec![e.diagnostic()],
+            CompileError::Codegen(e) => vec![e.diagnostic()],
+            CompileError::Eval(e) => vec![e.diagnostic()],
+            CompileError::Io { path, error } => vec![Diagnostic::unlabeled(format!("Cannot read {}: {}", path, error))],
+        };
+
+        diagnostics.sort_by_key(|d| d.label.map(|label| label.span.start));
+        diagnostics
+    }
 }
 
 impl fmt::Display for CompileError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            CompileError::Lexer(e) => write!(f, "{}", e),
-            CompileError::Parse(e) => write!(f, "{}", e),
+            CompileError::Syntax { .. } | CompileError::Io { .. } => write_all(f, &self.diagnostics()),
+            CompileError::Resolve(e) => write!(f, "{}", e),
+            CompileError::Codegen(e) => write!(f, "{}", e),
             CompileError::Eval(e) => write!(f, "{}", e),
         }
     }
 }
 
-impl From<LexerError> for CompileError {
-    fn from(value: LexerError) -> Self {
-        CompileError::Lexer(value)
+//位置順に1行に1つずつ並べる
+fn write_all<E: fmt::Display>(f: &mut fmt::Formatter<'_>, errors: &[E]) -> fmt::Result {
+    let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    write!(f, "{}", messages.join("\n"))
+}
+
+impl From<Vec<LexerError>> for CompileError {
+    fn from(value: Vec<LexerError>) -> Self {
+        CompileError::Syntax { lexer: value, parser: vec![] }
+    }
+}
+
+impl From<Vec<ParseError>> for CompileError {
+    fn from(value: Vec<ParseError>) -> Self {
+        CompileError::Syntax { lexer: vec![], parser: value }
+    }
+}
+
+impl From<ResolveError> for CompileError {
+    fn from(value: ResolveError) -> Self {
+        CompileError::Resolve(value)
     }
 }
 
-impl From<ParseError> for CompileError {
-    fn from(value: ParseError) -> Self {
-        CompileError::Parse(value)
+impl From<CodegenError> for CompileError {
+    fn from(value: CodegenError) -> Self {
+        CompileError::Codegen(value)
     }
 }
 
@@ -1,15 +1,26 @@
 mod rloxs_lexer;
 mod rloxs_parser;
+mod rloxs_resolver;
 mod syntax;
 mod rloxs_eval;
+mod rloxs_vm;
 mod errors;
+mod diagnostics;
+mod bignum;
+#[cfg(test)]
+mod test_support;
 
-use std::{fs::File, io::{Read, Write}, path::Path};
+use std::{fs, io::Write, thread};
 
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
+use diagnostics::Renderer;
 use errors::CompileError;
+use rloxs_eval::Interpreter;
 use rloxs_lexer::Lexer;
 use rloxs_parser::parser::Parser;
+use rloxs_resolver::Resolver;
+use rloxs_vm::{Compiler, Vm};
+use syntax::Stmt;
 
 fn cli() -> Command {
     Command::new("rloxs")
@@ -18,69 +29,208 @@ fn cli() -> Command {
         .value_name("FILE")
         .help("Input a .rloxs file.")
     )
+    .arg(Arg::new("backend")
+        .long("backend")
+        .value_name("BACKEND")
+        .value_parser(["tree", "vm"])
+        .default_value("tree")
+        .help("Execution backend: tree-walking interpreter or bytecode VM.")
+    )
+    .arg(Arg::new("gc-stress")
+        .long("gc-stress")
+        .action(ArgAction::SetTrue)
+        .help("Run the garbage collector on every allocation.")
+    )
+    .arg(Arg::new("gc-stats")
+        .long("gc-stats")
+        .action(ArgAction::SetTrue)
+        .help("Print garbage collector statistics on exit.")
+    )
+    .arg(Arg::new("doc")
+        .long("doc")
+        .action(ArgAction::SetTrue)
+        .help("Print the top-level declarations of FILE with their doc comments instead of running it.")
+    )
+    .arg(Arg::new("cst")
+        .long("cst")
+        .action(ArgAction::SetTrue)
+        .help("Print the concrete syntax tree of FILE, including whitespace and comments, instead of running it.")
+    )
+}
+
+//tree-walkerは関数呼び出しごとにRustのスタックを消費するので、
+//MAX_CALL_DEPTHまで呼び出せるだけの大きさのスタックで実行する
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+//REPLでは同じバックエンドを使い回し、前の行の宣言を引き継ぐ
+enum Backend {
+    Tree(Interpreter),
+    Vm(Vm),
+}
+
+impl Backend {
+    fn new(name: &str, interpreter: Interpreter) -> Self {
+        match name {
+            "vm" => Backend::Vm(Vm::new(interpreter)),
+            _ => Backend::Tree(interpreter),
+        }
+    }
+
+    fn interpreter(&self) -> &Interpreter {
+        match self {
+            Backend::Tree(interpreter) => interpreter,
+            Backend::Vm(vm) => vm.host(),
+        }
+    }
 }
 
 
 fn main() {
     let matches: clap::ArgMatches = cli().get_matches();
 
-    if let Some(filepath) = matches.get_one::<String>("filename") {
-        let filepath = Path::new(filepath);
-        let file = File::open(filepath);
-        let mut file = match file {
-            Ok(f) => f,
-            Err(e) => {
-                panic!("{}", e);
+    let handle = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || start(&matches))
+        .unwrap();
+
+    if handle.join().is_err() {
+        std::process::exit(1);
+    }
+}
+
+fn start(matches: &clap::ArgMatches) {
+    let mut interpreter = Interpreter::new();
+    interpreter.heap_mut().set_stress(matches.get_flag("gc-stress"));
+    let mut backend = Backend::new(matches.get_one::<String>("backend").unwrap(), interpreter);
+
+    let failed = if let Some(filepath) = matches.get_one::<String>("filename") {
+        //読めなければまだ何も実行していないので、報告してすぐに終わる
+        let source = match fs::read_to_string(filepath) {
+            Ok(source) => source,
+            Err(error) => {
+                report(&CompileError::Io { path: filepath.clone(), error }, "");
+                std::process::exit(1);
             },
         };
 
-        let mut source = String::new();
-
-        if let Err(e) = file.read_to_string(&mut source) {
-            panic!("{}", e);
+        if matches.get_flag("cst") {
+            print_cst(&source);
+            return;
         }
 
-        if let Err(e) = run(&source) {
-            panic!("{}", e);
+        let result = match matches.get_flag("doc") {
+            true => print_docs(&source),
+            false => run(&source, 0, &mut backend),
+        };
+
+        match result {
+            Ok(()) => false,
+            Err(e) => {
+                report(&e, &source);
+                true
+            },
         }
 
     }else {
-        repl();
+        repl(&mut backend);
+        false
+    };
+
+    //スクリプトがエラーで終わったときも統計は出す
+    if matches.get_flag("gc-stats") {
+        let heap = backend.interpreter().heap();
+        eprintln!("{}, {} bytes live", heap.stats(), heap.bytes_allocated());
+    }
+
+    if failed {
+        std::process::exit(1);
     }
 }
 
-fn repl() {
+//前の行で定義した関数の中で起きたエラーも指せるように、入力した行をすべてつないでソースとして持つ
+fn repl(backend: &mut Backend) {
+    let mut source = String::new();
+
     loop {
         print!("> ");
         std::io::stdout().flush().unwrap();
 
         let mut line = String::new();
-        std::io::stdin().read_line(&mut line).unwrap();
+        //EOF(Ctrl-D)で終了
+        if std::io::stdin().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
 
         let line = match line.trim() {
-            _ if line.is_empty() => continue,
+            "" => continue,
             line => line,
         };
 
-        if let Err(e) = run(line) {
-            eprintln!("{e}");
+        let start = source.len();
+        source.push_str(line);
+        let result = run(&source, start, backend);
+        source.push('\n');
+
+        if let Err(e) = result {
+            report(&e, &source);
             continue;
         }
     }
 }
 
-fn run(line: &str) -> Result<(), CompileError>{
-    let mut lexer = Lexer::new(line);
-    let tokens = lexer.lex()?;
+fn report(error: &CompileError, source: &str) {
+    let renderer = Renderer::for_stderr();
+    for diagnostic in error.diagnostics() {
+        eprint!("{}", renderer.render(&diagnostic, source));
+    }
+}
 
-    for t in &tokens {
-        println!("{}", t);
+//sourceのstartバイト目から後を読む。位置はsource全体の中の位置になる
+//字句エラーがあってもErrorトークンを含めたまま構文解析し、両方のエラーをまとめて返す
+fn parse(source: &str, start: usize) -> Result<Vec<Stmt>, CompileError> {
+    let (tokens, lexer) = Lexer::new(&source[start..]).starting_at(start).tokenize();
+    let parser = match Parser::new(tokens).parse_program() {
+        Ok(program) if lexer.is_empty() => return Ok(program),
+        Ok(_) => vec![],
+        Err(errors) => errors,
+    };
+
+    Err(CompileError::Syntax { lexer, parser })
+}
+
+fn print_docs(source: &str) -> Result<(), CompileError> {
+    let program = parse(source, 0)?;
+    print!("{}", syntax::doc::render_docs(&program));
+    Ok(())
+}
+
+//構文エラーがあっても木は作れるので、木を表示してからエラーを報告する
+fn print_cst(source: &str) {
+    let (cst, diagnostics) = rloxs_parser::cst::parse_cst(source);
+    print!("{}", cst.dump());
+
+    let renderer = Renderer::for_stderr();
+    for diagnostic in &diagnostics {
+        eprint!("{}", renderer.render(diagnostic, source));
+    }
+    if !diagnostics.is_empty() {
+        std::process::exit(1);
     }
+}
 
-    let mut parser = Parser::new(tokens);
-    let ast = parser.parse_expression()?;
+fn run(source: &str, start: usize, backend: &mut Backend) -> Result<(), CompileError>{
+    let program = parse(source, start)?;
 
-    println!("{:?}", ast);
+    let mut resolver = Resolver::new();
+    resolver.resolve(&program)?;
+
+    match backend {
+        Backend::Tree(interpreter) => interpreter.interpret(&program)?,
+        Backend::Vm(vm) => {
+            let script = Compiler::new().compile(&program)?;
+            vm.interpret(script)?;
+        },
+    }
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,56 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use super::value::Value;
+
+//メソッドはtree-walkerではFunction、VMではClosureとして持つ
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Rc<Class>>,
+    methods: RefCell<HashMap<String, Value>>,
+}
+
+impl Class {
+    pub fn new(name: String, superclass: Option<Rc<Class>>) -> Self {
+        Self { name, superclass, methods: RefCell::new(HashMap::new()) }
+    }
+
+    pub fn add_method(&self, name: &str, method: Value) {
+        self.methods.borrow_mut().insert(name.to_string(), method);
+    }
+
+    //見つからなければスーパークラスを辿る
+    pub fn find_method(&self, name: &str) -> Option<Value> {
+        match self.methods.borrow().get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+
+    pub fn methods(&self) -> &RefCell<HashMap<String, Value>> {
+        &self.methods
+    }
+}
+
+impl fmt::Debug for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: HashMap<String, Value>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Self { class, fields: HashMap::new() }
+    }
+}
+
+//フィールドは自分自身を含み得るので中身までは出力しない
+impl fmt::Debug for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} instance>", self.class.name)
+    }
+}
@@ -0,0 +1,86 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use super::value::Value;
+
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //ブロックごとに外側のスコープを指す新しいスコープを作る
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    //同じスコープでの再定義と外側の束縛のシャドーイングはどちらも許可する
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.enclosing.as_ref()?.borrow().get(name),
+        }
+    }
+
+    //resolverが求めた距離だけ外側のスコープから値を取り出す
+    pub fn get_at(&self, depth: usize, name: &str) -> Option<Value> {
+        match depth {
+            0 => self.values.get(name).cloned(),
+            _ => self.enclosing.as_ref()?.borrow().get_at(depth - 1, name),
+        }
+    }
+
+    pub fn assign_at(&mut self, depth: usize, name: &str, value: Value) -> bool {
+        match depth {
+            0 => match self.values.get_mut(name) {
+                Some(slot) => {
+                    *slot = value;
+                    true
+                },
+                None => false,
+            },
+            _ => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow_mut().assign_at(depth - 1, name, value),
+                None => false,
+            },
+        }
+    }
+
+    //宣言済みの変数が見つかるまで外側へ辿る。見つからなければfalse
+    pub fn assign(&mut self, name: &str, value: Value) -> bool {
+        match self.values.get_mut(name) {
+            Some(slot) => {
+                *slot = value;
+                true
+            },
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+                None => false,
+            },
+        }
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.values.iter()
+    }
+
+    pub fn enclosing(&self) -> Option<&Rc<RefCell<Environment>>> {
+        self.enclosing.as_ref()
+    }
+
+    //GCが到達不能と判断したときに循環参照を断ち切るため中身を捨てる
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
@@ -1,12 +1,594 @@
-use std::fmt;
+use std::{error::Error, fmt::Display};
+
+use crate::{diagnostics::Diagnostic, syntax::{OperatorKind, Span}};
 
 #[derive(Debug)]
 pub enum EvalError {
-    //todo
+    TypeMismatch(TypeMismatch),
+    UndefinedVariable(UndefinedVariable),
+    UndeclaredAssignment(UndeclaredAssignment),
+    NotCallable(NotCallable),
+    ArityMismatch(ArityMismatch),
+    UndefinedProperty(UndefinedProperty),
+    NotAnInstance(NotAnInstance),
+    InvalidSuperclass(InvalidSuperclass),
+    StackOverflow(StackOverflow),
+    DivisionByZero(DivisionByZero),
+    NotIndexable(NotIndexable),
+    InvalidIndex(InvalidIndex),
+    IndexOutOfBounds(IndexOutOfBounds),
+    UnhashableKey(UnhashableKey),
+    NotIterable(NotIterable),
+    InvalidOpcode(InvalidOpcode),
+    Native(NativeError),
+}
+
+impl EvalError {
+    pub fn diagnostic(&self) -> Diagnostic {
+        match self {
+            EvalError::TypeMismatch(e) => e.diagnostic(),
+            EvalError::UndefinedVariable(e) => e.diagnostic(),
+            EvalError::UndeclaredAssignment(e) => e.diagnostic(),
+            EvalError::NotCallable(e) => e.diagnostic(),
+            EvalError::ArityMismatch(e) => e.diagnostic(),
+            EvalError::UndefinedProperty(e) => e.diagnostic(),
+            EvalError::NotAnInstance(e) => e.diagnostic(),
+            EvalError::InvalidSuperclass(e) => e.diagnostic(),
+            EvalError::StackOverflow(e) => e.diagnostic(),
+            EvalError::DivisionByZero(e) => e.diagnostic(),
+            EvalError::NotIndexable(e) => e.diagnostic(),
+            EvalError::InvalidIndex(e) => e.diagnostic(),
+            EvalError::IndexOutOfBounds(e) => e.diagnostic(),
+            EvalError::UnhashableKey(e) => e.diagnostic(),
+            EvalError::NotIterable(e) => e.diagnostic(),
+            EvalError::InvalidOpcode(e) => e.diagnostic(),
+            EvalError::Native(e) => e.diagnostic(),
+        }
+    }
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::TypeMismatch(e) => write!(f, "{}", e),
+            EvalError::UndefinedVariable(e) => write!(f, "{}", e),
+            EvalError::UndeclaredAssignment(e) => write!(f, "{}", e),
+            EvalError::NotCallable(e) => write!(f, "{}", e),
+            EvalError::ArityMismatch(e) => write!(f, "{}", e),
+            EvalError::UndefinedProperty(e) => write!(f, "{}", e),
+            EvalError::NotAnInstance(e) => write!(f, "{}", e),
+            EvalError::InvalidSuperclass(e) => write!(f, "{}", e),
+            EvalError::StackOverflow(e) => write!(f, "{}", e),
+            EvalError::DivisionByZero(e) => write!(f, "{}", e),
+            EvalError::NotIndexable(e) => write!(f, "{}", e),
+            EvalError::InvalidIndex(e) => write!(f, "{}", e),
+            EvalError::IndexOutOfBounds(e) => write!(f, "{}", e),
+            EvalError::UnhashableKey(e) => write!(f, "{}", e),
+            EvalError::NotIterable(e) => write!(f, "{}", e),
+            EvalError::InvalidOpcode(e) => write!(f, "{}", e),
+            EvalError::Native(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for EvalError {}
+
+#[derive(Debug)]
+pub struct TypeMismatch {
+    op_kind: OperatorKind,
+    operand_types: Vec<&'static str>,
+    span: Span,
+}
+
+impl TypeMismatch {
+    pub fn new(
+        op_kind: OperatorKind,
+        operand_types: Vec<&'static str>,
+        span: Span,
+    ) -> Self {
+        Self { op_kind, operand_types, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            format!("Mismatched types for '{}': {}", self.op_kind, self.operand_types.join(", ")),
+            self.span,
+        )
+    }
+}
+
+impl Display for TypeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<TypeMismatch> for EvalError {
+    fn from(value: TypeMismatch) -> Self {
+        EvalError::TypeMismatch(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct UndefinedVariable {
+    name: String,
+    span: Span,
+}
+
+impl UndefinedVariable {
+    pub fn new(name: String, span: Span) -> Self {
+        Self { name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(format!("Undefined variable: {}", self.name), self.span)
+    }
+}
+
+impl Display for UndefinedVariable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<UndefinedVariable> for EvalError {
+    fn from(value: UndefinedVariable) -> Self {
+        EvalError::UndefinedVariable(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct UndeclaredAssignment {
+    name: String,
+    span: Span,
+}
+
+impl UndeclaredAssignment {
+    pub fn new(name: String, span: Span) -> Self {
+        Self { name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(format!("Assignment to undeclared variable: {}", self.name), self.span)
+    }
+}
+
+impl Display for UndeclaredAssignment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<UndeclaredAssignment> for EvalError {
+    fn from(value: UndeclaredAssignment) -> Self {
+        EvalError::UndeclaredAssignment(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct NotCallable {
+    type_name: &'static str,
+    span: Span,
+}
+
+impl NotCallable {
+    pub fn new(type_name: &'static str, span: Span) -> Self {
+        Self { type_name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            format!("Can only call functions and classes, got: {}", self.type_name),
+            self.span,
+        )
+    }
+}
+
+impl Display for NotCallable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<NotCallable> for EvalError {
+    fn from(value: NotCallable) -> Self {
+        EvalError::NotCallable(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct ArityMismatch {
+    name: String,
+    expected: usize,
+    found: usize,
+    span: Span,
+}
+
+impl ArityMismatch {
+    pub fn new(name: String, expected: usize, found: usize, span: Span) -> Self {
+        Self { name, expected, found, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            format!("Expected {} arguments but got {} for {}", self.expected, self.found, self.name),
+            self.span,
+        )
+    }
+}
+
+impl Display for ArityMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<ArityMismatch> for EvalError {
+    fn from(value: ArityMismatch) -> Self {
+        EvalError::ArityMismatch(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct UndefinedProperty {
+    name: String,
+    span: Span,
+}
+
+impl UndefinedProperty {
+    pub fn new(name: String, span: Span) -> Self {
+        Self { name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(format!("Undefined property: {}", self.name), self.span)
+    }
+}
+
+impl Display for UndefinedProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<UndefinedProperty> for EvalError {
+    fn from(value: UndefinedProperty) -> Self {
+        EvalError::UndefinedProperty(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct NotAnInstance {
+    type_name: &'static str,
+    span: Span,
+}
+
+impl NotAnInstance {
+    pub fn new(type_name: &'static str, span: Span) -> Self {
+        Self { type_name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            format!("Only instances have properties, got: {}", self.type_name),
+            self.span,
+        )
+    }
+}
+
+impl Display for NotAnInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<NotAnInstance> for EvalError {
+    fn from(value: NotAnInstance) -> Self {
+        EvalError::NotAnInstance(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidSuperclass {
+    type_name: &'static str,
+    span: Span,
+}
+
+impl InvalidSuperclass {
+    pub fn new(type_name: &'static str, span: Span) -> Self {
+        Self { type_name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            format!("Superclass must be a class, got: {}", self.type_name),
+            self.span,
+        )
+    }
+}
+
+impl Display for InvalidSuperclass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<InvalidSuperclass> for EvalError {
+    fn from(value: InvalidSuperclass) -> Self {
+        EvalError::InvalidSuperclass(value)
+    }
+}
+
+//呼び出しの深さがMAX_CALL_DEPTHを超えたときのエラー
+#[derive(Debug)]
+pub struct StackOverflow {
+    span: Span,
 }
 
-impl fmt::Display for EvalError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "")
+impl StackOverflow {
+    pub fn new(span: Span) -> Self {
+        Self { span }
     }
-}
\ No newline at end of file
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new("Stack overflow".to_string(), self.span)
+    }
+}
+
+impl Display for StackOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<StackOverflow> for EvalError {
+    fn from(value: StackOverflow) -> Self {
+        EvalError::StackOverflow(value)
+    }
+}
+
+//整数の~/と%で右辺が0のときのエラー
+#[derive(Debug)]
+pub struct DivisionByZero {
+    span: Span,
+}
+
+impl DivisionByZero {
+    pub fn new(span: Span) -> Self {
+        Self { span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new("Integer division by zero".to_string(), self.span)
+    }
+}
+
+impl Display for DivisionByZero {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<DivisionByZero> for EvalError {
+    fn from(value: DivisionByZero) -> Self {
+        EvalError::DivisionByZero(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct NotIndexable {
+    type_name: &'static str,
+    span: Span,
+}
+
+impl NotIndexable {
+    pub fn new(type_name: &'static str, span: Span) -> Self {
+        Self { type_name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(format!("Only lists and maps can be indexed, got: {}", self.type_name), self.span)
+    }
+}
+
+impl Display for NotIndexable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<NotIndexable> for EvalError {
+    fn from(value: NotIndexable) -> Self {
+        EvalError::NotIndexable(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidIndex {
+    type_name: &'static str,
+    span: Span,
+}
+
+impl InvalidIndex {
+    pub fn new(type_name: &'static str, span: Span) -> Self {
+        Self { type_name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(format!("List index must be an int, got: {}", self.type_name), self.span)
+    }
+}
+
+impl Display for InvalidIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<InvalidIndex> for EvalError {
+    fn from(value: InvalidIndex) -> Self {
+        EvalError::InvalidIndex(value)
+    }
+}
+
+//indexは負の添字も書かれたままの値で持つ
+#[derive(Debug)]
+pub struct IndexOutOfBounds {
+    index: String,
+    len: usize,
+    span: Span,
+}
+
+impl IndexOutOfBounds {
+    pub fn new(index: String, len: usize, span: Span) -> Self {
+        Self { index, len, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            format!("Index {} out of bounds for list of length {}", self.index, self.len),
+            self.span,
+        )
+    }
+}
+
+impl Display for IndexOutOfBounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<IndexOutOfBounds> for EvalError {
+    fn from(value: IndexOutOfBounds) -> Self {
+        EvalError::IndexOutOfBounds(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct UnhashableKey {
+    type_name: &'static str,
+    span: Span,
+}
+
+impl UnhashableKey {
+    pub fn new(type_name: &'static str, span: Span) -> Self {
+        Self { type_name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            format!("Map key must be a string, number, bool or nil, got: {}", self.type_name),
+            self.span,
+        )
+    }
+}
+
+impl Display for UnhashableKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<UnhashableKey> for EvalError {
+    fn from(value: UnhashableKey) -> Self {
+        EvalError::UnhashableKey(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct NotIterable {
+    type_name: &'static str,
+    span: Span,
+}
+
+impl NotIterable {
+    pub fn new(type_name: &'static str, span: Span) -> Self {
+        Self { type_name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            format!("Only values with an iter() method can be iterated, got: {}", self.type_name),
+            self.span,
+        )
+    }
+}
+
+impl Display for NotIterable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<NotIterable> for EvalError {
+    fn from(value: NotIterable) -> Self {
+        EvalError::NotIterable(value)
+    }
+}
+
+//VMが知らないバイトを命令として読んだ。コンパイラの不具合なので内部エラーとして扱う
+#[derive(Debug)]
+pub struct InvalidOpcode {
+    byte: u8,
+    offset: usize,
+    span: Span,
+}
+
+impl InvalidOpcode {
+    pub fn new(byte: u8, offset: usize, span: Span) -> Self {
+        Self { byte, offset, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            format!("Internal error: invalid opcode {} at offset {}", self.byte, self.offset),
+            self.span,
+        )
+    }
+}
+
+impl Display for InvalidOpcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<InvalidOpcode> for EvalError {
+    fn from(value: InvalidOpcode) -> Self {
+        EvalError::InvalidOpcode(value)
+    }
+}
+
+//ネイティブ関数が返すエラー。関数名と位置は呼び出し側で埋める
+#[derive(Debug)]
+pub struct NativeError {
+    function: String,
+    message: String,
+    span: Span,
+}
+
+impl NativeError {
+    pub fn new(message: String) -> Self {
+        Self { function: String::new(), message, span: Span::default() }
+    }
+
+    pub fn at(self, function: &str, span: Span) -> Self {
+        Self { function: function.to_string(), span, ..self }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(format!("{}: {}", self.function, self.message), self.span)
+    }
+}
+
+impl Display for NativeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<NativeError> for EvalError {
+    fn from(value: NativeError) -> Self {
+        EvalError::Native(value)
+    }
+}
@@ -1,8 +1,626 @@
-use crate::syntax::Expr;
+use std::{cell::RefCell, io::{BufRead, Write}, rc::Rc};
 
-use super::errors::EvalError;
+use crate::syntax::{ClassDecl, Expr, Identifier, OperatorKind, Span, Stmt};
 
+use super::{
+    class::{Class, Instance},
+    environment::Environment,
+    errors::{
+        ArityMismatch,
+        EvalError,
+        InvalidSuperclass,
+        NotAnInstance,
+        NotCallable,
+        NotIterable,
+        StackOverflow,
+        UndeclaredAssignment,
+        UndefinedProperty,
+        UndefinedVariable,
+    },
+    function::Function,
+    gc::{GcRef, Heap},
+    iter::{NativeIterator, HAS_NEXT, ITER, NEXT},
+    map::{build_map, Map},
+    native::{builtin_methods, call_bound_native, call_native, BoundNative, NativeFn, NativeFunction},
+    ops::{eval_binary, eval_unary, get_index, set_index},
+    prelude,
+    value::Value,
+};
 
-fn eval_expr(expr: Expr) -> Result<(), EvalError> {
-    todo!()
-}
\ No newline at end of file
+//関数呼び出しのネストの上限。tree-walkerとVMで共有する
+pub const MAX_CALL_DEPTH: usize = 1024;
+
+//文の実行結果。returnは呼び出し元の関数まで巻き戻す
+enum Flow {
+    Normal,
+    Return(Value),
+}
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    //Noneなら標準入力から読む
+    input: Option<Box<dyn BufRead>>,
+    output: Box<dyn Write>,
+    call_depth: usize,
+    heap: Heap,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::with_output(Box::new(std::io::stdout()))
+    }
+
+    //printの出力先を差し替えるためのコンストラクタ
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        Self::with_io(None, output)
+    }
+
+    //input()の読み込み元も差し替える
+    pub fn with_io(input: Option<Box<dyn BufRead>>, output: Box<dyn Write>) -> Self {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let mut heap = Heap::new();
+        heap.register(GcRef::Environment(globals.clone()));
+
+        let mut interpreter = Self {
+            environment: globals.clone(),
+            globals,
+            input,
+            output,
+            call_depth: 0,
+            heap,
+        };
+        prelude::install(&mut interpreter);
+
+        interpreter
+    }
+
+    //Rustの関数をグローバルに登録する
+    pub fn define_native(&mut self, name: &str, arity: usize, function: NativeFn) {
+        let native = NativeFunction::new(name, arity, function);
+        self.globals.borrow_mut().define(name, Value::Native(Rc::new(native)));
+    }
+
+    pub fn globals(&self) -> &Rc<RefCell<Environment>> {
+        &self.globals
+    }
+
+    //標準入力はREPLと共有するので、ロックは1行読むごとに取る
+    pub fn read_line(&mut self, buf: &mut String) -> std::io::Result<usize> {
+        match &mut self.input {
+            Some(input) => input.read_line(buf),
+            None => std::io::stdin().read_line(buf),
+        }
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        self.output.as_mut()
+    }
+
+    pub fn heap(&self) -> &Heap {
+        &self.heap
+    }
+
+    pub fn heap_mut(&mut self) -> &mut Heap {
+        &mut self.heap
+    }
+
+    //新しく作ったオブジェクトをGCに登録し、閾値を超えていれば回収する
+    fn track(&mut self, object: GcRef) {
+        self.heap.register(object);
+        if self.heap.should_collect() {
+            self.collect_garbage();
+        }
+    }
+
+    //tree-walkerのルートはグローバルと実行中のスコープ。
+    //呼び出し元のスコープや評価途中の値はRustのスタックから参照されているのでGCがルートとして扱う
+    pub fn collect_garbage(&mut self) {
+        let roots = vec![
+            GcRef::Environment(self.globals.clone()),
+            GcRef::Environment(self.environment.clone()),
+        ];
+        self.heap.collect(roots);
+    }
+
+    fn new_scope(&mut self, enclosing: Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+        let scope = Rc::new(RefCell::new(Environment::with_enclosing(enclosing)));
+        self.track(GcRef::Environment(scope.clone()));
+        scope
+    }
+
+    //ネイティブ関数からも使うのでpubにしている
+    pub fn new_list(&mut self, elements: Vec<Value>) -> Value {
+        let list = Rc::new(RefCell::new(elements));
+        self.track(GcRef::List(list.clone()));
+        Value::List(list)
+    }
+
+    pub fn new_iterator(&mut self, iterator: NativeIterator) -> Value {
+        let iterator = Rc::new(RefCell::new(iterator));
+        self.track(GcRef::Iterator(iterator.clone()));
+        Value::Iterator(iterator)
+    }
+
+    fn new_map(&mut self, map: Map) -> Value {
+        let map = Rc::new(RefCell::new(map));
+        self.track(GcRef::Map(map.clone()));
+        Value::Map(map)
+    }
+
+    fn new_bound_native(&mut self, receiver: Value, native: NativeFunction) -> Value {
+        let bound = Rc::new(BoundNative::new(receiver, native));
+        self.track(GcRef::BoundNative(bound.clone()));
+        Value::BoundNative(bound)
+    }
+
+    fn new_function(&mut self, function: Function) -> Rc<Function> {
+        let function = Rc::new(function);
+        self.track(GcRef::Function(function.clone()));
+        function
+    }
+
+    //thisを束縛したスコープを挟んだメソッドを作る
+    fn bind_method(&mut self, method: &Function, instance: Rc<RefCell<Instance>>) -> Rc<Function> {
+        let scope = self.new_scope(method.closure.clone());
+        scope.borrow_mut().define("this", Value::Instance(instance));
+
+        self.new_function(Function::new(method.decl.clone(), scope, method.is_initializer))
+    }
+
+    pub fn interpret(&mut self, program: &[Stmt]) -> Result<(), EvalError> {
+        self.execute_stmts(program)?;
+        Ok(())
+    }
+
+    fn execute_stmts(&mut self, stmts: &[Stmt]) -> Result<Flow, EvalError> {
+        for stmt in stmts {
+            if let Flow::Return(value) = self.execute(stmt)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<Flow, EvalError> {
+        match stmt {
+            Stmt::Expression { expr, .. } => {
+                self.eval_expr(expr)?;
+            },
+            Stmt::Print { expr, .. } => {
+                let value = self.eval_expr(expr)?;
+                writeln!(self.output, "{}", value).unwrap();
+            },
+            Stmt::Let { name, initializer, .. } => {
+                let value = match initializer {
+                    Some(expr) => self.eval_expr(expr)?,
+                    None => Value::Nil,
+                };
+                self.environment.borrow_mut().define(&name.name, value);
+            },
+            Stmt::Fn(decl) => {
+                let function = self.new_function(Function::new(decl.clone(), self.environment.clone(), false));
+                self.environment.borrow_mut().define(&decl.name.name, Value::Function(function));
+            },
+            Stmt::Class(decl) => self.define_class(decl)?,
+            Stmt::Block { stmts, .. } => return self.execute_block(stmts),
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                if self.eval_expr(condition)?.is_truthy() {
+                    return self.execute_block(then_branch);
+                } else if let Some(else_branch) = else_branch {
+                    return self.execute_block(else_branch);
+                }
+            },
+            Stmt::While { condition, body, .. } => {
+                while self.eval_expr(condition)?.is_truthy() {
+                    if let Flow::Return(value) = self.execute_block(body)? {
+                        return Ok(Flow::Return(value));
+                    }
+                }
+            },
+            Stmt::For { initializer, condition, increment, body, .. } => {
+                //初期化節で宣言した変数はループの外から見えないようにする
+                let enclosing = self.enter_scope();
+                let result = self.execute_for(initializer, condition, increment, body);
+                self.environment = enclosing;
+                return result;
+            },
+            Stmt::ForIn { variable, iterable, body, .. } => {
+                return self.execute_for_in(variable, iterable, body);
+            },
+            Stmt::Return { value, .. } => {
+                let value = match value {
+                    Some(expr) => self.eval_expr(expr)?,
+                    None => Value::Nil,
+                };
+                return Ok(Flow::Return(value));
+            },
+            Stmt::Error { .. } => {},
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn execute_block(&mut self, stmts: &[Stmt]) -> Result<Flow, EvalError> {
+        let enclosing = self.enter_scope();
+        //エラーで抜けた場合もスコープを戻す
+        let result = self.execute_stmts(stmts);
+        self.environment = enclosing;
+        result
+    }
+
+    //ループ変数は反復ごとに新しいスコープへコピーし、
+    //各反復で作られたクロージャがその反復の値を捕捉するようにする
+    fn execute_for(
+        &mut self,
+        initializer: &Option<Box<Stmt>>,
+        condition: &Option<Expr>,
+        increment: &Option<Expr>,
+        body: &[Stmt],
+    ) -> Result<Flow, EvalError> {
+        let loop_var = match initializer.as_deref() {
+            Some(Stmt::Let { name, .. }) => Some(name.name.as_str()),
+            _ => None,
+        };
+
+        if let Some(initializer) = initializer {
+            self.execute(initializer)?;
+        }
+
+        let loop_scope = self.environment.clone();
+        self.environment = self.iteration_scope(&loop_scope, loop_var);
+
+        loop {
+            if let Some(condition) = condition {
+                if !self.eval_expr(condition)?.is_truthy() {
+                    break;
+                }
+            }
+
+            if let Flow::Return(value) = self.execute_block(body)? {
+                return Ok(Flow::Return(value));
+            }
+
+            //インクリメントは次の反復のスコープで行う
+            self.environment = self.iteration_scope(&loop_scope, loop_var);
+
+            if let Some(increment) = increment {
+                self.eval_expr(increment)?;
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    //反復プロトコル(iter.rsを参照)に従ってループを回す
+    fn execute_for_in(
+        &mut self,
+        variable: &Identifier,
+        iterable: &Expr,
+        body: &[Stmt],
+    ) -> Result<Flow, EvalError> {
+        let span = iterable.span();
+        let iterable = self.eval_expr(iterable)?;
+        let type_name = iterable.type_name();
+        let iter = self
+            .get_member(iterable, &Identifier { name: ITER.to_string(), span })
+            .map_err(|_| NotIterable::new(type_name, span))?;
+        let iterator = self.call_value(iter, vec![], span)?;
+
+        let has_next = Identifier { name: HAS_NEXT.to_string(), span };
+        let next = Identifier { name: NEXT.to_string(), span };
+        loop {
+            let method = self.get_member(iterator.clone(), &has_next)?;
+            if !self.call_value(method, vec![], span)?.is_truthy() {
+                break;
+            }
+            let method = self.get_member(iterator.clone(), &next)?;
+            let value = self.call_value(method, vec![], span)?;
+
+            //ループ変数は反復ごとのスコープに置き、クロージャがその反復の値を捕捉するようにする
+            let enclosing = self.enter_scope();
+            self.environment.borrow_mut().define(&variable.name, value);
+            let result = self.execute_block(body);
+            self.environment = enclosing;
+
+            if let Flow::Return(value) = result? {
+                return Ok(Flow::Return(value));
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn iteration_scope(
+        &mut self,
+        loop_scope: &Rc<RefCell<Environment>>,
+        loop_var: Option<&str>,
+    ) -> Rc<RefCell<Environment>> {
+        let scope = self.new_scope(loop_scope.clone());
+
+        if let Some(name) = loop_var {
+            let value = self.environment.borrow().get(name).unwrap_or(Value::Nil);
+            scope.borrow_mut().define(name, value);
+        }
+
+        scope
+    }
+
+    //新しいスコープに入り、元のスコープを返す
+    fn enter_scope(&mut self) -> Rc<RefCell<Environment>> {
+        let scope = self.new_scope(self.environment.clone());
+        std::mem::replace(&mut self.environment, scope)
+    }
+
+    fn define_class(&mut self, decl: &ClassDecl) -> Result<(), EvalError> {
+        //スーパークラスは宣言した時点のスコープから名前で探す
+        let superclass = match &decl.superclass {
+            Some(ident) => match self.environment.borrow().get(&ident.name) {
+                Some(Value::Class(class)) => Some(class),
+                Some(value) => Err(InvalidSuperclass::new(value.type_name(), ident.span))?,
+                None => Err(UndefinedVariable::new(ident.name.clone(), ident.span))?,
+            },
+            None => None,
+        };
+
+        //メソッドからスーパークラスをsuperで参照できるようにスコープを挟む
+        let method_scope = match &superclass {
+            Some(superclass) => {
+                let scope = self.new_scope(self.environment.clone());
+                scope.borrow_mut().define("super", Value::Class(superclass.clone()));
+                scope
+            },
+            None => self.environment.clone(),
+        };
+
+        let class = Rc::new(Class::new(decl.name.name.clone(), superclass));
+        self.track(GcRef::Class(class.clone()));
+        for method in &decl.methods {
+            let is_initializer = method.name.name == "init";
+            let function = self.new_function(Function::new(method.clone(), method_scope.clone(), is_initializer));
+            class.add_method(&method.name.name, Value::Function(function));
+        }
+
+        self.environment.borrow_mut().define(&decl.name.name, Value::Class(class));
+
+        Ok(())
+    }
+
+    //depthがNoneならグローバル変数として探す
+    fn lookup_variable(&self, ident: &Identifier, depth: Option<usize>) -> Result<Value, EvalError> {
+        let value = match depth {
+            Some(depth) => self.environment.borrow().get_at(depth, &ident.name),
+            None => self.globals.borrow().get(&ident.name),
+        };
+
+        match value {
+            Some(value) => Ok(value),
+            None => Err(UndefinedVariable::new(ident.name.clone(), ident.span))?,
+        }
+    }
+
+    fn call_value(
+        &mut self,
+        callee: Value,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, EvalError> {
+        let (name, arity) = match &callee {
+            Value::Function(function) => (function.name().to_string(), function.arity()),
+            //クラスを呼び出すときの引数の数はinitに従う
+            Value::Class(class) => (class.name.clone(), find_method(class, "init").map_or(0, |init| init.arity())),
+            Value::Native(native) => return call_native(self, native, &args, span),
+            Value::BoundNative(bound) => return call_bound_native(self, bound, &args, span),
+            callee => Err(NotCallable::new(callee.type_name(), span))?,
+        };
+
+        if arity != args.len() {
+            Err(ArityMismatch::new(name, arity, args.len(), span))?
+        }
+
+        if self.call_depth >= MAX_CALL_DEPTH {
+            Err(StackOverflow::new(span))?
+        }
+
+        match callee {
+            Value::Function(function) => self.call_function(&function, args),
+            Value::Class(class) => {
+                let instance = Rc::new(RefCell::new(Instance::new(class.clone())));
+                self.track(GcRef::Instance(instance.clone()));
+                if let Some(init) = find_method(&class, "init") {
+                    let init = self.bind_method(&init, instance.clone());
+                    self.call_function(&init, args)?;
+                }
+                Ok(Value::Instance(instance))
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn call_function(&mut self, function: &Function, args: Vec<Value>) -> Result<Value, EvalError> {
+        let scope = self.new_scope(function.closure.clone());
+        for (param, arg) in function.decl.params.iter().zip(args) {
+            scope.borrow_mut().define(&param.name, arg);
+        }
+
+        let enclosing = std::mem::replace(&mut self.environment, scope);
+        self.call_depth += 1;
+        let result = self.execute_stmts(&function.decl.body);
+        self.call_depth -= 1;
+        self.environment = enclosing;
+
+        match result? {
+            _ if function.is_initializer => Ok(function.closure.borrow().get("this").unwrap_or(Value::Nil)),
+            Flow::Return(value) => Ok(value),
+            Flow::Normal => Ok(Value::Nil),
+        }
+    }
+
+    pub fn eval_expr(&mut self, expr: &Expr) -> Result<Value, EvalError> {
+        match expr {
+            Expr::Literal { kind, .. } => Ok(Value::from(kind)),
+            Expr::Grouping { expr, .. } => self.eval_expr(expr),
+            Expr::Variable { name, depth, .. } => self.lookup_variable(name, depth.get()),
+            Expr::Assign { name, expr, depth, .. } => {
+                let value = self.eval_expr(expr)?;
+                let assigned = match depth.get() {
+                    Some(depth) => self.environment.borrow_mut().assign_at(depth, &name.name, value.clone()),
+                    None => self.globals.borrow_mut().assign(&name.name, value.clone()),
+                };
+
+                if assigned {
+                    Ok(value)
+                } else {
+                    Err(UndeclaredAssignment::new(name.name.clone(), name.span))?
+                }
+            },
+            Expr::UnaryOp { operator, operand, .. } => {
+                let operand = self.eval_expr(operand)?;
+                eval_unary(operator, operand)
+            },
+            Expr::BinaryOp { left, operator, right, .. } => {
+                let left = self.eval_expr(left)?;
+
+                //and/orは短絡評価するため右辺を先に評価しない
+                match operator.op_kind {
+                    OperatorKind::And if !left.is_truthy() => return Ok(left),
+                    OperatorKind::Or if left.is_truthy() => return Ok(left),
+                    OperatorKind::And | OperatorKind::Or => return self.eval_expr(right),
+                    _ => {},
+                }
+
+                let right = self.eval_expr(right)?;
+                eval_binary(operator, left, right)
+            },
+            Expr::Call { callee, args, paren, .. } => {
+                let callee = self.eval_expr(callee)?;
+
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.eval_expr(arg)?);
+                }
+
+                self.call_value(callee, values, paren.span)
+            },
+            Expr::Get { object, name, .. } => {
+                let object = self.eval_expr(object)?;
+                self.get_member(object, name)
+            },
+            Expr::Set { object, name, operator, value, .. } => {
+                let instance = match self.eval_expr(object)? {
+                    Value::Instance(instance) => instance,
+                    value => Err(NotAnInstance::new(value.type_name(), name.span))?,
+                };
+
+                let value = match operator {
+                    Some(operator) => {
+                        let current = self.get_property(&instance, name)?;
+                        let value = self.eval_expr(value)?;
+                        eval_binary(operator, current, value)?
+                    },
+                    None => self.eval_expr(value)?,
+                };
+                instance.borrow_mut().fields.insert(name.name.clone(), value.clone());
+                Ok(value)
+            },
+            Expr::List { elements, .. } => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.eval_expr(element)?);
+                }
+                Ok(self.new_list(values))
+            },
+            Expr::Map { entries, brace, .. } => {
+                let mut pairs = Vec::with_capacity(entries.len() * 2);
+                for (key, value) in entries {
+                    pairs.push(self.eval_expr(key)?);
+                    pairs.push(self.eval_expr(value)?);
+                }
+                let map = build_map(&pairs, brace.span)?;
+                Ok(self.new_map(map))
+            },
+            Expr::Index { object, index, bracket, .. } => {
+                let object = self.eval_expr(object)?;
+                let index = self.eval_expr(index)?;
+                get_index(&object, &index, bracket.span)
+            },
+            Expr::IndexSet { object, index, operator, value, bracket, .. } => {
+                let object = self.eval_expr(object)?;
+                let index = self.eval_expr(index)?;
+
+                let value = match operator {
+                    Some(operator) => {
+                        let current = get_index(&object, &index, bracket.span)?;
+                        let value = self.eval_expr(value)?;
+                        eval_binary(operator, current, value)?
+                    },
+                    None => self.eval_expr(value)?,
+                };
+                set_index(&object, &index, value.clone(), bracket.span)?;
+                Ok(value)
+            },
+            Expr::This { keyword, depth } => self.lookup_variable(keyword, depth.get()),
+            Expr::Super { keyword, method, depth, .. } => {
+                let this = Identifier { name: "this".to_string(), ..keyword.clone() };
+
+                //thisのスコープはsuperのスコープのすぐ内側にある
+                let depth = depth.get();
+                let this_depth = depth.map(|depth| depth - 1);
+
+                let (Value::Class(superclass), Value::Instance(instance)) =
+                    (self.lookup_variable(keyword, depth)?, self.lookup_variable(&this, this_depth)?)
+                else {
+                    unreachable!("super and this are only defined by class declarations")
+                };
+
+                match find_method(&superclass, &method.name) {
+                    Some(function) => Ok(Value::Function(self.bind_method(&function, instance))),
+                    None => Err(UndefinedProperty::new(method.name.clone(), method.span))?,
+                }
+            },
+            Expr::Interpolation { parts, .. } => {
+                let mut str = String::new();
+                for part in parts {
+                    str.push_str(&self.eval_expr(part)?.to_string());
+                }
+                Ok(Value::String(str))
+            },
+        }
+    }
+
+    //フィールドがメソッドより優先される
+    //インスタンスのプロパティか、組み込みの型のメソッド
+    fn get_member(&mut self, object: Value, name: &Identifier) -> Result<Value, EvalError> {
+        match object {
+            Value::Instance(instance) => self.get_property(&instance, name),
+            value => match builtin_methods(&value) {
+                Some(methods) => match methods(&name.name) {
+                    Some(method) => Ok(self.new_bound_native(value, method)),
+                    None => Err(UndefinedProperty::new(name.name.clone(), name.span))?,
+                },
+                None => Err(NotAnInstance::new(value.type_name(), name.span))?,
+            },
+        }
+    }
+
+    fn get_property(&mut self, instance: &Rc<RefCell<Instance>>, name: &Identifier) -> Result<Value, EvalError> {
+        if let Some(value) = instance.borrow().fields.get(&name.name) {
+            return Ok(value.clone());
+        }
+
+        let method = find_method(&instance.borrow().class, &name.name);
+        match method {
+            Some(method) => Ok(Value::Function(self.bind_method(&method, instance.clone()))),
+            None => Err(UndefinedProperty::new(name.name.clone(), name.span))?,
+        }
+    }
+}
+
+//tree-walkerで定義したクラスのメソッドは常にFunction
+fn find_method(class: &Class, name: &str) -> Option<Rc<Function>> {
+    match class.find_method(name)? {
+        Value::Function(function) => Some(function),
+        _ => None,
+    }
+}
@@ -0,0 +1,34 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::syntax::FnDecl;
+
+use super::environment::Environment;
+
+//宣言時の環境を捕捉した関数値
+pub struct Function {
+    pub decl: Rc<FnDecl>,
+    pub closure: Rc<RefCell<Environment>>,
+    //initメソッドは常にthisを返す
+    pub is_initializer: bool,
+}
+
+impl Function {
+    pub fn new(decl: Rc<FnDecl>, closure: Rc<RefCell<Environment>>, is_initializer: bool) -> Self {
+        Self { decl, closure, is_initializer }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.decl.name.name
+    }
+
+    pub fn arity(&self) -> usize {
+        self.decl.params.len()
+    }
+}
+
+//closureは自分自身を含み得るので中身までは出力しない
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.name())
+    }
+}
@@ -0,0 +1,456 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    mem::size_of,
+    rc::{Rc, Weak},
+};
+
+use crate::rloxs_vm::object::{BoundMethod, Closure, Upvalue};
+
+use super::{
+    class::{Class, Instance},
+    environment::Environment,
+    function::Function,
+    iter::NativeIterator,
+    map::Map,
+    native::BoundNative,
+    value::Value,
+};
+
+//最初の回収までに確保してよい量
+const INITIAL_THRESHOLD: usize = 1024 * 1024;
+//回収後に生き残った量の何倍で次の回収を行うか
+const GROWTH_FACTOR: usize = 2;
+
+//GCが追跡するヒープオブジェクトへの参照
+#[derive(Debug, Clone)]
+pub enum GcRef {
+    Environment(Rc<RefCell<Environment>>),
+    Function(Rc<Function>),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+    Closure(Rc<Closure>),
+    Upvalue(Rc<RefCell<Upvalue>>),
+    BoundMethod(Rc<BoundMethod>),
+    BoundNative(Rc<BoundNative>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<Map>>),
+    Iterator(Rc<RefCell<NativeIterator>>),
+}
+
+//ヒープはオブジェクトを弱参照で持つ。メモリの解放自体はRcに任せ、
+//GCは到達不能な循環の中身を空にしてRcが解放できるようにする
+#[derive(Debug)]
+enum WeakRef {
+    Environment(Weak<RefCell<Environment>>),
+    Function(Weak<Function>),
+    Class(Weak<Class>),
+    Instance(Weak<RefCell<Instance>>),
+    Closure(Weak<Closure>),
+    Upvalue(Weak<RefCell<Upvalue>>),
+    BoundMethod(Weak<BoundMethod>),
+    BoundNative(Weak<BoundNative>),
+    List(Weak<RefCell<Vec<Value>>>),
+    Map(Weak<RefCell<Map>>),
+    Iterator(Weak<RefCell<NativeIterator>>),
+}
+
+impl WeakRef {
+    fn upgrade(&self) -> Option<GcRef> {
+        let object = match self {
+            WeakRef::Environment(weak) => GcRef::Environment(weak.upgrade()?),
+            WeakRef::Function(weak) => GcRef::Function(weak.upgrade()?),
+            WeakRef::Class(weak) => GcRef::Class(weak.upgrade()?),
+            WeakRef::Instance(weak) => GcRef::Instance(weak.upgrade()?),
+            WeakRef::Closure(weak) => GcRef::Closure(weak.upgrade()?),
+            WeakRef::Upvalue(weak) => GcRef::Upvalue(weak.upgrade()?),
+            WeakRef::BoundMethod(weak) => GcRef::BoundMethod(weak.upgrade()?),
+            WeakRef::BoundNative(weak) => GcRef::BoundNative(weak.upgrade()?),
+            WeakRef::List(weak) => GcRef::List(weak.upgrade()?),
+            WeakRef::Map(weak) => GcRef::Map(weak.upgrade()?),
+            WeakRef::Iterator(weak) => GcRef::Iterator(weak.upgrade()?),
+        };
+
+        Some(object)
+    }
+}
+
+impl GcRef {
+    pub fn from_value(value: &Value) -> Option<GcRef> {
+        let object = match value {
+            Value::Function(function) => GcRef::Function(function.clone()),
+            Value::Class(class) => GcRef::Class(class.clone()),
+            Value::Instance(instance) => GcRef::Instance(instance.clone()),
+            Value::Closure(closure) => GcRef::Closure(closure.clone()),
+            Value::BoundMethod(bound) => GcRef::BoundMethod(bound.clone()),
+            Value::BoundNative(bound) => GcRef::BoundNative(bound.clone()),
+            Value::List(list) => GcRef::List(list.clone()),
+            Value::Map(map) => GcRef::Map(map.clone()),
+            Value::Iterator(iterator) => GcRef::Iterator(iterator.clone()),
+            //ネイティブ関数は他のオブジェクトを参照しないので追跡しない
+            Value::Nil | Value::Bool(_) | Value::Integer(_) | Value::BigInt(_) | Value::Number(_) | Value::String(_) | Value::Native(_) => return None,
+        };
+
+        Some(object)
+    }
+
+    fn downgrade(&self) -> WeakRef {
+        match self {
+            GcRef::Environment(rc) => WeakRef::Environment(Rc::downgrade(rc)),
+            GcRef::Function(rc) => WeakRef::Function(Rc::downgrade(rc)),
+            GcRef::Class(rc) => WeakRef::Class(Rc::downgrade(rc)),
+            GcRef::Instance(rc) => WeakRef::Instance(Rc::downgrade(rc)),
+            GcRef::Closure(rc) => WeakRef::Closure(Rc::downgrade(rc)),
+            GcRef::Upvalue(rc) => WeakRef::Upvalue(Rc::downgrade(rc)),
+            GcRef::BoundMethod(rc) => WeakRef::BoundMethod(Rc::downgrade(rc)),
+            GcRef::BoundNative(rc) => WeakRef::BoundNative(Rc::downgrade(rc)),
+            GcRef::List(rc) => WeakRef::List(Rc::downgrade(rc)),
+            GcRef::Map(rc) => WeakRef::Map(Rc::downgrade(rc)),
+            GcRef::Iterator(rc) => WeakRef::Iterator(Rc::downgrade(rc)),
+        }
+    }
+
+    //オブジェクトの同一性はアドレスで判定する
+    fn address(&self) -> usize {
+        match self {
+            GcRef::Environment(rc) => Rc::as_ptr(rc) as *const () as usize,
+            GcRef::Function(rc) => Rc::as_ptr(rc) as *const () as usize,
+            GcRef::Class(rc) => Rc::as_ptr(rc) as *const () as usize,
+            GcRef::Instance(rc) => Rc::as_ptr(rc) as *const () as usize,
+            GcRef::Closure(rc) => Rc::as_ptr(rc) as *const () as usize,
+            GcRef::Upvalue(rc) => Rc::as_ptr(rc) as *const () as usize,
+            GcRef::BoundMethod(rc) => Rc::as_ptr(rc) as *const () as usize,
+            GcRef::BoundNative(rc) => Rc::as_ptr(rc) as *const () as usize,
+            GcRef::List(rc) => Rc::as_ptr(rc) as *const () as usize,
+            GcRef::Map(rc) => Rc::as_ptr(rc) as *const () as usize,
+            GcRef::Iterator(rc) => Rc::as_ptr(rc) as *const () as usize,
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            GcRef::Environment(rc) => Rc::strong_count(rc),
+            GcRef::Function(rc) => Rc::strong_count(rc),
+            GcRef::Class(rc) => Rc::strong_count(rc),
+            GcRef::Instance(rc) => Rc::strong_count(rc),
+            GcRef::Closure(rc) => Rc::strong_count(rc),
+            GcRef::Upvalue(rc) => Rc::strong_count(rc),
+            GcRef::BoundMethod(rc) => Rc::strong_count(rc),
+            GcRef::BoundNative(rc) => Rc::strong_count(rc),
+            GcRef::List(rc) => Rc::strong_count(rc),
+            GcRef::Map(rc) => Rc::strong_count(rc),
+            GcRef::Iterator(rc) => Rc::strong_count(rc),
+        }
+    }
+
+    //参照しているオブジェクトのアドレスをvisitに渡す。
+    //借用中で中身を見られなければfalseを返す
+    fn trace(&self, visit: &mut dyn FnMut(usize)) -> bool {
+        match self {
+            GcRef::Environment(rc) => {
+                let Ok(environment) = rc.try_borrow() else { return false };
+                environment.values().filter_map(|(_, value)| value_address(value)).for_each(&mut *visit);
+                if let Some(enclosing) = environment.enclosing() {
+                    visit(Rc::as_ptr(enclosing) as *const () as usize);
+                }
+            },
+            GcRef::Function(function) => visit(Rc::as_ptr(&function.closure) as *const () as usize),
+            GcRef::Class(class) => {
+                let Ok(methods) = class.methods().try_borrow() else { return false };
+                methods.values().filter_map(value_address).for_each(&mut *visit);
+                if let Some(superclass) = &class.superclass {
+                    visit(Rc::as_ptr(superclass) as *const () as usize);
+                }
+            },
+            GcRef::Instance(rc) => {
+                let Ok(instance) = rc.try_borrow() else { return false };
+                instance.fields.values().filter_map(value_address).for_each(&mut *visit);
+                visit(Rc::as_ptr(&instance.class) as *const () as usize);
+            },
+            GcRef::Closure(closure) => {
+                for upvalue in &closure.upvalues {
+                    visit(Rc::as_ptr(upvalue) as *const () as usize);
+                }
+            },
+            GcRef::Upvalue(rc) => {
+                let Ok(upvalue) = rc.try_borrow() else { return false };
+                if let Upvalue::Closed(value) = &*upvalue {
+                    value_address(value).into_iter().for_each(&mut *visit);
+                }
+            },
+            GcRef::BoundMethod(bound) => {
+                visit(Rc::as_ptr(&bound.receiver) as *const () as usize);
+                visit(Rc::as_ptr(&bound.method) as *const () as usize);
+            },
+            GcRef::BoundNative(bound) => value_address(&bound.receiver).into_iter().for_each(&mut *visit),
+            GcRef::List(rc) => {
+                let Ok(elements) = rc.try_borrow() else { return false };
+                elements.iter().filter_map(value_address).for_each(&mut *visit);
+            },
+            //キーは文字列や数値だけなので値だけを辿る
+            GcRef::Map(rc) => {
+                let Ok(map) = rc.try_borrow() else { return false };
+                map.entries().filter_map(|(_, value)| value_address(value)).for_each(&mut *visit);
+            },
+            //並べておいた値は文字列やマップのキーだけなので、辿るのはリストだけ
+            GcRef::Iterator(rc) => {
+                let Ok(iterator) = rc.try_borrow() else { return false };
+                if let NativeIterator::List { list, .. } = &*iterator {
+                    visit(Rc::as_ptr(list) as *const () as usize);
+                }
+            },
+        }
+
+        true
+    }
+
+    //おおよその使用バイト数
+    fn size(&self) -> usize {
+        match self {
+            GcRef::Environment(rc) => {
+                size_of::<Environment>() + rc.try_borrow().map_or(0, |environment| {
+                    environment.values().map(|(name, value)| entry_size(name, value)).sum()
+                })
+            },
+            GcRef::Function(_) => size_of::<Function>(),
+            GcRef::Class(class) => {
+                size_of::<Class>() + class.name.capacity() + class.methods().try_borrow().map_or(0, |methods| {
+                    methods.iter().map(|(name, value)| entry_size(name, value)).sum()
+                })
+            },
+            GcRef::Instance(rc) => {
+                size_of::<Instance>() + rc.try_borrow().map_or(0, |instance| {
+                    instance.fields.iter().map(|(name, value)| entry_size(name, value)).sum()
+                })
+            },
+            GcRef::Closure(closure) => {
+                size_of::<Closure>() + closure.upvalues.len() * size_of::<Rc<RefCell<Upvalue>>>()
+            },
+            GcRef::Upvalue(rc) => {
+                size_of::<Upvalue>() + rc.try_borrow().map_or(0, |upvalue| match &*upvalue {
+                    Upvalue::Closed(value) => value_size(value),
+                    Upvalue::Open(_) => 0,
+                })
+            },
+            GcRef::BoundMethod(_) => size_of::<BoundMethod>(),
+            GcRef::BoundNative(_) => size_of::<BoundNative>(),
+            GcRef::List(rc) => {
+                size_of::<Vec<Value>>() + rc.try_borrow().map_or(0, |elements| {
+                    elements.iter().map(|value| size_of::<Value>() + value_size(value)).sum()
+                })
+            },
+            GcRef::Map(rc) => {
+                size_of::<Map>() + rc.try_borrow().map_or(0, |map| {
+                    map.entries().map(|(key, value)| {
+                        2 * size_of::<Value>() + value_size(key) + value_size(value)
+                    }).sum()
+                })
+            },
+            GcRef::Iterator(rc) => {
+                size_of::<NativeIterator>() + rc.try_borrow().map_or(0, |iterator| match &*iterator {
+                    NativeIterator::Values { values, .. } => {
+                        values.iter().map(|value| size_of::<Value>() + value_size(value)).sum()
+                    },
+                    NativeIterator::List { .. } | NativeIterator::Range { .. } => 0,
+                })
+            },
+        }
+    }
+
+    //到達不能なオブジェクトの中身を捨てて循環を断ち切る
+    fn clear(&self) {
+        match self {
+            GcRef::Environment(rc) => rc.borrow_mut().clear(),
+            GcRef::Class(class) => class.methods().borrow_mut().clear(),
+            GcRef::Instance(rc) => rc.borrow_mut().fields.clear(),
+            GcRef::Upvalue(rc) => *rc.borrow_mut() = Upvalue::Closed(Value::Nil),
+            GcRef::List(rc) => rc.borrow_mut().clear(),
+            GcRef::Map(rc) => rc.borrow_mut().clear(),
+            GcRef::Iterator(rc) => rc.borrow_mut().clear(),
+            //これらは中身を変更できないが、参照先のオブジェクトを空にすれば循環は切れる
+            GcRef::Function(_) | GcRef::Closure(_) | GcRef::BoundMethod(_) | GcRef::BoundNative(_) => {},
+        }
+    }
+}
+
+fn value_address(value: &Value) -> Option<usize> {
+    let address = match value {
+        Value::Function(rc) => Rc::as_ptr(rc) as *const () as usize,
+        Value::Class(rc) => Rc::as_ptr(rc) as *const () as usize,
+        Value::Instance(rc) => Rc::as_ptr(rc) as *const () as usize,
+        Value::Closure(rc) => Rc::as_ptr(rc) as *const () as usize,
+        Value::BoundMethod(rc) => Rc::as_ptr(rc) as *const () as usize,
+        Value::BoundNative(rc) => Rc::as_ptr(rc) as *const () as usize,
+        Value::List(rc) => Rc::as_ptr(rc) as *const () as usize,
+        Value::Map(rc) => Rc::as_ptr(rc) as *const () as usize,
+        Value::Iterator(rc) => Rc::as_ptr(rc) as *const () as usize,
+        Value::Nil | Value::Bool(_) | Value::Integer(_) | Value::BigInt(_) | Value::Number(_) | Value::String(_) | Value::Native(_) => return None,
+    };
+
+    Some(address)
+}
+
+fn value_size(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.capacity(),
+        _ => 0,
+    }
+}
+
+fn entry_size(name: &String, value: &Value) -> usize {
+    size_of::<String>() + name.capacity() + size_of::<Value>() + value_size(value)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct GcStats {
+    pub collections: usize,
+    pub objects_freed: usize,
+    pub bytes_freed: usize,
+}
+
+impl fmt::Display for GcStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gc: {} collections, {} objects freed, {} bytes freed",
+            self.collections, self.objects_freed, self.bytes_freed,
+        )
+    }
+}
+
+//インタプリタが所有するマークアンドスイープGC。
+//ルートは呼び出し側が渡す環境やVMのスタックに加え、ヒープの外(Rustのローカル変数など)から
+//参照されているオブジェクトとする。参照数がヒープ内からの参照数より多ければ外から参照されている
+#[derive(Debug)]
+pub struct Heap {
+    objects: Vec<WeakRef>,
+    bytes_allocated: usize,
+    next_gc: usize,
+    //trueなら確保のたびに回収する
+    stress: bool,
+    stats: GcStats,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            objects: vec![],
+            bytes_allocated: 0,
+            next_gc: INITIAL_THRESHOLD,
+            stress: false,
+            stats: GcStats::default(),
+        }
+    }
+
+    pub fn set_stress(&mut self, stress: bool) {
+        self.stress = stress;
+    }
+
+    pub fn stats(&self) -> GcStats {
+        self.stats
+    }
+
+    pub fn bytes_allocated(&self) -> usize {
+        self.bytes_allocated
+    }
+
+    pub fn register(&mut self, object: GcRef) {
+        self.bytes_allocated += object.size();
+        self.objects.push(object.downgrade());
+    }
+
+    pub fn should_collect(&self) -> bool {
+        self.stress || self.bytes_allocated > self.next_gc
+    }
+
+    pub fn collect(&mut self, roots: Vec<GcRef>) {
+        //Rcがすでに解放したものは登録から外す
+        let objects = self.objects.iter().filter_map(WeakRef::upgrade).collect::<Vec<_>>();
+        let index = objects
+            .iter()
+            .enumerate()
+            .map(|(i, object)| (object.address(), i))
+            .collect::<HashMap<_, _>>();
+
+        //upgradeした分を除いた参照数
+        let strong = objects.iter().map(|object| object.strong_count() - 1).collect::<Vec<_>>();
+
+        let mut internal = vec![0; objects.len()];
+        let mut opaque = vec![false; objects.len()];
+        for (i, object) in objects.iter().enumerate() {
+            opaque[i] = !object.trace(&mut |address| {
+                if let Some(&j) = index.get(&address) {
+                    internal[j] += 1;
+                }
+            });
+        }
+
+        let mut worklist = vec![];
+        for root in &roots {
+            match index.get(&root.address()) {
+                Some(&i) => worklist.push(i),
+                //登録されていないルートは参照先だけを辿る
+                None => {
+                    root.trace(&mut |address| worklist.extend(index.get(&address)));
+                },
+            }
+        }
+        drop(roots);
+
+        //中身を見られないものや、ヒープの外から参照されているものもルートにする
+        for i in 0..objects.len() {
+            if opaque[i] || strong[i] > internal[i] {
+                worklist.push(i);
+            }
+        }
+
+        let mut marked = vec![false; objects.len()];
+        while let Some(i) = worklist.pop() {
+            if marked[i] {
+                continue;
+            }
+            marked[i] = true;
+
+            objects[i].trace(&mut |address| {
+                if let Some(&j) = index.get(&address) {
+                    if !marked[j] {
+                        worklist.push(j);
+                    }
+                }
+            });
+        }
+
+        let mut live_bytes = 0;
+        let mut survivors = Vec::with_capacity(objects.len());
+        let mut garbage = vec![];
+        for (object, marked) in objects.into_iter().zip(marked) {
+            let size = object.size();
+            if marked {
+                live_bytes += size;
+                survivors.push(object.downgrade());
+            } else {
+                self.stats.bytes_freed += size;
+                garbage.push(object);
+            }
+        }
+
+        self.stats.collections += 1;
+        self.stats.objects_freed += garbage.len();
+        for object in &garbage {
+            object.clear();
+        }
+        //ここでgarbageを手放すと、循環が切れたオブジェクトがRcによって解放される
+        drop(garbage);
+
+        self.objects = survivors;
+        self.bytes_allocated = live_bytes;
+        self.next_gc = (live_bytes * GROWTH_FACTOR).max(INITIAL_THRESHOLD);
+    }
+}
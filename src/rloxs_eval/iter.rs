@@ -0,0 +1,109 @@
+use std::{cell::RefCell, rc::Rc};
+
+use super::{
+    errors::{EvalError, NativeError},
+    eval::Interpreter,
+    native::{NativeFn, NativeFunction},
+    value::Value,
+};
+
+//for-inの反復プロトコル。tree-walkerとVMはどちらもこの約束だけを使ってループを回す
+//
+//  for x in e { ... } は
+//  1. eを1回だけ評価し、そのiter()を引数なしで呼んで反復子を得る。iterを持たない値はNotIterable
+//  2. 反復のたびに反復子のhas_next()を呼び、偽ならループを抜ける
+//  3. 真ならnext()を呼び、その値を反復ごとの新しいスコープのxに入れて本体を実行する
+//
+//リスト・マップ(キー)・文字列(1文字ずつ)・rangeは組み込みの反復子を返す。
+//クラスもiter・has_next・nextを定義すれば同じように反復できる。
+//反復子のiter()は自分自身を返すので、反復子もそのままfor-inに渡せる
+pub const ITER: &str = "iter";
+pub const HAS_NEXT: &str = "has_next";
+pub const NEXT: &str = "next";
+
+#[derive(Debug)]
+pub enum NativeIterator {
+    //反復中の変更も見えるように、毎回そのときの長さと比べる
+    List { list: Rc<RefCell<Vec<Value>>>, index: usize },
+    //マップのキーや文字列の文字は、iter()を呼んだ時点のものを並べておく
+    Values { values: Vec<Value>, index: usize },
+    //startからendの手前まで
+    Range { next: i64, end: i64 },
+}
+
+impl NativeIterator {
+    fn has_next(&self) -> bool {
+        match self {
+            NativeIterator::List { list, index } => *index < list.borrow().len(),
+            NativeIterator::Values { values, index } => *index < values.len(),
+            NativeIterator::Range { next, end } => next < end,
+        }
+    }
+
+    fn next(&mut self) -> Option<Value> {
+        let value = match self {
+            NativeIterator::List { list, index } => list.borrow().get(*index).cloned(),
+            NativeIterator::Values { values, index } => values.get(*index).cloned(),
+            NativeIterator::Range { next, end } => (next < end).then_some(Value::Integer(*next)),
+        }?;
+
+        match self {
+            NativeIterator::List { index, .. } | NativeIterator::Values { index, .. } => *index += 1,
+            NativeIterator::Range { next, .. } => *next += 1,
+        }
+        Some(value)
+    }
+
+    //参照している値を空にして循環を断ち切る
+    pub fn clear(&mut self) {
+        *self = NativeIterator::Values { values: vec![], index: 0 };
+    }
+}
+
+//反復子のメソッド。呼び出すと反復子自身が最初の引数になる
+pub fn iterator_method(name: &str) -> Option<NativeFunction> {
+    let (arity, function): (usize, NativeFn) = match name {
+        ITER => (0, iter_self),
+        HAS_NEXT => (0, has_next),
+        NEXT => (0, next),
+        _ => return None,
+    };
+
+    Some(NativeFunction::new(name, arity, function))
+}
+
+//文字列のメソッドは今のところiterだけ
+pub fn string_method(name: &str) -> Option<NativeFunction> {
+    match name {
+        ITER => Some(NativeFunction::new(name, 0, string_iter)),
+        _ => None,
+    }
+}
+
+fn receiver(args: &[Value]) -> &Rc<RefCell<NativeIterator>> {
+    match &args[0] {
+        Value::Iterator(iterator) => iterator,
+        _ => unreachable!("iterator methods are only bound to iterators"),
+    }
+}
+
+fn iter_self(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    Ok(args[0].clone())
+}
+
+fn has_next(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    Ok(Value::Bool(receiver(args).borrow().has_next()))
+}
+
+fn next(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    match receiver(args).borrow_mut().next() {
+        Some(value) => Ok(value),
+        None => Err(NativeError::new("Iterator is exhausted".to_string()))?,
+    }
+}
+
+fn string_iter(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let Value::String(s) = &args[0] else { unreachable!("string methods are only bound to strings") };
+    let values = s.chars().map(|ch| Value::String(ch.to_string())).collect();
+    Ok(interpreter.new_iterator(NativeIterator::Values { values, index: 0 }))
+}
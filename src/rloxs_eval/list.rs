@@ -0,0 +1,140 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::syntax::Span;
+
+use super::{
+    errors::{EvalError, IndexOutOfBounds, InvalidIndex, NativeError},
+    eval::Interpreter,
+    iter::{NativeIterator, ITER},
+    native::{NativeFn, NativeFunction},
+    value::Value,
+};
+
+pub fn get_element(list: &RefCell<Vec<Value>>, index: &Value, span: Span) -> Result<Value, EvalError> {
+    let list = list.borrow();
+    let i = element_index(index, list.len(), span)?;
+    Ok(list[i].clone())
+}
+
+pub fn set_element(
+    list: &RefCell<Vec<Value>>,
+    index: &Value,
+    value: Value,
+    span: Span,
+) -> Result<(), EvalError> {
+    let mut list = list.borrow_mut();
+    let i = element_index(index, list.len(), span)?;
+    list[i] = value;
+    Ok(())
+}
+
+fn element_index(index: &Value, len: usize, span: Span) -> Result<usize, EvalError> {
+    match index {
+        Value::Integer(n) => match offset(*n, len) {
+            Some(i) if i < len => Ok(i),
+            _ => Err(IndexOutOfBounds::new(n.to_string(), len, span))?,
+        },
+        //i64に収まらない添字はどのリストでも範囲外
+        Value::BigInt(n) => Err(IndexOutOfBounds::new(n.to_string(), len, span))?,
+        value => Err(InvalidIndex::new(value.type_name(), span))?,
+    }
+}
+
+//負の添字は末尾から数える。先頭より前ならNone
+fn offset(index: i64, len: usize) -> Option<usize> {
+    match index < 0 {
+        true => usize::try_from(index + len as i64).ok(),
+        false => usize::try_from(index).ok(),
+    }
+}
+
+//リストのメソッド。呼び出すとリスト自身が最初の引数になる
+pub fn list_method(name: &str) -> Option<NativeFunction> {
+    let (arity, function): (usize, NativeFn) = match name {
+        "push" => (1, push),
+        "pop" => (0, pop),
+        "insert" => (2, insert),
+        "remove" => (1, remove),
+        "len" => (0, len),
+        "slice" => (2, slice),
+        "contains" => (1, contains),
+        ITER => (0, iter),
+        _ => return None,
+    };
+
+    Some(NativeFunction::new(name, arity, function))
+}
+
+fn receiver(args: &[Value]) -> &Rc<RefCell<Vec<Value>>> {
+    match &args[0] {
+        Value::List(list) => list,
+        _ => unreachable!("list methods are only bound to lists"),
+    }
+}
+
+//メソッドの引数の添字。endがtrueなら末尾の次も指せる
+fn argument_index(index: &Value, len: usize, end: bool) -> Result<usize, EvalError> {
+    let limit = if end { len + 1 } else { len };
+
+    match index {
+        Value::Integer(n) => match offset(*n, len) {
+            Some(i) if i < limit => Ok(i),
+            _ => Err(NativeError::new(format!("Index {} out of bounds for list of length {}", n, len)))?,
+        },
+        value => Err(NativeError::new(format!("List index must be an int, got: {}", value.type_name())))?,
+    }
+}
+
+fn push(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    receiver(args).borrow_mut().push(args[1].clone());
+    Ok(Value::Nil)
+}
+
+fn pop(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    match receiver(args).borrow_mut().pop() {
+        Some(value) => Ok(value),
+        None => Err(NativeError::new("Cannot pop from an empty list".to_string()))?,
+    }
+}
+
+//indexの位置に入れ、後ろの要素をずらす
+fn insert(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let mut list = receiver(args).borrow_mut();
+    let i = argument_index(&args[1], list.len(), true)?;
+    list.insert(i, args[2].clone());
+    Ok(Value::Nil)
+}
+
+//indexの位置の要素を取り除いて返す
+fn remove(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let mut list = receiver(args).borrow_mut();
+    let i = argument_index(&args[1], list.len(), false)?;
+    Ok(list.remove(i))
+}
+
+fn len(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    Ok(Value::Integer(receiver(args).borrow().len() as i64))
+}
+
+//startからendの手前までを新しいリストにする
+fn slice(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let elements = {
+        let list = receiver(args).borrow();
+        let start = argument_index(&args[1], list.len(), true)?;
+        let end = argument_index(&args[2], list.len(), true)?;
+        if start > end {
+            Err(NativeError::new(format!("Slice start {} is after end {}", args[1], args[2])))?
+        }
+        list[start..end].to_vec()
+    };
+
+    Ok(interpreter.new_list(elements))
+}
+
+fn contains(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    Ok(Value::Bool(receiver(args).borrow().contains(&args[1])))
+}
+
+fn iter(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    Ok(interpreter.new_iterator(NativeIterator::List { list: receiver(args).clone(), index: 0 }))
+}
@@ -0,0 +1,206 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{bignum::BigInt, syntax::Span};
+
+use super::{
+    errors::{EvalError, NativeError, UnhashableKey},
+    eval::Interpreter,
+    iter::{NativeIterator, ITER},
+    native::{NativeFn, NativeFunction},
+    value::Value,
+};
+
+//マップのキーとして使える値。Valueの==で等しい値は同じキーになる
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Nil,
+    Bool(bool),
+    Integer(i64),
+    BigInt(BigInt),
+    //整数でない浮動小数点数のビット列。NaNは1つにまとめる
+    Float(u64),
+    String(String),
+}
+
+impl MapKey {
+    //関数やリストなど、同一性で比べる値はキーにできない
+    pub fn new(value: &Value) -> Option<MapKey> {
+        let key = match value {
+            Value::Nil => MapKey::Nil,
+            Value::Bool(b) => MapKey::Bool(*b),
+            Value::Integer(n) => MapKey::Integer(*n),
+            Value::BigInt(n) => MapKey::BigInt((**n).clone()),
+            //1.0と1は等しいので同じキーにする
+            Value::Number(n) => match BigInt::from_f64(*n) {
+                Some(n) => match n.to_i64() {
+                    Some(n) => MapKey::Integer(n),
+                    None => MapKey::BigInt(n),
+                },
+                None if n.is_nan() => MapKey::Float(f64::NAN.to_bits()),
+                None => MapKey::Float(n.to_bits()),
+            },
+            Value::String(s) => MapKey::String(s.clone()),
+            _ => return None,
+        };
+
+        Some(key)
+    }
+}
+
+//挿入順を保つマップ。キーは最初に入れたときの値のまま持つ
+//取り除いたエントリは墓標(None)にして位置をずらさず、墓標が半分を超えたら詰め直す
+#[derive(Debug, Default)]
+pub struct Map {
+    entries: Vec<Option<(Value, Value)>>,
+    indices: HashMap<MapKey, usize>,
+}
+
+impl Map {
+    pub fn get(&self, key: &MapKey) -> Option<&Value> {
+        self.indices.get(key).and_then(|&i| self.entries[i].as_ref()).map(|(_, value)| value)
+    }
+
+    pub fn set(&mut self, key: &Value, value: Value, span: Span) -> Result<(), EvalError> {
+        let map_key = map_key(key, span)?;
+        match self.indices.get(&map_key) {
+            Some(&i) => {
+                if let Some(entry) = &mut self.entries[i] {
+                    entry.1 = value;
+                }
+            },
+            None => {
+                self.indices.insert(map_key, self.entries.len());
+                self.entries.push(Some((key.clone(), value)));
+            },
+        }
+        Ok(())
+    }
+
+    //キーを取り除けるのはremoveだけ。nilも普通の値として残す
+    pub fn remove(&mut self, key: &MapKey) -> Option<Value> {
+        let i = self.indices.remove(key)?;
+        let (_, value) = self.entries[i].take()?;
+        if self.entries.len() > 2 * self.len() {
+            self.compact();
+        }
+        Some(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    //挿入順に並べた生きているエントリ
+    pub fn entries(&self) -> impl Iterator<Item = &(Value, Value)> {
+        self.entries.iter().flatten()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.indices.clear();
+    }
+
+    //墓標を取り除き、残ったエントリの位置を付け直す
+    fn compact(&mut self) {
+        self.entries.retain(Option::is_some);
+        for (i, (key, _)) in self.entries.iter().flatten().enumerate() {
+            if let Some(key) = MapKey::new(key) {
+                self.indices.insert(key, i);
+            }
+        }
+    }
+}
+
+//リテラルのキーと値を交互に並べたもの。同じキーは後の値が残る
+pub fn build_map(pairs: &[Value], span: Span) -> Result<Map, EvalError> {
+    let mut map = Map::default();
+    for pair in pairs.chunks(2) {
+        map.set(&pair[0], pair[1].clone(), span)?;
+    }
+    Ok(map)
+}
+
+//ないキーを読むとnil
+pub fn get_entry(map: &RefCell<Map>, key: &Value, span: Span) -> Result<Value, EvalError> {
+    let key = map_key(key, span)?;
+    Ok(map.borrow().get(&key).cloned().unwrap_or(Value::Nil))
+}
+
+pub fn set_entry(map: &RefCell<Map>, key: &Value, value: Value, span: Span) -> Result<(), EvalError> {
+    map.borrow_mut().set(key, value, span)
+}
+
+fn map_key(key: &Value, span: Span) -> Result<MapKey, EvalError> {
+    match MapKey::new(key) {
+        Some(key) => Ok(key),
+        None => Err(UnhashableKey::new(key.type_name(), span))?,
+    }
+}
+
+//マップのメソッド。呼び出すとマップ自身が最初の引数になる
+pub fn map_method(name: &str) -> Option<NativeFunction> {
+    let (arity, function): (usize, NativeFn) = match name {
+        "keys" => (0, keys),
+        "values" => (0, values),
+        "entries" => (0, entries),
+        "len" => (0, len),
+        "contains" => (1, contains),
+        "remove" => (1, remove),
+        ITER => (0, iter),
+        _ => return None,
+    };
+
+    Some(NativeFunction::new(name, arity, function))
+}
+
+fn receiver(args: &[Value]) -> &Rc<RefCell<Map>> {
+    match &args[0] {
+        Value::Map(map) => map,
+        _ => unreachable!("map methods are only bound to maps"),
+    }
+}
+
+fn argument_key(key: &Value) -> Result<MapKey, EvalError> {
+    match MapKey::new(key) {
+        Some(key) => Ok(key),
+        None => Err(NativeError::new(format!("Map key must be a string, number, bool or nil, got: {}", key.type_name())))?,
+    }
+}
+
+fn keys(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let keys = receiver(args).borrow().entries().map(|(key, _)| key.clone()).collect();
+    Ok(interpreter.new_list(keys))
+}
+
+fn values(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let values = receiver(args).borrow().entries().map(|(_, value)| value.clone()).collect();
+    Ok(interpreter.new_list(values))
+}
+
+//[キー, 値]のリストを挿入順に並べる
+fn entries(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let entries: Vec<_> = receiver(args).borrow().entries().cloned().collect();
+    let pairs = entries.into_iter().map(|(key, value)| interpreter.new_list(vec![key, value])).collect();
+    Ok(interpreter.new_list(pairs))
+}
+
+fn len(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    Ok(Value::Integer(receiver(args).borrow().len() as i64))
+}
+
+fn contains(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let key = argument_key(&args[1])?;
+    Ok(Value::Bool(receiver(args).borrow().get(&key).is_some()))
+}
+
+//取り除いた値を返す。キーがなければnil
+fn remove(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let key = argument_key(&args[1])?;
+    Ok(receiver(args).borrow_mut().remove(&key).unwrap_or(Value::Nil))
+}
+
+//キーを挿入順に返す反復子
+fn iter(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let values = receiver(args).borrow().entries().map(|(key, _)| key.clone()).collect();
+    Ok(interpreter.new_iterator(NativeIterator::Values { values, index: 0 }))
+}
@@ -1,4 +1,19 @@
+#[cfg(test)]
+mod tests;
+
 pub mod eval;
+pub mod environment;
+pub mod class;
+pub mod function;
+pub mod gc;
+pub mod native;
+pub mod list;
+pub mod map;
+pub mod iter;
+pub mod prelude;
+pub mod ops;
+pub mod value;
 pub mod errors;
 
-pub use errors::EvalError;
\ No newline at end of file
+pub use errors::EvalError;
+pub use eval::Interpreter;
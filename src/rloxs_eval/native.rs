@@ -0,0 +1,102 @@
+use std::fmt;
+
+use crate::syntax::Span;
+
+use super::{
+    errors::{ArityMismatch, EvalError},
+    eval::Interpreter,
+    iter::{iterator_method, string_method},
+    list::list_method,
+    map::map_method,
+    value::Value,
+};
+
+//Rustで実装した関数の本体。引数の数は呼び出し前にチェック済み
+pub type NativeFn = fn(&mut Interpreter, &[Value]) -> Result<Value, EvalError>;
+
+//ホストの機能をrloxsから呼び出すための唯一の拡張点
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: usize,
+    pub function: NativeFn,
+}
+
+impl NativeFunction {
+    pub fn new(name: &str, arity: usize, function: NativeFn) -> Self {
+        Self { name: name.to_string(), arity, function }
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+//リストのメソッドのように値に束縛したネイティブ関数。
+//呼び出すとreceiverが最初の引数になり、arityはそれを除いた数
+pub struct BoundNative {
+    pub receiver: Value,
+    pub native: NativeFunction,
+}
+
+impl BoundNative {
+    pub fn new(receiver: Value, native: NativeFunction) -> Self {
+        Self { receiver, native }
+    }
+}
+
+impl fmt::Debug for BoundNative {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.native.name)
+    }
+}
+
+//組み込みの型が持つメソッドの表。メソッドを持たない型ならNone
+pub fn builtin_methods(value: &Value) -> Option<fn(&str) -> Option<NativeFunction>> {
+    match value {
+        Value::List(_) => Some(list_method),
+        Value::Map(_) => Some(map_method),
+        Value::String(_) => Some(string_method),
+        Value::Iterator(_) => Some(iterator_method),
+        _ => None,
+    }
+}
+
+//tree-walkerとVMで共有する呼び出し処理
+pub fn call_native(
+    interpreter: &mut Interpreter,
+    native: &NativeFunction,
+    args: &[Value],
+    span: Span,
+) -> Result<Value, EvalError> {
+    if native.arity != args.len() {
+        Err(ArityMismatch::new(native.name.clone(), native.arity, args.len(), span))?
+    }
+
+    (native.function)(interpreter, args).map_err(|e| match e {
+        EvalError::Native(e) => e.at(&native.name, span).into(),
+        e => e,
+    })
+}
+
+pub fn call_bound_native(
+    interpreter: &mut Interpreter,
+    bound: &BoundNative,
+    args: &[Value],
+    span: Span,
+) -> Result<Value, EvalError> {
+    let native = &bound.native;
+    if native.arity != args.len() {
+        Err(ArityMismatch::new(native.name.clone(), native.arity, args.len(), span))?
+    }
+
+    let mut with_receiver = Vec::with_capacity(args.len() + 1);
+    with_receiver.push(bound.receiver.clone());
+    with_receiver.extend_from_slice(args);
+
+    (native.function)(interpreter, &with_receiver).map_err(|e| match e {
+        EvalError::Native(e) => e.at(&native.name, span).into(),
+        e => e,
+    })
+}
@@ -0,0 +1,128 @@
+use crate::{bignum::BigInt, syntax::{Operator, OperatorKind, Span}};
+
+use super::{
+    errors::{DivisionByZero, EvalError, NotIndexable, TypeMismatch},
+    list::{get_element, set_element},
+    map::{get_entry, set_entry},
+    value::Value,
+};
+
+//演算子の意味はtree-walkerとVMで共有する
+pub fn eval_unary(operator: &Operator, operand: Value) -> Result<Value, EvalError> {
+    match (operator.op_kind, operand) {
+        (OperatorKind::Not, operand) => Ok(Value::Bool(!operand.is_truthy())),
+        (OperatorKind::Subtract, Value::Integer(n)) => match n.checked_neg() {
+            Some(n) => Ok(Value::Integer(n)),
+            None => Ok(Value::from(-&BigInt::from(n))),
+        },
+        (OperatorKind::Subtract, Value::BigInt(n)) => Ok(Value::from(-&*n)),
+        (OperatorKind::Subtract, Value::Number(n)) => Ok(Value::Number(-n)),
+        (op_kind, operand) => Err(
+            TypeMismatch::new(op_kind, vec![operand.type_name()], operator.span)
+        )?,
+    }
+}
+
+pub fn eval_binary(operator: &Operator, left: Value, right: Value) -> Result<Value, EvalError> {
+    let value = match (operator.op_kind, left, right) {
+        (OperatorKind::Equal, l, r) => Value::Bool(l == r),
+        (OperatorKind::NotEqual, l, r) => Value::Bool(l != r),
+
+        (OperatorKind::Add, Value::String(l), Value::String(r)) => Value::String(l + &r),
+
+        (_, Value::Integer(l), Value::Integer(r)) => eval_integer(operator, l, r)?,
+
+        (op_kind, l, r) => match (l.to_bigint(), r.to_bigint()) {
+            (Some(l), Some(r)) => eval_bigint(operator, &l, &r)?,
+            //整数と浮動小数点数が混ざれば浮動小数点数にそろえる
+            _ => match (l.to_f64(), r.to_f64()) {
+                (Some(l), Some(r)) => eval_float(op_kind, l, r),
+                _ => Err(TypeMismatch::new(op_kind, vec![l.type_name(), r.type_name()], operator.span))?,
+            },
+        },
+    };
+
+    Ok(value)
+}
+
+//整数どうしの演算。/は割り切れないこともあるので浮動小数点数で計算する
+fn eval_integer(operator: &Operator, l: i64, r: i64) -> Result<Value, EvalError> {
+    let result = match operator.op_kind {
+        OperatorKind::Add => l.checked_add(r),
+        OperatorKind::Subtract => l.checked_sub(r),
+        OperatorKind::Multiply => l.checked_mul(r),
+        OperatorKind::IntDivide | OperatorKind::Modulo if r == 0 => {
+            Err(DivisionByZero::new(operator.span))?
+        },
+        OperatorKind::IntDivide => l.checked_div(r),
+        OperatorKind::Modulo => l.checked_rem(r),
+        OperatorKind::Greater => return Ok(Value::Bool(l > r)),
+        OperatorKind::GreaterEqual => return Ok(Value::Bool(l >= r)),
+        OperatorKind::Less => return Ok(Value::Bool(l < r)),
+        OperatorKind::LessEqual => return Ok(Value::Bool(l <= r)),
+        op_kind => return Ok(eval_float(op_kind, l as f64, r as f64)),
+    };
+
+    match result {
+        Some(n) => Ok(Value::Integer(n)),
+        //i64からあふれたらBigIntで計算し直す
+        None => eval_bigint(operator, &BigInt::from(l), &BigInt::from(r)),
+    }
+}
+
+fn eval_bigint(operator: &Operator, l: &BigInt, r: &BigInt) -> Result<Value, EvalError> {
+    let value = match operator.op_kind {
+        OperatorKind::Add => Value::from(l + r),
+        OperatorKind::Subtract => Value::from(l - r),
+        OperatorKind::Multiply => Value::from(l * r),
+        OperatorKind::IntDivide | OperatorKind::Modulo => {
+            let (quotient, remainder) = l.div_rem(r)
+                .ok_or_else(|| DivisionByZero::new(operator.span))?;
+            match operator.op_kind {
+                OperatorKind::IntDivide => Value::from(quotient),
+                _ => Value::from(remainder),
+            }
+        },
+        OperatorKind::Greater => Value::Bool(l > r),
+        OperatorKind::GreaterEqual => Value::Bool(l >= r),
+        OperatorKind::Less => Value::Bool(l < r),
+        OperatorKind::LessEqual => Value::Bool(l <= r),
+        op_kind => eval_float(op_kind, l.to_f64(), r.to_f64()),
+    };
+
+    Ok(value)
+}
+
+//~/と%は整数と同じく0方向への切り捨てに合わせる
+fn eval_float(op_kind: OperatorKind, l: f64, r: f64) -> Value {
+    match op_kind {
+        OperatorKind::Add => Value::Number(l + r),
+        OperatorKind::Subtract => Value::Number(l - r),
+        OperatorKind::Multiply => Value::Number(l * r),
+        OperatorKind::Divide => Value::Number(l / r),
+        OperatorKind::IntDivide => Value::Number((l / r).trunc()),
+        OperatorKind::Modulo => Value::Number(l % r),
+        OperatorKind::Greater => Value::Bool(l > r),
+        OperatorKind::GreaterEqual => Value::Bool(l >= r),
+        OperatorKind::Less => Value::Bool(l < r),
+        OperatorKind::LessEqual => Value::Bool(l <= r),
+        op_kind => unreachable!("{} is not an arithmetic operator", op_kind),
+    }
+}
+
+//添字の読み書きもtree-walkerとVMで共有する
+pub fn get_index(object: &Value, index: &Value, span: Span) -> Result<Value, EvalError> {
+    match object {
+        Value::List(list) => get_element(list, index, span),
+        Value::Map(map) => get_entry(map, index, span),
+        value => Err(NotIndexable::new(value.type_name(), span))?,
+    }
+}
+
+pub fn set_index(object: &Value, index: &Value, value: Value, span: Span) -> Result<(), EvalError> {
+    match object {
+        Value::List(list) => set_element(list, index, value, span),
+        Value::Map(map) => set_entry(map, index, value, span),
+        object => Err(NotIndexable::new(object.type_name(), span))?,
+    }
+}
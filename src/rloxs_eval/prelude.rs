@@ -0,0 +1,165 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::bignum::BigInt;
+
+use super::{
+    errors::{EvalError, NativeError},
+    eval::Interpreter,
+    iter::NativeIterator,
+    value::Value,
+};
+
+//Interpreterの作成時にグローバルへ登録される標準の関数
+pub fn install(interpreter: &mut Interpreter) {
+    interpreter.define_native("clock", 0, clock);
+    interpreter.define_native("type", 1, type_of);
+    interpreter.define_native("str", 1, str);
+    interpreter.define_native("num", 1, num);
+    interpreter.define_native("int", 1, int);
+    interpreter.define_native("float", 1, float);
+    interpreter.define_native("pow", 2, pow);
+    interpreter.define_native("radix", 2, radix);
+    interpreter.define_native("len", 1, len);
+    interpreter.define_native("range", 2, range);
+    interpreter.define_native("input", 0, input);
+}
+
+//UNIXエポックからの経過秒数
+fn clock(_: &mut Interpreter, _: &[Value]) -> Result<Value, EvalError> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| NativeError::new(e.to_string()))?;
+
+    Ok(Value::Number(elapsed.as_secs_f64()))
+}
+
+fn type_of(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    Ok(Value::String(args[0].type_name().to_string()))
+}
+
+//printと同じ表記の文字列にする
+fn str(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    Ok(Value::String(args[0].to_string()))
+}
+
+//文字列は整数として読めれば整数、そうでなければ浮動小数点数にする
+fn num(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    match &args[0] {
+        Value::Integer(_) | Value::BigInt(_) | Value::Number(_) => Ok(args[0].clone()),
+        Value::String(s) => match (BigInt::parse(s.trim(), 10), s.trim().parse::<f64>()) {
+            (Some(n), _) => Ok(Value::from(n)),
+            (_, Ok(n)) => Ok(Value::Number(n)),
+            _ => Err(NativeError::new(format!("Cannot convert \"{}\" to a number", s)))?,
+        },
+        value => Err(NativeError::new(format!("Cannot convert {} to a number", value.type_name())))?,
+    }
+}
+
+//浮動小数点数は0方向に切り捨てる。無限大とNaNはエラー
+fn int(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    match &args[0] {
+        Value::Integer(_) | Value::BigInt(_) => Ok(args[0].clone()),
+        Value::Number(n) => match BigInt::from_f64(n.trunc()) {
+            Some(n) => Ok(Value::from(n)),
+            None => Err(NativeError::new(format!("Cannot convert {:?} to an int", n)))?,
+        },
+        Value::String(s) => match BigInt::parse(s.trim(), 10) {
+            Some(n) => Ok(Value::from(n)),
+            None => Err(NativeError::new(format!("Cannot convert \"{}\" to an int", s)))?,
+        },
+        value => Err(NativeError::new(format!("Cannot convert {} to an int", value.type_name())))?,
+    }
+}
+
+fn float(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    match &args[0] {
+        Value::String(s) => match s.trim().parse::<f64>() {
+            Ok(n) => Ok(Value::Number(n)),
+            Err(_) => Err(NativeError::new(format!("Cannot convert \"{}\" to a float", s)))?,
+        },
+        value => match value.to_f64() {
+            Some(n) => Ok(Value::Number(n)),
+            None => Err(NativeError::new(format!("Cannot convert {} to a float", value.type_name())))?,
+        },
+    }
+}
+
+//powが丸めずに作る整数の大きさの上限(ビット数)
+const MAX_POW_BITS: u64 = 1 << 18;
+
+//整数の0以上の乗数なら丸めずに計算する。それ以外は浮動小数点数で計算する
+fn pow(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let (base, exponent) = (&args[0], &args[1]);
+
+    match (base.to_bigint(), exponent) {
+        (Some(base), Value::Integer(exponent)) if *exponent >= 0 => {
+            let exponent = u32::try_from(*exponent)
+                .map_err(|_| NativeError::new(format!("Exponent too large: {}", exponent)))?;
+            //0と±1は何乗しても大きくならない
+            if base.bits() > 1 && base.bits() * exponent as u64 > MAX_POW_BITS {
+                Err(NativeError::new(format!("Result too large: more than {} bits", MAX_POW_BITS)))?;
+            }
+            Ok(Value::from(base.pow(exponent)))
+        },
+        _ => match (base.to_f64(), exponent.to_f64()) {
+            (Some(base), Some(exponent)) => Ok(Value::Number(base.powf(exponent))),
+            _ => Err(NativeError::new(format!(
+                "Cannot raise {} to the power of {}",
+                base.type_name(),
+                exponent.type_name(),
+            )))?,
+        },
+    }
+}
+
+//整数をradix進の文字列にする。10より上の桁は小文字
+fn radix(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    let radix = match &args[1] {
+        Value::Integer(radix @ 2..=36) => *radix as u32,
+        value => Err(NativeError::new(format!("Radix must be an int from 2 to 36, got {}", value)))?,
+    };
+
+    match args[0].to_bigint() {
+        Some(n) => Ok(Value::String(n.to_string_radix(radix))),
+        None => Err(NativeError::new(format!("Cannot format {} in a radix", args[0].type_name())))?,
+    }
+}
+
+//文字列の長さは文字数で数える
+fn len(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    match &args[0] {
+        Value::String(s) => Ok(Value::Integer(s.chars().count() as i64)),
+        Value::List(list) => Ok(Value::Integer(list.borrow().len() as i64)),
+        Value::Map(map) => Ok(Value::Integer(map.borrow().len() as i64)),
+        value => Err(NativeError::new(format!("Cannot take the length of {}", value.type_name())))?,
+    }
+}
+
+//startからendの手前までの整数を順に返す反復子
+fn range(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    match (&args[0], &args[1]) {
+        (Value::Integer(start), Value::Integer(end)) => {
+            Ok(interpreter.new_iterator(NativeIterator::Range { next: *start, end: *end }))
+        },
+        (start, end) if start.to_bigint().is_some() && end.to_bigint().is_some() => {
+            Err(NativeError::new(format!("Range bounds are too large: {}, {}", start, end)))?
+        },
+        (start, end) => Err(NativeError::new(
+            format!("Range bounds must be ints, got: {}, {}", start.type_name(), end.type_name())
+        ))?,
+    }
+}
+
+//1行読み込んで改行を除いた文字列を返す。入力が終わっていればnil
+fn input(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, EvalError> {
+    let mut line = String::new();
+    let read = interpreter.read_line(&mut line).map_err(|e| NativeError::new(e.to_string()))?;
+
+    if read == 0 {
+        return Ok(Value::Nil);
+    }
+
+    let trimmed = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(trimmed);
+    Ok(Value::String(line))
+}
@@ -0,0 +1,1199 @@
+use crate::{
+    rloxs_lexer::Lexer,
+    rloxs_parser::parser::Parser,
+    rloxs_resolver::Resolver,
+    rloxs_vm::{Compiler, Vm},
+    test_support::{summary, SharedBuffer},
+};
+
+use super::{value::Value, *};
+
+//エラーは位置を含めた一行の形で返す
+fn test_helper(input: &str) -> Result<Value, String> {
+    let tokens = Lexer::new(input).lex().unwrap();
+    let expr = Parser::new(tokens).parse_expression().unwrap();
+    Interpreter::new().eval_expr(&expr).map_err(|e| summary(&e.diagnostic(), input))
+}
+
+//tree-walkerとVMの両方で、GCを確保のたびに動かす場合も含めて実行し、出力とエラーが一致することを確かめる
+fn run_helper(input: &str) -> Result<String, String> {
+    let (tree_output, tree_result) = run_on_backend(input, false, false);
+
+    for (use_vm, gc_stress) in [(true, false), (false, true), (true, true)] {
+        let (output, result) = run_on_backend(input, use_vm, gc_stress);
+        let label = format!("vm: {}, gc stress: {}", use_vm, gc_stress);
+
+        assert_eq!(tree_output, output, "{} printed different output for:\n{}", label, input);
+        assert_eq!(
+            tree_result.as_ref().map_err(|e| summary(&e.diagnostic(), input)),
+            result.as_ref().map_err(|e| summary(&e.diagnostic(), input)),
+            "{} returned a different result for:\n{}",
+            label,
+            input,
+        );
+    }
+
+    tree_result.map(|_| tree_output).map_err(|e| summary(&e.diagnostic(), input))
+}
+
+fn run_on_backend(input: &str, use_vm: bool, gc_stress: bool) -> (String, Result<(), EvalError>) {
+    let output = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(Box::new(output.clone()));
+    interpreter.heap_mut().set_stress(gc_stress);
+
+    let result = match use_vm {
+        true => {
+            let tokens = Lexer::new(input).lex().unwrap();
+            let program = Parser::new(tokens).parse_program().unwrap();
+            Resolver::new().resolve(&program).unwrap();
+            let script = Compiler::new().compile(&program).unwrap();
+            Vm::new(interpreter).interpret(script)
+        },
+        false => run_with(&mut interpreter, input),
+    };
+
+    (output.take(), result)
+}
+
+fn run_with(interpreter: &mut Interpreter, input: &str) -> Result<(), EvalError> {
+    let tokens = Lexer::new(input).lex().unwrap();
+    let program = Parser::new(tokens).parse_program().unwrap();
+    Resolver::new().resolve(&program).unwrap();
+    interpreter.interpret(&program)
+}
+
+#[test]
+fn eval_arithmetic() {
+    assert_eq!(test_helper("1 + 2").unwrap(), Value::Integer(3));
+    assert_eq!(test_helper("10 - 4 - 3").unwrap(), Value::Integer(3));
+    assert_eq!(test_helper("2 * 3").unwrap(), Value::Integer(6));
+    assert_eq!(test_helper("-(1 + 2) / 2").unwrap(), Value::Number(-1.5));
+}
+
+#[test]
+fn eval_comparison_and_equality() {
+    assert_eq!(test_helper("1 < 2").unwrap(), Value::Bool(true));
+    assert_eq!(test_helper("3 >= 4").unwrap(), Value::Bool(false));
+    assert_eq!(test_helper("1 == 1").unwrap(), Value::Bool(true));
+    assert_eq!(test_helper(r#""a" != "a""#).unwrap(), Value::Bool(false));
+    assert_eq!(test_helper(r#"nil == false"#).unwrap(), Value::Bool(false));
+    assert_eq!(test_helper("!nil").unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn eval_string_concatenation() {
+    assert_eq!(
+        test_helper(r#""Hello, " + "World!""#).unwrap(),
+        Value::String("Hello, World!".to_string())
+    );
+}
+
+#[test]
+fn eval_logical_short_circuit() {
+    assert_eq!(test_helper(r#"nil or "default""#).unwrap(), Value::String("default".to_string()));
+    assert_eq!(test_helper("false and undefined").unwrap(), Value::Bool(false));
+    assert_eq!(test_helper("1 or undefined").unwrap(), Value::Integer(1));
+    assert_eq!(test_helper("true and 2").unwrap(), Value::Integer(2));
+}
+
+#[test]
+fn eval_type_mismatch() {
+    let err = test_helper(r#"1 + "a""#).unwrap_err();
+    assert_eq!(err, "Mismatched types for '+': int, string at [1:2]");
+
+    let err = test_helper(r#"-"a""#).unwrap_err();
+    assert_eq!(err, "Mismatched types for '-': string at [1:0]");
+}
+
+#[test]
+fn eval_undefined_variable() {
+    let err = test_helper("1 + x").unwrap_err();
+    assert_eq!(err, "Undefined variable: x at [1:4]");
+
+    let err = test_helper("y = 1").unwrap_err();
+    assert_eq!(err, "Assignment to undeclared variable: y at [1:0]");
+}
+
+#[test]
+fn run_print_and_let() {
+    let output = run_helper(r#"
+let greeting = "Hello";
+let nothing;
+print greeting + ", World!";
+print nothing;
+print 1.5 * 2;
+"#).unwrap();
+
+    assert_eq!(output, "Hello, World!\nnil\n3.0\n");
+}
+
+#[test]
+fn run_control_flow() {
+    let output = run_helper(r#"
+let n = 3;
+while n > 0 {
+    print n;
+    n = n - 1;
+}
+if n == 0 { print "done"; } else { print "unreachable"; }
+for (let i = 0; i < 2; i = i + 1) {
+    print i;
+}
+"#).unwrap();
+
+    assert_eq!(output, "3\n2\n1\ndone\n0\n1\n");
+}
+
+#[test]
+fn run_block_scopes() {
+    let output = run_helper(r#"
+let a = "global a";
+let b = "global b";
+{
+    let a = "outer a";
+    {
+        let a = "inner a";
+        print a;
+        print b;
+        b = "assigned b";
+    }
+    print a;
+}
+print a;
+print b;
+"#).unwrap();
+
+    assert_eq!(output, "inner a\nglobal b\nouter a\nglobal a\nassigned b\n");
+}
+
+#[test]
+fn run_block_local_is_not_visible_outside() {
+    let err = run_helper("{ let local = 1; } print local;").unwrap_err();
+    assert_eq!(err, "Undefined variable: local at [1:25]");
+
+    let err = run_helper("for (let i = 0; i < 1; i = i + 1) {} print i;").unwrap_err();
+    assert_eq!(err, "Undefined variable: i at [1:43]");
+}
+
+#[test]
+fn run_assign_to_undeclared() {
+    let err = run_helper("{ undeclared = 1; }").unwrap_err();
+    assert_eq!(err, "Assignment to undeclared variable: undeclared at [1:2]");
+}
+
+#[test]
+fn run_scope_is_restored_after_error() {
+    let output = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(Box::new(output.clone()));
+
+    run_with(&mut interpreter, "let x = 1;").unwrap();
+    assert!(run_with(&mut interpreter, "{ let x = 2; print missing; }").is_err());
+    run_with(&mut interpreter, "print x;").unwrap();
+
+    assert_eq!(output.take(), "1\n");
+}
+
+#[test]
+fn run_function_call_and_return() {
+    let output = run_helper(r#"
+fn add(a, b) {
+    return a + b;
+}
+fn greet(name) {
+    print "Hi, " + name;
+}
+print add(1, 2);
+print greet("rloxs");
+print add;
+"#).unwrap();
+
+    assert_eq!(output, "3\nHi, rloxs\nnil\n<fn add>\n");
+}
+
+#[test]
+fn run_recursive_function() {
+    let output = run_helper(r#"
+fn fib(n) {
+    if n < 2 { return n; }
+    return fib(n - 1) + fib(n - 2);
+}
+print fib(15);
+"#).unwrap();
+
+    assert_eq!(output, "610\n");
+}
+
+#[test]
+fn run_return_unwinds_loops() {
+    let output = run_helper(r#"
+fn find_first_over(limit) {
+    for (let i = 0; i < 100; i = i + 1) {
+        while true {
+            if i > limit { return i; }
+            i = i + 1;
+        }
+    }
+}
+print find_first_over(5);
+"#).unwrap();
+
+    assert_eq!(output, "6\n");
+}
+
+//fnとreturnの文はどちらのバックエンドでも実行できる
+#[test]
+fn run_function_declarations_and_bare_return() {
+    let output = run_helper(r#"
+fn sign(n) {
+    if n < 0 { print "negative"; return; }
+    print "non-negative";
+}
+{
+    fn inner() {}
+    print inner();
+}
+print sign(-1);
+print sign(1);
+"#).unwrap();
+
+    assert_eq!(output, "nil\nnegative\nnil\nnon-negative\nnil\n");
+}
+
+#[test]
+fn run_closure_counter() {
+    let output = run_helper(r#"
+fn make_counter() {
+    let count = 0;
+    fn counter() {
+        count = count + 1;
+        return count;
+    }
+    return counter;
+}
+let a = make_counter();
+let b = make_counter();
+print a();
+print a();
+print b();
+"#).unwrap();
+
+    assert_eq!(output, "1\n2\n1\n");
+}
+
+#[test]
+fn run_closures_capture_each_loop_iteration() {
+    let output = run_helper(r#"
+let first;
+let second;
+for (let i = 0; i < 2; i = i + 1) {
+    fn capture() { return i; }
+    if i == 0 { first = capture; } else { second = capture; }
+}
+print first();
+print second();
+"#).unwrap();
+
+    assert_eq!(output, "0\n1\n");
+}
+
+#[test]
+fn run_callback() {
+    let output = run_helper(r#"
+fn twice(f, x) { return f(f(x)); }
+fn inc(x) { return x + 1; }
+print twice(inc, 1);
+"#).unwrap();
+
+    assert_eq!(output, "3\n");
+}
+
+#[test]
+fn run_call_errors() {
+    let err = run_helper("fn f(a) {} f(1, 2);").unwrap_err();
+    assert_eq!(err, "Expected 1 arguments but got 2 for f at [1:12]");
+
+    let err = run_helper(r#""not a function"();"#).unwrap_err();
+    assert_eq!(err, "Can only call functions and classes, got: string at [1:16]");
+}
+
+#[test]
+fn run_class_fields_and_methods() {
+    let output = run_helper(r#"
+class Point {
+    fn init(x, y) {
+        this.x = x;
+        this.y = y;
+    }
+
+    fn sum() {
+        return this.x + this.y;
+    }
+}
+let p = Point(1, 2);
+print p.sum();
+p.x = 10;
+print p.sum();
+print Point;
+print p;
+"#).unwrap();
+
+    assert_eq!(output, "3\n12\n<class Point>\n<Point instance>\n");
+}
+
+#[test]
+fn run_bound_method_keeps_this() {
+    let output = run_helper(r#"
+class Greeter {
+    fn init(name) { this.name = name; }
+    fn greet() { print "Hello, " + this.name; }
+}
+let greet = Greeter("rloxs").greet;
+greet();
+"#).unwrap();
+
+    assert_eq!(output, "Hello, rloxs\n");
+}
+
+#[test]
+fn run_initializer_returns_this() {
+    let output = run_helper(r#"
+class Foo {
+    fn init() {
+        this.value = 1;
+        return;
+    }
+}
+let foo = Foo();
+print foo.init() == foo;
+"#).unwrap();
+
+    assert_eq!(output, "true\n");
+}
+
+#[test]
+fn run_inheritance_and_super() {
+    let output = run_helper(r#"
+class Animal {
+    fn init(name) { this.name = name; }
+    fn speak() { return this.name + " makes a sound"; }
+    fn kind() { return "animal"; }
+}
+class Dog < Animal {
+    fn speak() { return super.speak() + " (woof)"; }
+}
+let dog = Dog("Rex");
+print dog.speak();
+print dog.kind();
+"#).unwrap();
+
+    assert_eq!(output, "Rex makes a sound (woof)\nanimal\n");
+}
+
+#[test]
+fn run_local_class_inheritance() {
+    let output = run_helper(r#"
+{
+    class A { fn get() { return 1; } }
+    class B < A { fn get() { return super.get() + 1; } }
+    let get = B().get;
+    print get();
+}
+"#).unwrap();
+
+    assert_eq!(output, "2\n");
+}
+
+#[test]
+fn run_class_errors() {
+    let err = run_helper("class A {} A().missing;").unwrap_err();
+    assert_eq!(err, "Undefined property: missing at [1:15]");
+
+    let err = run_helper("let a = 1; a.field = 2;").unwrap_err();
+    assert_eq!(err, "Only instances have properties, got: int at [1:13]");
+
+    let err = run_helper("let NotClass = 1; class B < NotClass {}").unwrap_err();
+    assert_eq!(err, "Superclass must be a class, got: int at [1:28]");
+
+    let err = run_helper("class A {} class B < A { fn f() { return super.missing(); } } B().f();").unwrap_err();
+    assert_eq!(err, "Undefined property: missing at [1:47]");
+
+    let err = run_helper("class A { fn init(a) {} } A();").unwrap_err();
+    assert_eq!(err, "Expected 1 arguments but got 0 for A at [1:27]");
+}
+
+#[test]
+fn run_compound_assignment() {
+    let output = run_helper(r#"
+let a = 10;
+a += 5;
+a -= 3;
+a *= 2;
+print a;
+a /= 8;
+print a;
+let s = "ab";
+s += "c";
+print s;
+{
+    let local = 1;
+    fn bump() { local += 1; return local; }
+    bump();
+    print bump();
+}
+print (a = 4) + (a *= 2);
+"#).unwrap();
+
+    assert_eq!(output, "24\n3.0\nabc\n3\n12\n");
+}
+
+#[test]
+fn run_compound_assignment_evaluates_object_once() {
+    let output = run_helper(r#"
+class Counter { fn init() { this.n = 1; } }
+let counter = Counter();
+let calls = 0;
+fn get() { calls += 1; return counter; }
+get().n += 10;
+get().n *= 2;
+print counter.n;
+print calls;
+print get().n -= 2;
+"#).unwrap();
+
+    assert_eq!(output, "22\n2\n20\n");
+
+    let err = run_helper("class A {} A().missing += 1;").unwrap_err();
+    assert_eq!(err, "Undefined property: missing at [1:15]");
+
+    let err = run_helper("class A { fn init() { this.x = nil; } } A().x += 1;").unwrap_err();
+    assert_eq!(err, "Mismatched types for '+': nil, int at [1:46]");
+}
+
+#[test]
+fn run_lists() {
+    let output = run_helper(r#"
+let xs = [1, 2.5, "three", nil, [true]];
+print xs;
+print xs[0] + xs[-5];
+print xs[4][0];
+print xs[-1][-1];
+print [];
+print type(xs);
+print "${[1, "a"]}";
+
+let ys = xs;
+ys[0] = "changed";
+print xs[0];
+print [1] == [1];
+print xs == ys;
+
+xs[4][0] = false;
+print xs[4];
+"#).unwrap();
+
+    assert_eq!(output, "[1, 2.5, \"three\", nil, [true]]\n2\ntrue\ntrue\n[]\nlist\n[1, \"a\"]\nchanged\nfalse\ntrue\n[false]\n");
+}
+
+#[test]
+fn run_list_index_assignment() {
+    let output = run_helper(r#"
+let xs = [1, 2, 3];
+print xs[1] = 20;
+xs[-1] *= 10;
+xs[0] -= 1;
+print xs;
+
+let calls = 0;
+fn list() { calls += 1; return xs; }
+fn index() { calls += 1; return 1; }
+list()[index()] += 1;
+print xs;
+print calls;
+
+let grid = [[0, 0], [0, 0]];
+grid[1][0] = 5;
+grid[1][0] /= 2;
+print grid;
+"#).unwrap();
+
+    assert_eq!(output, "20\n[0, 20, 30]\n[0, 21, 30]\n2\n[[0, 0], [2.5, 0]]\n");
+}
+
+#[test]
+fn run_list_methods() {
+    let output = run_helper(r#"
+let xs = [];
+print xs.push(1);
+xs.push(2);
+xs.push(3);
+print xs.len();
+print xs.pop();
+print xs;
+xs.insert(0, "first");
+xs.insert(-1, "middle");
+xs.insert(xs.len(), "last");
+print xs;
+print xs.remove(1);
+print xs.remove(-1);
+print xs;
+print xs.contains(2);
+print xs.contains(3);
+let ys = [1, 2, 3, 4, 5];
+print ys.slice(1, 3);
+print ys.slice(-2, 5);
+print ys.slice(2, 2);
+print ys.slice(0, ys.len()) == ys;
+let push = ys.push;
+push(6);
+print ys;
+print push;
+print len(ys);
+"#).unwrap();
+
+    assert_eq!(output, concat!(
+        "nil\n3\n3\n[1, 2]\n",
+        "[\"first\", 1, \"middle\", 2, \"last\"]\n",
+        "1\nlast\n[\"first\", \"middle\", 2]\ntrue\nfalse\n",
+        "[2, 3]\n[4, 5]\n[]\nfalse\n",
+        "[1, 2, 3, 4, 5, 6]\n<native fn push>\n6\n",
+    ));
+}
+
+#[test]
+fn run_list_errors() {
+    let cases = [
+        ("[1, 2][2];", "Index 2 out of bounds for list of length 2 at [1:6]"),
+        ("[1, 2][-3];", "Index -3 out of bounds for list of length 2 at [1:6]"),
+        ("let xs = []; xs[0] = 1;", "Index 0 out of bounds for list of length 0 at [1:15]"),
+        ("[1][99999999999999999999];", "Index 99999999999999999999 out of bounds for list of length 1 at [1:3]"),
+        ("[1][\"0\"];", "List index must be an int, got: string at [1:3]"),
+        ("[1][0.0];", "List index must be an int, got: float at [1:3]"),
+        ("let s = \"abc\"; s[0];", "Only lists and maps can be indexed, got: string at [1:16]"),
+        ("[].pop();", "pop: Cannot pop from an empty list at [1:6]"),
+        ("[1].remove(1);", "remove: Index 1 out of bounds for list of length 1 at [1:10]"),
+        ("[1].insert(3, 0);", "insert: Index 3 out of bounds for list of length 1 at [1:10]"),
+        ("[1, 2].slice(2, 1);", "slice: Slice start 2 is after end 1 at [1:12]"),
+        ("[1].remove(nil);", "remove: List index must be an int, got: nil at [1:10]"),
+        ("[].push();", "Expected 1 arguments but got 0 for push at [1:7]"),
+        ("[].missing;", "Undefined property: missing at [1:3]"),
+        ("[].x = 1;", "Only instances have properties, got: list at [1:3]"),
+    ];
+
+    for (input, expected) in cases {
+        let err = run_helper(input).unwrap_err();
+        assert_eq!(err, expected, "{}", input);
+    }
+}
+
+#[test]
+fn run_maps() {
+    let output = run_helper(r#"
+let m = #{"a": 1, "b": [2], 3: "three", nil: true};
+print m;
+print m["a"] + m["b"][0];
+print m[3];
+print m[nil];
+print m["missing"];
+print #{};
+print type(m);
+print "${#{"k": "v"}["k"]}";
+
+m["c"] = 4;
+m["a"] += 10;
+m.remove("b");
+m["e"] = nil;
+print m;
+print len(m);
+print m.contains("e");
+
+let n = m;
+n["d"] = 5;
+print m["d"];
+print #{} == #{};
+print m == n;
+print #{"x": 1, "x": 2, "y": nil};
+"#).unwrap();
+
+    assert_eq!(output, concat!(
+        "#{\"a\": 1, \"b\": [2], 3: \"three\", nil: true}\n3\nthree\ntrue\nnil\n#{}\nmap\nv\n",
+        "#{\"a\": 11, 3: \"three\", nil: true, \"c\": 4, \"e\": nil}\n5\ntrue\n",
+        "5\nfalse\ntrue\n#{\"x\": 2, \"y\": nil}\n",
+    ));
+}
+
+#[test]
+fn run_map_keys() {
+    //==で等しいキーは同じエントリを指す。キーは最初に入れた値のまま残る
+    let output = run_helper(r#"
+let m = #{};
+m[1] = "int";
+m[1.0] = "float";
+m[true] = "bool";
+m["1"] = "string";
+m[0.5] = "half";
+m[-0.0] = "zero";
+m[0] += "!";
+m[99999999999999999999] = "big";
+m[99999999999999999999.0] = "big float";
+print m;
+print m[1];
+print m[0.5];
+print m[1e20] == m[100000000000000000000];
+m[0.0/0.0] = "nan";
+print m[0.0/0.0];
+"#).unwrap();
+
+    assert_eq!(output, concat!(
+        "#{1: \"float\", true: \"bool\", \"1\": \"string\", 0.5: \"half\", -0.0: \"zero!\", ",
+        "99999999999999999999: \"big\", 1e20: \"big float\"}\n",
+        "float\nhalf\ntrue\nnan\n",
+    ));
+}
+
+#[test]
+fn run_map_methods() {
+    let output = run_helper(r#"
+let m = #{"a": 1, "b": 2, "c": 3};
+print m.keys();
+print m.values();
+print m.entries();
+print m.len();
+print m.contains("b");
+print m.contains("z");
+print m.remove("b");
+print m.remove("b");
+print m.keys();
+m["b"] = 4;
+print m.entries();
+
+let total = 0;
+let keys = m.keys();
+for (let i = 0; i < keys.len(); i += 1) {
+    total += m[keys[i]];
+}
+print total;
+"#).unwrap();
+
+    assert_eq!(output, concat!(
+        "[\"a\", \"b\", \"c\"]\n[1, 2, 3]\n[[\"a\", 1], [\"b\", 2], [\"c\", 3]]\n3\ntrue\nfalse\n",
+        "2\nnil\n[\"a\", \"c\"]\n[[\"a\", 1], [\"c\", 3], [\"b\", 4]]\n8\n",
+    ));
+}
+
+#[test]
+fn run_map_removal_keeps_order() {
+    let output = run_helper(r#"
+let m = #{};
+for i in range(0, 10) { m[i] = i * i; }
+for i in range(0, 8) {
+    if (i % 3 != 1) { m.remove(i); }
+}
+m[2] = "back";
+m.remove(9);
+print m;
+print m.len();
+print m[4] + m[7];
+"#).unwrap();
+
+    assert_eq!(output, "#{1: 1, 4: 16, 7: 49, 8: 64, 2: \"back\"}\n5\n65\n");
+}
+
+#[test]
+fn run_map_errors() {
+    let cases = [
+        ("#{[]: 1};", "Map key must be a string, number, bool or nil, got: list at [1:0]"),
+        ("fn f() {} #{\"a\": 1}[f];", "Map key must be a string, number, bool or nil, got: function at [1:19]"),
+        ("let m = #{}; m[m] = 1;", "Map key must be a string, number, bool or nil, got: map at [1:14]"),
+        ("#{}.contains(clock);", "contains: Map key must be a string, number, bool or nil, got: function at [1:12]"),
+        ("#{}[\"a\"] += 1;", "Mismatched types for '+': nil, int at [1:9]"),
+        ("#{}.missing;", "Undefined property: missing at [1:4]"),
+        ("#{}.x = 1;", "Only instances have properties, got: map at [1:4]"),
+    ];
+
+    for (input, expected) in cases {
+        let err = run_helper(input).unwrap_err();
+        assert_eq!(err, expected, "{}", input);
+    }
+}
+
+#[test]
+fn run_for_in() {
+    let output = run_helper(r#"
+for x in [1, nil, "two"] { print x; }
+for k in #{"a": 1, 2: "b"} { print k; }
+for ch in "añ" { print ch; }
+for i in range(0, 3) { print i; }
+for i in range(3, 0) { print i; }
+for x in [] { print x; }
+
+let sum = 0;
+for row in [[1, 2], [3]] {
+    for x in row { sum += x; }
+}
+print sum;
+
+let xs = [1];
+for x in xs {
+    if (x < 3) { xs.push(x + 1); }
+}
+print xs;
+
+let m = #{"a": 1};
+for k in m { m[k + "!"] = 2; }
+print m;
+
+let x = "outer";
+for x in [1] { let x = "shadow"; }
+print x;
+"#).unwrap();
+
+    assert_eq!(output, concat!(
+        "1\nnil\ntwo\na\n2\na\nñ\n0\n1\n2\n",
+        "6\n[1, 2, 3]\n#{\"a\": 1, \"a!\": 2}\nouter\n",
+    ));
+}
+
+#[test]
+fn run_for_in_protocol() {
+    //スクリプトのクラスも組み込みの反復子と同じ約束で反復できる
+    let output = run_helper(r#"
+class Countdown {
+    fn init(n) { this.n = n; }
+    fn iter() { return CountdownIterator(this.n); }
+}
+class CountdownIterator {
+    fn init(n) { this.n = n; }
+    fn has_next() { return this.n > 0; }
+    fn next() { this.n -= 1; return this.n + 1; }
+}
+let countdown = Countdown(3);
+for n in countdown { print n; }
+for n in countdown { print n; }
+
+let it = [7, 8, 9].iter();
+print it.next();
+print it.iter() == it;
+for x in it { print x; }
+print it.has_next();
+print it;
+print type(range(0, 1));
+
+let fs = [];
+for i in range(0, 3) {
+    fn get() { return i; }
+    fs.push(get);
+}
+for f in fs { print f(); }
+
+fn first_even(xs) {
+    for x in xs {
+        if (x % 2 == 0) { return x; }
+    }
+    return nil;
+}
+print first_even([1, 3, 4, 6]);
+"#).unwrap();
+
+    assert_eq!(output, concat!(
+        "3\n2\n1\n3\n2\n1\n",
+        "7\ntrue\n8\n9\nfalse\n<iterator>\niterator\n",
+        "0\n1\n2\n4\n",
+    ));
+}
+
+#[test]
+fn run_for_in_errors() {
+    let cases = [
+        ("for x in 3 {}", "Only values with an iter() method can be iterated, got: int at [1:9]"),
+        ("class A {} for x in A() {}", "Only values with an iter() method can be iterated, got: instance at [1:20]"),
+        ("class A { fn iter() { return 1; } } for x in A() {}", "Only instances have properties, got: int at [1:45]"),
+        ("class A { fn iter() { return this; } } for x in A() {}", "Undefined property: has_next at [1:48]"),
+        ("let it = [].iter(); it.next();", "next: Iterator is exhausted at [1:27]"),
+        ("range(0, 1.5);", "range: Range bounds must be ints, got: int, float at [1:5]"),
+        ("range(0, 99999999999999999999);", "range: Range bounds are too large: 0, 99999999999999999999 at [1:5]"),
+        ("\"\".missing;", "Undefined property: missing at [1:3]"),
+    ];
+
+    for (input, expected) in cases {
+        let err = run_helper(input).unwrap_err();
+        assert_eq!(err, expected, "{}", input);
+    }
+}
+
+#[test]
+fn run_closure_ignores_later_shadowing() {
+    let output = run_helper(r#"
+let a = "global";
+{
+    fn show() { print a; }
+    show();
+    let a = "block";
+    show();
+    print a;
+}
+"#).unwrap();
+
+    assert_eq!(output, "global\nglobal\nblock\n");
+}
+
+//インスタンスのフィールドに自身を捕捉した関数を入れて循環を作る
+const CYCLE_PROGRAM: &str = r#"
+class Node {}
+fn make() {
+    let node = Node();
+    fn get() { return node; }
+    node.get = get;
+}
+for (let i = 0; i < 10; i = i + 1) { make(); }
+"#;
+
+#[test]
+fn gc_collects_cycles_in_tree_walker() {
+    let mut interpreter = Interpreter::with_output(Box::new(SharedBuffer::default()));
+    run_with(&mut interpreter, CYCLE_PROGRAM).unwrap();
+    interpreter.collect_garbage();
+
+    let stats = interpreter.heap().stats();
+    assert_eq!(stats.collections, 1);
+    //インスタンス、関数、関数を宣言したスコープの3つが1回のmakeごとに残る
+    assert_eq!(stats.objects_freed, 30, "{}", stats);
+    assert!(stats.bytes_freed > 0);
+}
+
+#[test]
+fn gc_collects_cycles_in_vm() {
+    let tokens = Lexer::new(CYCLE_PROGRAM).lex().unwrap();
+    let program = Parser::new(tokens).parse_program().unwrap();
+    let script = Compiler::new().compile(&program).unwrap();
+
+    let mut vm = Vm::new(Interpreter::with_output(Box::new(SharedBuffer::default())));
+    vm.interpret(script).unwrap();
+    vm.collect_garbage();
+
+    let stats = vm.host().heap().stats();
+    assert_eq!(stats.collections, 1);
+    //インスタンス、クロージャ、捕捉されたupvalueの3つが1回のmakeごとに残る
+    assert_eq!(stats.objects_freed, 30, "{}", stats);
+}
+
+#[test]
+fn gc_keeps_reachable_objects() {
+    let output = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(Box::new(output.clone()));
+    run_with(&mut interpreter, CYCLE_PROGRAM).unwrap();
+    run_with(&mut interpreter, "let kept = Node(); fn get() { return kept; } kept.get = get;").unwrap();
+
+    interpreter.collect_garbage();
+    interpreter.collect_garbage();
+    run_with(&mut interpreter, "print kept.get() == kept;").unwrap();
+
+    assert_eq!(output.take(), "true\n");
+}
+
+#[test]
+fn gc_collects_list_cycles() {
+    let source = r#"
+fn make() {
+    let xs = [];
+    xs.push(xs);
+    xs.push(xs.len);
+}
+for (let i = 0; i < 10; i = i + 1) { make(); }
+"#;
+
+    let mut interpreter = Interpreter::with_output(Box::new(SharedBuffer::default()));
+    run_with(&mut interpreter, source).unwrap();
+    interpreter.collect_garbage();
+    //自分を含むリストと、それを束縛したメソッドの2つが1回のmakeごとに残る
+    assert_eq!(interpreter.heap().stats().objects_freed, 20, "{}", interpreter.heap().stats());
+
+    let program = Parser::new(Lexer::new(source).lex().unwrap()).parse_program().unwrap();
+    let script = Compiler::new().compile(&program).unwrap();
+    let mut vm = Vm::new(Interpreter::with_output(Box::new(SharedBuffer::default())));
+    vm.interpret(script).unwrap();
+    vm.collect_garbage();
+    assert_eq!(vm.host().heap().stats().objects_freed, 20, "{}", vm.host().heap().stats());
+}
+
+#[test]
+fn gc_collects_map_cycles() {
+    let source = r#"
+fn make() {
+    let m = #{};
+    m["self"] = m;
+    m["keys"] = m.keys;
+}
+for (let i = 0; i < 10; i = i + 1) { make(); }
+"#;
+
+    let mut interpreter = Interpreter::with_output(Box::new(SharedBuffer::default()));
+    run_with(&mut interpreter, source).unwrap();
+    interpreter.collect_garbage();
+    //自分を含むマップと、それを束縛したメソッドの2つが1回のmakeごとに残る
+    assert_eq!(interpreter.heap().stats().objects_freed, 20, "{}", interpreter.heap().stats());
+
+    let program = Parser::new(Lexer::new(source).lex().unwrap()).parse_program().unwrap();
+    let script = Compiler::new().compile(&program).unwrap();
+    let mut vm = Vm::new(Interpreter::with_output(Box::new(SharedBuffer::default())));
+    vm.interpret(script).unwrap();
+    vm.collect_garbage();
+    assert_eq!(vm.host().heap().stats().objects_freed, 20, "{}", vm.host().heap().stats());
+}
+
+#[test]
+fn gc_collects_iterator_cycles() {
+    let source = r#"
+fn make() {
+    let xs = [];
+    xs.push(xs.iter());
+}
+for (let i = 0; i < 10; i = i + 1) { make(); }
+"#;
+
+    let mut interpreter = Interpreter::with_output(Box::new(SharedBuffer::default()));
+    run_with(&mut interpreter, source).unwrap();
+    interpreter.collect_garbage();
+    //リストと、そのリストを辿る反復子の2つが1回のmakeごとに残る
+    assert_eq!(interpreter.heap().stats().objects_freed, 20, "{}", interpreter.heap().stats());
+}
+
+#[test]
+fn gc_runs_when_threshold_is_exceeded() {
+    let mut interpreter = Interpreter::with_output(Box::new(SharedBuffer::default()));
+    run_with(&mut interpreter, &CYCLE_PROGRAM.replace("i < 10", "i < 5000")).unwrap();
+
+    let stats = interpreter.heap().stats();
+    assert!(stats.collections > 0);
+    //閾値は生き残った量に合わせて伸びるので回収は毎回は起きない
+    assert!(stats.collections < 100, "{}", stats);
+    assert!(interpreter.heap().bytes_allocated() < 1024 * 1024 * 2);
+}
+
+
+#[test]
+fn run_prelude_functions() {
+    let output = run_helper(r#"
+print type(1);
+print type("a");
+print type(nil);
+print type(clock);
+print type(clock());
+print str(1.5) + "!";
+print num(" 42 ") + 1;
+print len("héllo");
+print clock;
+"#).unwrap();
+
+    assert_eq!(output, "int\nstring\nnil\nfunction\nfloat\n1.5!\n43\n5\n<native fn clock>\n");
+}
+
+#[test]
+fn run_integer_arithmetic() {
+    let output = run_helper(r#"
+print 3;
+print 3.0;
+print 7 / 2;
+print 7 ~/ 2;
+print -7 ~/ 2;
+print 7 % 3;
+print -7 % 3;
+print 7.5 % 2;
+print 1 + 0.5;
+print 2 == 2.0;
+print 9007199254740993 == 9007199254740992.0;
+print 9007199254740993 - 1;
+print 3 < 3.5;
+"#).unwrap();
+
+    assert_eq!(output, "3\n3.0\n3.5\n3\n-3\n1\n-1\n1.5\n1.5\ntrue\nfalse\n9007199254740992\ntrue\n");
+}
+
+#[test]
+fn run_bigint_promotion() {
+    //i64からあふれた値はBigIntになり、収まればIntegerに戻る
+    let output = run_helper(r#"
+let max = 9223372036854775807;
+print max + 1;
+print type(max + 1);
+print (max + 1) - 1 == max;
+print -(-max - 1);
+print max * max;
+print (max * max) ~/ max;
+print (max * max) % 1000;
+print 340282366920938463463374607431768211456 > max;
+print 18446744073709551616 == 18446744073709551616.0;
+print 18446744073709551616 / 2;
+"#).unwrap();
+
+    assert_eq!(output, concat!(
+        "9223372036854775808\n",
+        "int\n",
+        "true\n",
+        "9223372036854775808\n",
+        "85070591730234615847396907784232501249\n",
+        "9223372036854775807\n",
+        "249\n",
+        "true\n",
+        "true\n",
+        "9.223372036854776e18\n",
+    ));
+
+    let err = run_helper("print 18446744073709551616 % 0;").unwrap_err();
+    assert_eq!(err, "Integer division by zero at [1:27]");
+}
+
+#[test]
+fn run_pow_and_radix() {
+    let output = run_helper(r#"
+print pow(2, 100);
+print pow(-3, 3);
+print pow(2, -1);
+print pow(2.5, 2);
+print radix(255, 16);
+print radix(-10, 2);
+print radix(pow(2, 64), 36);
+print int("123456789012345678901234567890") + 1;
+print int(1e20);
+"#).unwrap();
+
+    assert_eq!(output, concat!(
+        "1267650600228229401496703205376\n",
+        "-27\n",
+        "0.5\n",
+        "6.25\n",
+        "ff\n",
+        "-1010\n",
+        "3w5e11264sgsg\n",
+        "123456789012345678901234567891\n",
+        "100000000000000000000\n",
+    ));
+
+    let err = run_helper("radix(10, 1);").unwrap_err();
+    assert_eq!(err, "radix: Radix must be an int from 2 to 36, got 1 at [1:5]");
+
+    let err = run_helper("pow(2, 10000000000);").unwrap_err();
+    assert_eq!(err, "pow: Exponent too large: 10000000000 at [1:3]");
+
+    let err = run_helper("pow(2, 4000000000);").unwrap_err();
+    assert_eq!(err, "pow: Result too large: more than 262144 bits at [1:3]");
+
+    let output = run_helper("print pow(-1, 4000000001); print pow(0, 4000000000);").unwrap();
+    assert_eq!(output, "-1\n0\n");
+}
+
+#[test]
+fn run_integer_errors() {
+    let err = run_helper("print 1 ~/ 0;").unwrap_err();
+    assert_eq!(err, "Integer division by zero at [1:8]");
+
+    let err = run_helper("print 1 % 0;").unwrap_err();
+    assert_eq!(err, "Integer division by zero at [1:8]");
+
+    //浮動小数点数の除算はエラーにならない
+    assert_eq!(run_helper("print 1 / 0;").unwrap(), "inf\n");
+}
+
+#[test]
+fn run_number_conversions() {
+    let output = run_helper(r#"
+print int(3.9);
+print int(-3.9);
+print int(" 12 ");
+print float(3);
+print float("2.5");
+print num("7");
+print num("7.0");
+"#).unwrap();
+
+    assert_eq!(output, "3\n-3\n12\n3.0\n2.5\n7\n7.0\n");
+
+    let err = run_helper("int(1 / 0);").unwrap_err();
+    assert_eq!(err, "int: Cannot convert inf to an int at [1:3]");
+
+    let err = run_helper(r#"int("1.5");"#).unwrap_err();
+    assert_eq!(err, r#"int: Cannot convert "1.5" to an int at [1:3]"#);
+}
+
+#[test]
+fn run_native_errors() {
+    let err = run_helper(r#"num("abc");"#).unwrap_err();
+    assert_eq!(err, r#"num: Cannot convert "abc" to a number at [1:3]"#);
+
+    let err = run_helper("len(1);").unwrap_err();
+    assert_eq!(err, "len: Cannot take the length of int at [1:3]");
+
+    let err = run_helper("type();").unwrap_err();
+    assert_eq!(err, "Expected 1 arguments but got 0 for type at [1:4]");
+}
+
+#[test]
+fn run_input_on_both_backends() {
+    let program = r#"let name = input(); print "hello " + name; print input(); print input();"#;
+
+    for use_vm in [false, true] {
+        let output = SharedBuffer::default();
+        let input = std::io::Cursor::new("rloxs\r\nlast");
+        let mut interpreter = Interpreter::with_io(Some(Box::new(input)), Box::new(output.clone()));
+
+        let tokens = Lexer::new(program).lex().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        Resolver::new().resolve(&program).unwrap();
+        match use_vm {
+            true => Vm::new(interpreter).interpret(Compiler::new().compile(&program).unwrap()).unwrap(),
+            false => interpreter.interpret(&program).unwrap(),
+        }
+
+        assert_eq!(output.take(), "hello rloxs\nlast\nnil\n");
+    }
+}
+
+fn native_add(_: &mut Interpreter, args: &[Value]) -> Result<Value, EvalError> {
+    match (&args[0], &args[1]) {
+        (Value::Integer(l), Value::Integer(r)) => Ok(Value::Integer(l + r)),
+        _ => Err(errors::NativeError::new("expected numbers".to_string()))?,
+    }
+}
+
+#[test]
+fn run_user_defined_native() {
+    let output = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(Box::new(output.clone()));
+    interpreter.define_native("add", 2, native_add);
+
+    run_with(&mut interpreter, "print add(1, 2);").unwrap();
+    let source = "\n  add(1, nil);";
+    let err = run_with(&mut interpreter, source).unwrap_err();
+    assert_eq!(summary(&err.diagnostic(), source), "add: expected numbers at [2:5]");
+
+    assert_eq!(output.take(), "3\n");
+}
+
+#[test]
+fn run_string_interpolation() {
+    let output = run_helper(r#"
+let name = "rloxs";
+let count = 2;
+class Box {}
+fn greet(who) { return "hi ${who}"; }
+print "Hello ${name}, you have ${count + 1} items";
+print "${nil} ${true} ${1.5} ${Box} ${greet}";
+print "nested ${greet("${name}!")} and ${"}"} ${"${"${count}"}"}";
+print "\${literal} $ {x} ${""}";
+"#).unwrap();
+
+    assert_eq!(output, concat!(
+        "Hello rloxs, you have 3 items\n",
+        "nil true 1.5 <class Box> <fn greet>\n",
+        "nested hi rloxs! and } 2\n",
+        "${literal} $ {x} \n",
+    ));
+}
+
+#[test]
+fn run_interpolation_error_position() {
+    let err = run_helper("let a = 1;\nprint \"a is ${a}, b is ${\n  a + nil}\";").unwrap_err();
+    assert_eq!(err, "Mismatched types for '+': int, nil at [3:4]");
+
+    let err = run_helper(r#"print "${missing}";"#).unwrap_err();
+    assert_eq!(err, "Undefined variable: missing at [1:9]");
+}
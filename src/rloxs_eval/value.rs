@@ -0,0 +1,208 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{bignum::BigInt, rloxs_vm::object::{BoundMethod, Closure}, syntax::token::LiteralKind};
+
+use super::{
+    class::{Class, Instance},
+    function::Function,
+    iter::NativeIterator,
+    map::Map,
+    native::{BoundNative, NativeFunction},
+};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Integer(i64),
+    //i64に収まらない整数。収まる値は常にIntegerで持つ
+    BigInt(Rc<BigInt>),
+    Number(f64),
+    String(String),
+    Function(Rc<Function>),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+    //VMで作られる関数値
+    Closure(Rc<Closure>),
+    BoundMethod(Rc<BoundMethod>),
+    Native(Rc<NativeFunction>),
+    BoundNative(Rc<BoundNative>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<Map>>),
+    //リストや文字列のiter()、rangeが返す組み込みの反復子
+    Iterator(Rc<RefCell<NativeIterator>>),
+}
+
+impl Value {
+    //nilとfalseのみが偽
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "bool",
+            Value::Integer(_) | Value::BigInt(_) => "int",
+            Value::Number(_) => "float",
+            Value::String(_) => "string",
+            Value::Function(_)
+            | Value::Closure(_)
+            | Value::BoundMethod(_)
+            | Value::Native(_)
+            | Value::BoundNative(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Iterator(_) => "iterator",
+        }
+    }
+
+    //整数ならBigIntとして取り出す
+    pub fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Value::Integer(n) => Some(BigInt::from(*n)),
+            Value::BigInt(n) => Some((**n).clone()),
+            _ => None,
+        }
+    }
+
+    //数値ならf64として取り出す。大きな整数は丸められる
+    pub fn to_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(n) => Some(*n as f64),
+            Value::BigInt(n) => Some(n.to_f64()),
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Integer(l), Value::Integer(r)) => l == r,
+            (Value::BigInt(l), Value::BigInt(r)) => l == r,
+            (Value::Number(l), Value::Number(r)) => l == r,
+            //整数と浮動小数点数は丸めずに同じ数のときだけ等しい
+            (Value::Integer(i), Value::Number(n)) | (Value::Number(n), Value::Integer(i)) => {
+                n.fract() == 0.0 && *n as i128 == *i as i128
+            },
+            (Value::BigInt(b), Value::Number(n)) | (Value::Number(n), Value::BigInt(b)) => {
+                BigInt::from_f64(*n).is_some_and(|n| n == **b)
+            },
+            (Value::String(l), Value::String(r)) => l == r,
+            //関数・クラス・インスタンス・リスト・マップ・反復子は同一の値のときだけ等しい
+            (Value::Function(l), Value::Function(r)) => Rc::ptr_eq(l, r),
+            (Value::Class(l), Value::Class(r)) => Rc::ptr_eq(l, r),
+            (Value::Instance(l), Value::Instance(r)) => Rc::ptr_eq(l, r),
+            (Value::Closure(l), Value::Closure(r)) => Rc::ptr_eq(l, r),
+            (Value::BoundMethod(l), Value::BoundMethod(r)) => Rc::ptr_eq(l, r),
+            (Value::Native(l), Value::Native(r)) => Rc::ptr_eq(l, r),
+            (Value::BoundNative(l), Value::BoundNative(r)) => Rc::ptr_eq(l, r),
+            (Value::List(l), Value::List(r)) => Rc::ptr_eq(l, r),
+            (Value::Map(l), Value::Map(r)) => Rc::ptr_eq(l, r),
+            (Value::Iterator(l), Value::Iterator(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl From<&LiteralKind> for Value {
+    fn from(value: &LiteralKind) -> Self {
+        match value {
+            LiteralKind::Nil => Value::Nil,
+            LiteralKind::Bool(b) => Value::Bool(*b),
+            LiteralKind::Integer(n) => Value::Integer(*n),
+            LiteralKind::BigInt(n) => Value::from(n.clone()),
+            LiteralKind::Number(n) => Value::Number(*n),
+            LiteralKind::String(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+//整数演算の結果はi64に収まればIntegerに戻す
+impl From<BigInt> for Value {
+    fn from(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(n) => Value::Integer(n),
+            None => Value::BigInt(Rc::new(value)),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self, &mut vec![])
+    }
+}
+
+//リストとマップは自分自身を含み得るので、表示中のものにもう一度出会ったら[...]や#{...}にする
+fn write_value(f: &mut fmt::Formatter<'_>, value: &Value, visiting: &mut Vec<*const ()>) -> fmt::Result {
+    match value {
+        Value::List(list) => {
+            let address = Rc::as_ptr(list) as *const ();
+            let Ok(elements) = list.try_borrow() else { return write!(f, "[...]") };
+            if visiting.contains(&address) {
+                return write!(f, "[...]");
+            }
+
+            visiting.push(address);
+            write!(f, "[")?;
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write_element(f, element, visiting)?;
+            }
+            visiting.pop();
+            write!(f, "]")
+        },
+        Value::Map(map) => {
+            let address = Rc::as_ptr(map) as *const ();
+            let Ok(map) = map.try_borrow() else { return write!(f, "#{{...}}") };
+            if visiting.contains(&address) {
+                return write!(f, "#{{...}}");
+            }
+
+            visiting.push(address);
+            write!(f, "#{{")?;
+            for (i, (key, value)) in map.entries().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write_element(f, key, visiting)?;
+                write!(f, ": ")?;
+                write_element(f, value, visiting)?;
+            }
+            visiting.pop();
+            write!(f, "}}")
+        },
+        Value::Nil => write!(f, "nil"),
+        Value::Bool(b) => write!(f, "{}", b),
+        Value::Integer(n) => write!(f, "{}", n),
+        Value::BigInt(n) => write!(f, "{}", n),
+        //整数と区別できるように3.0のような表記にする
+        Value::Number(n) => write!(f, "{:?}", n),
+        Value::String(s) => write!(f, "{}", s),
+        Value::Function(func) => write!(f, "<fn {}>", func.name()),
+        Value::Class(class) => write!(f, "<class {}>", class.name),
+        Value::Instance(instance) => write!(f, "<{} instance>", instance.borrow().class.name),
+        Value::Closure(closure) => write!(f, "<fn {}>", closure.function.name),
+        Value::BoundMethod(bound) => write!(f, "<fn {}>", bound.method.function.name),
+        Value::Native(native) => write!(f, "<native fn {}>", native.name),
+        Value::BoundNative(bound) => write!(f, "<native fn {}>", bound.native.name),
+        Value::Iterator(_) => write!(f, "<iterator>"),
+    }
+}
+
+//リストやマップの中の文字列は区別できるように引用符を付ける
+fn write_element(f: &mut fmt::Formatter<'_>, value: &Value, visiting: &mut Vec<*const ()>) -> fmt::Result {
+    match value {
+        Value::String(s) => write!(f, "{:?}", s),
+        value => write_value(f, value, visiting),
+    }
+}
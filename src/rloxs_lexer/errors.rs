@@ -1,39 +1,157 @@
-use std::{error::Error, fmt::Display, num::ParseFloatError};
+use std::{error::Error, fmt::Display};
+
+use crate::{diagnostics::Diagnostic, syntax::Span};
 
 #[derive(Debug)]
 pub struct UnexpectedChar {
     ch: char,
-    line: usize,
-    column: usize,
+    span: Span,
 }
 
 impl UnexpectedChar {
-    pub fn new(ch: char, line: usize, column: usize) -> Self {
-        Self { ch, line, column }
+    pub fn new(ch: char, span: Span) -> Self {
+        Self { ch, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(format!("Unexpected character: {}", self.ch), self.span)
     }
 }
 
 impl Display for UnexpectedChar {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Unexpected character: {} at [{}:{}]", self.ch, self.line, self.column)
+        write!(f, "{}", self.diagnostic())
     }
 }
 
 impl Error for UnexpectedChar {}
 
+#[derive(Debug)]
+pub struct InvalidNumber {
+    literal: String,
+    span: Span,
+}
+
+impl InvalidNumber {
+    pub fn new(literal: String, span: Span) -> Self {
+        Self { literal, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(format!("Invalid number literal: {}", self.literal), self.span)
+    }
+}
+
+impl Display for InvalidNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl Error for InvalidNumber {}
+
+//位置はエスケープの\の位置を指す
+#[derive(Debug)]
+pub struct InvalidEscape {
+    sequence: String,
+    span: Span,
+}
+
+impl InvalidEscape {
+    pub fn new(sequence: String, span: Span) -> Self {
+        Self { sequence, span }
+    }
 
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(format!("Invalid escape sequence: {}", self.sequence), self.span)
+    }
+}
+
+impl Display for InvalidEscape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl Error for InvalidEscape {}
+
+//位置は文字列の開始の"を指す
+#[derive(Debug)]
+pub struct UnterminatedString {
+    span: Span,
+}
+
+impl UnterminatedString {
+    pub fn new(span: Span) -> Self {
+        Self { span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new("Unterminated string".to_string(), self.span)
+    }
+}
+
+impl Display for UnterminatedString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl Error for UnterminatedString {}
+
+//位置は一番外側の/*を指す
+#[derive(Debug)]
+pub struct UnterminatedComment {
+    span: Span,
+}
+
+impl UnterminatedComment {
+    pub fn new(span: Span) -> Self {
+        Self { span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new("Unterminated block comment".to_string(), self.span)
+    }
+}
+
+impl Display for UnterminatedComment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl Error for UnterminatedComment {}
 
 #[derive(Debug)]
 pub enum LexerError {
     UnexpectedChar(UnexpectedChar),
-    ParseFloatError(ParseFloatError),
+    InvalidNumber(InvalidNumber),
+    UnterminatedString(UnterminatedString),
+    UnterminatedComment(UnterminatedComment),
+    InvalidEscape(InvalidEscape),
+}
+
+impl LexerError {
+    pub fn diagnostic(&self) -> Diagnostic {
+        match self {
+            LexerError::UnexpectedChar(e) => e.diagnostic(),
+            LexerError::InvalidNumber(e) => e.diagnostic(),
+            LexerError::UnterminatedString(e) => e.diagnostic(),
+            LexerError::UnterminatedComment(e) => e.diagnostic(),
+            LexerError::InvalidEscape(e) => e.diagnostic(),
+        }
+    }
 }
 
 impl Display for LexerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             LexerError::UnexpectedChar(e) => write!(f, "{}", e),
-            LexerError::ParseFloatError(e) => write!(f, "{}", e),
+            LexerError::InvalidNumber(e) => write!(f, "{}", e),
+            LexerError::UnterminatedString(e) => write!(f, "{}", e),
+            LexerError::UnterminatedComment(e) => write!(f, "{}", e),
+            LexerError::InvalidEscape(e) => write!(f, "{}", e),
         }
     }
 }
@@ -44,8 +162,26 @@ impl From<UnexpectedChar> for LexerError {
     }
 }
 
-impl From<ParseFloatError> for LexerError {
-    fn from(value: ParseFloatError) -> Self {
-        LexerError::ParseFloatError(value)
+impl From<InvalidNumber> for LexerError {
+    fn from(value: InvalidNumber) -> Self {
+        LexerError::InvalidNumber(value)
+    }
+}
+
+impl From<UnterminatedString> for LexerError {
+    fn from(value: UnterminatedString) -> Self {
+        LexerError::UnterminatedString(value)
+    }
+}
+
+impl From<UnterminatedComment> for LexerError {
+    fn from(value: UnterminatedComment) -> Self {
+        LexerError::UnterminatedComment(value)
+    }
+}
+
+impl From<InvalidEscape> for LexerError {
+    fn from(value: InvalidEscape) -> Self {
+        LexerError::InvalidEscape(value)
     }
 }
\ No newline at end of file
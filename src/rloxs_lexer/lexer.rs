@@ -1,13 +1,25 @@
-use crate::{rloxs_lexer::errors::UnexpectedChar, syntax::token::{LiteralKind, Token, TokenKind}};
+use crate::{
+    bignum::BigInt,
+    rloxs_lexer::errors::UnexpectedChar,
+    syntax::{token::{LiteralKind, Token, TokenKind, TokenWithTrivia, Trivia, TriviaKind}, Span},
+};
 
-use super::errors::LexerError;
+use super::errors::{InvalidEscape, InvalidNumber, LexerError, UnterminatedComment, UnterminatedString};
+
+//読み途中の文字列補間。${の中で開いた{の数と、文字列の開始位置を持つ
+struct Interpolation {
+    braces: usize,
+    start: usize,
+}
 
 pub struct Lexer {
     input: Vec<char>,
     tokens: Vec<Token>,
+    errors: Vec<LexerError>,
+    interpolations: Vec<Interpolation>,
     pos: usize,
-    line: usize,
-    column: usize,
+    //posまでのバイト数。Token::spanに使う
+    offset: usize,
 }
 
 impl Lexer {
@@ -15,23 +27,108 @@ impl Lexer {
         Self {
             input: input.chars().collect::<Vec<char>>(),
             tokens: vec![],
+            errors: vec![],
+            interpolations: vec![],
             pos: 0,
-            line: 1,
-            column: 0,
+            offset: 0,
+        }
+    }
+
+    //入力がもっと大きなソースのoffsetバイト目から始まるものとしてspanを付ける。
+    //REPLで前の行までをつないだソースの位置を指すのに使う
+    pub fn starting_at(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    //字句エラーがないことを確かめてトークン列だけを使うテスト用
+    #[cfg(test)]
+    pub fn lex(&mut self) -> Result<Vec<Token>, Vec<LexerError>> {
+        let (tokens, errors) = self.tokenize();
+
+        match errors.is_empty() {
+            true => Ok(tokens),
+            false => Err(errors),
         }
     }
 
-    pub fn lex(&mut self) -> Result<Vec<Token>, LexerError> {
+    //エラーがあってもTokenKind::Errorを置いて最後まで読み進める
+    pub fn tokenize(&mut self) -> (Vec<Token>, Vec<LexerError>) {
         while !self.is_at_end() {
-            let token = self.next_token()?;
-            self.tokens.push(token);
+            let token = self.next_token();
+            //末尾の空白やコメントを読み飛ばして終端に着いたときのEofは下で1つだけ追加する
+            if token.token_kind != TokenKind::Eof {
+                self.tokens.push(token);
+            }
+        }
+
+        //閉じられないまま終わった文字列補間
+        for interpolation in std::mem::take(&mut self.interpolations) {
+            let span = Span::new(interpolation.start, self.offset);
+            self.error(UnterminatedString::new(span));
         }
 
         //Token::Eofを位置付きで追加するためもう一回呼ぶ
-        let token = self.next_token()?;
+        let token = self.next_token();
         self.tokens.push(token);
 
-        Ok(self.tokens.to_owned())
+        (std::mem::take(&mut self.tokens), std::mem::take(&mut self.errors))
+    }
+
+    //空白とコメントを捨てずにトークンのトリビアとして残すモード。CSTを作るときに使う
+    //コメントはトークンとしては返さない
+    pub fn tokenize_with_trivia(&mut self) -> (Vec<TokenWithTrivia>, Vec<LexerError>) {
+        let source = self.input.iter().collect::<String>();
+        let (tokens, errors) = self.tokenize();
+
+        let mut result: Vec<TokenWithTrivia> = vec![];
+        let mut pending = vec![];
+        let mut end = 0;
+
+        for token in tokens {
+            //トークンの間にはskip_whitespaceで読み飛ばした空白しかない
+            if token.span.start > end {
+                let span = Span::new(end, token.span.start);
+                pending.push(Trivia { kind: TriviaKind::Whitespace, text: source[span.start..span.end].to_string(), span });
+            }
+            end = token.span.end;
+            let text = source[token.span.start..token.span.end].to_string();
+
+            let comment = match token.token_kind {
+                TokenKind::LineComment => Some(TriviaKind::LineComment),
+                TokenKind::BlockComment => Some(TriviaKind::BlockComment),
+                TokenKind::DocComment(_) => Some(TriviaKind::DocComment),
+                _ => None,
+            };
+
+            match comment {
+                Some(kind) => pending.push(Trivia { kind, text, span: token.span }),
+                None => {
+                    let leading = match result.last_mut() {
+                        Some(previous) => split_trailing(&mut previous.trailing, pending),
+                        None => pending,
+                    };
+                    pending = vec![];
+                    result.push(TokenWithTrivia { token, text, leading, trailing: vec![] });
+                },
+            }
+        }
+
+        (result, errors)
+    }
+
+    fn error(&mut self, error: impl Into<LexerError>) -> TokenKind {
+        self.errors.push(error.into());
+        TokenKind::Error
+    }
+
+    //補間の中で閉じられなかった文字列は、外側の文字列も閉じられていないので、
+    //tokenizeの最後に外側の文字列のエラーとしてだけ報告する
+    fn unterminated_string(&mut self, start: usize) -> TokenKind {
+        match self.interpolations.is_empty() {
+            true => self.error(UnterminatedString::new(Span::new(start, self.offset))),
+            false => TokenKind::Error,
+        }
     }
 
     fn is_at_end(&self) -> bool {
@@ -47,12 +144,17 @@ impl Lexer {
         }
     }
 
+    //現在位置からn文字先の文字を返却するメソッド
+    fn peek_nth_char(&self, n: usize) -> Option<char> {
+        self.input.get(self.pos + n).copied()
+    }
+
     //現在の文字を返却し、位置を進めておくメソッド
     fn next_char(&mut self) -> Option<char> {
         if !self.is_at_end() {
             let ch = self.input[self.pos];
             self.pos += 1;
-            self.column += 1;
+            self.offset += ch.len_utf8();
             Some(ch)
         }else {
             None
@@ -60,29 +162,8 @@ impl Lexer {
     }
 
     fn skip_whitespace(&mut self) {
-        while let Some(ch) = self.peek_char() {
-            if !ch.is_whitespace() {
-                break;
-            }
-
-            if ch == '\n' {
-                self.next_char();
-
-                self.line += 1;
-                self.column = 0;
-
-            } else if ch == '\r' {
-                self.next_char();
-
-                //二回目のnext_char()は境界チェックができないためif let 文
-                if let Some('\n') = self.peek_char() {
-                    self.next_char();
-                    self.line += 1;
-                    self.column = 0;
-                }
-            }else {
-                self.next_char();
-            }
+        while self.peek_char().is_some_and(char::is_whitespace) {
+            self.next_char();
         }
     }
 
@@ -94,34 +175,74 @@ impl Lexer {
         }
     }
 
-    fn next_token(&mut self) -> Result<Token, LexerError> {
+    fn next_token(&mut self) -> Token {
         self.skip_whitespace();
-        let pos = self.pos;
-        let line = self.line;
-        let column = self.column;
+        let start = self.offset;
 
         let token_kind = match self.next_char() {
             Some(ch) => match ch {
+                    '+' if self.match_next_char('=') => {
+                        self.next_char();
+                        TokenKind::PlusEqual
+                    },
+                    '-' if self.match_next_char('=') => {
+                        self.next_char();
+                        TokenKind::MinusEqual
+                    },
+                    '*' if self.match_next_char('=') => {
+                        self.next_char();
+                        TokenKind::StarEqual
+                    },
                     '+' => TokenKind::Plus,
                     '-' => TokenKind::Minus,
                     '*' => TokenKind::Star,
+                    '%' => TokenKind::Percent,
+                    '~' if self.match_next_char('/') => {
+                        self.next_char();
+                        TokenKind::TildeSlash
+                    },
                     '(' => TokenKind::LeftParen,
                     ')' => TokenKind::RightParen,
-                    '{' => TokenKind::LeftBrace,
-                    '}' => TokenKind::RightBrace,
+                    '[' => TokenKind::LeftBracket,
+                    ']' => TokenKind::RightBracket,
+                    '{' => {
+                        if let Some(interpolation) = self.interpolations.last_mut() {
+                            interpolation.braces += 1;
+                        }
+                        TokenKind::LeftBrace
+                    },
+                    //#{も}で閉じるので、補間の中では{と同じく数える
+                    '#' if self.match_next_char('{') => {
+                        self.next_char();
+                        if let Some(interpolation) = self.interpolations.last_mut() {
+                            interpolation.braces += 1;
+                        }
+                        TokenKind::HashBrace
+                    },
+                    '}' => match self.interpolations.last_mut() {
+                        //${に対応する}なら文字列の続きを読む
+                        Some(interpolation) if interpolation.braces == 0 => {
+                            let Interpolation { start, .. } = self.interpolations.pop().unwrap();
+                            self.read_string(start, true)
+                        },
+                        Some(interpolation) => {
+                            interpolation.braces -= 1;
+                            TokenKind::RightBrace
+                        },
+                        None => TokenKind::RightBrace,
+                    },
                     '.' => TokenKind::Dot,
                     ',' => TokenKind::Comma,
+                    ':' => TokenKind::Colon,
                     ';' => TokenKind::Semicolon,
                     '/' => {
                         if self.match_next_char('/') {
-                            while let Some(ch) = self.next_char() {
-                                if ch == '\n' {
-                                    self.line += 1;
-                                    self.column = 0;
-                                    break;
-                                }
-                            }
-                            TokenKind::LineComment
+                            self.read_line_comment()
+                        }else if self.match_next_char('*') {
+                            self.read_block_comment(start)
+                        }else if self.match_next_char('=') {
+                            self.next_char();
+                            TokenKind::SlashEqual
                         }else {
                             TokenKind::Slash
                         }
@@ -129,7 +250,7 @@ impl Lexer {
                     '=' => {
                         if self.match_next_char('=') {
                             //文字を次に進める。返り値は利用しない。
-                            //self.posとself.columnをインクリメントするため
+                            //self.posとself.offsetをインクリメントするため
                             self.next_char();
                             TokenKind::EqualEqual
                         }else {
@@ -160,62 +281,284 @@ impl Lexer {
                             TokenKind::Greater
                         }
                     },
-                    '"' => {
-                        let mut str = String::new();
-                        while let Some(ch) = self.next_char() {
-                            if ch == '"' {
-                                break;
-                            }else {
-                                str.push(ch);
-                            }
-                        }
-
-                        TokenKind::Literal { kind: LiteralKind::String(str) }
-                    },
-                    ('0'..='9') => {
-                        let integer = self.read_integer(ch);
-                        match integer.parse::<f64>() {
-                            Ok(f) => TokenKind::Literal { kind: LiteralKind::Number(f) },
-                            Err(e) => Err(e)?,
-                        }
-                    },
+                    '"' => self.read_string(start, false),
+                    'r' if self.is_raw_string_start() => self.read_raw_string(start),
+                    ('0'..='9') => self.read_number(ch, start),
                     _ if ch.is_alphanumeric() || ch == '_' => {
                         let ident = self.read_ident(ch);
                         self.keyword_or_ident(ident)
                     },
-                    _ => {
-                        Err(UnexpectedChar::new(ch, line, column))?
-                    }
+                    _ => self.error(UnexpectedChar::new(ch, Span::new(start, self.offset))),
             },
             None => {
                 TokenKind::Eof
             }
         };
 
-        Ok(
-            Token {
-                token_kind,
-                pos,
-                line,
-                column,
+        Token {
+            token_kind,
+            span: Span::new(start, self.offset),
+        }
+    }
+
+    //開始の"を読んだ後から閉じる"か${までを読む。
+    //resumedは${...}の後の続きを読んでいるときにtrue
+    fn read_string(&mut self, start: usize, resumed: bool) -> TokenKind {
+        let mut str = String::new();
+        let mut valid = true;
+
+        loop {
+            let escape_start = self.offset;
+
+            match self.next_char() {
+                Some('"') => break,
+                Some('$') if self.peek_char() == Some('{') => {
+                    self.next_char();
+                    self.interpolations.push(Interpolation { braces: 0, start });
+
+                    return match valid {
+                        true => TokenKind::StringPart(str),
+                        false => TokenKind::Error,
+                    };
+                },
+                Some('\\') => match self.read_escape() {
+                    Ok(ch) => str.push(ch),
+                    //不正なエスケープがあっても閉じる"までは読み進める
+                    Err(sequence) => {
+                        let span = Span::new(escape_start, self.offset);
+                        self.error(InvalidEscape::new(sequence, span));
+                        valid = false;
+                    },
+                },
+                Some(ch) => str.push(ch),
+                None => return self.unterminated_string(start),
+            }
+        }
+
+        match (valid, resumed) {
+            (true, false) => TokenKind::Literal { kind: LiteralKind::String(str) },
+            (true, true) => TokenKind::StringEnd(str),
+            (false, _) => TokenKind::Error,
+        }
+    }
+
+    //\の後を読む。不正なときはエラー表示用に読んだ部分を返す
+    fn read_escape(&mut self) -> Result<char, String> {
+        match self.next_char() {
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('0') => Ok('\0'),
+            Some('\\') => Ok('\\'),
+            Some('"') => Ok('"'),
+            Some('\'') => Ok('\''),
+            Some('$') => Ok('$'),
+            Some('u') => self.read_unicode_escape(),
+            Some('\n') | None => Err("\\".to_string()),
+            Some(ch) => Err(format!("\\{}", ch)),
+        }
+    }
+
+    //\u{1F600}の形式。1から6桁の16進数でUnicodeのスカラー値を表す
+    fn read_unicode_escape(&mut self) -> Result<char, String> {
+        let mut sequence = String::from("\\u");
+        if self.peek_char() != Some('{') {
+            return Err(sequence);
+        }
+        self.next_char();
+        sequence.push('{');
+
+        let mut digits = String::new();
+        while let Some(ch) = self.peek_char() {
+            //閉じる"や改行まで飲み込まないようにする
+            if matches!(ch, '}' | '"' | '\n') {
+                break;
+            }
+            self.next_char();
+            digits.push(ch);
+            sequence.push(ch);
+        }
+
+        if self.peek_char() != Some('}') {
+            return Err(sequence);
+        }
+        self.next_char();
+        sequence.push('}');
+
+        if digits.is_empty() || digits.len() > 6 || !digits.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return Err(sequence);
+        }
+
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or(sequence)
+    }
+
+    //スラッシュ3つで始まればドキュメントコメント。4つ以上なら普通のコメント
+    fn read_line_comment(&mut self) -> TokenKind {
+        self.next_char();
+        let is_doc = self.peek_char() == Some('/') && self.peek_nth_char(1) != Some('/');
+        if is_doc {
+            self.next_char();
+        }
+
+        let mut text = String::new();
+        while let Some(ch) = self.next_char() {
+            if ch == '\n' {
+                break;
+            }
+            text.push(ch);
+        }
+
+        match is_doc {
+            //直後の空白1つと行末の\rは本文に含めない
+            true => {
+                let text = text.strip_suffix('\r').unwrap_or(&text);
+                TokenKind::DocComment(text.strip_prefix(' ').unwrap_or(text).to_string())
+            },
+            false => TokenKind::LineComment,
+        }
+    }
+
+    //ブロックコメントは入れ子にできる。閉じられないまま終わればエラー
+    fn read_block_comment(&mut self, start: usize) -> TokenKind {
+        self.next_char();
+        let mut depth = 1;
+
+        while depth > 0 {
+            match self.next_char() {
+                Some('/') if self.peek_char() == Some('*') => {
+                    self.next_char();
+                    depth += 1;
+                },
+                Some('*') if self.peek_char() == Some('/') => {
+                    self.next_char();
+                    depth -= 1;
+                },
+                Some(_) => {},
+                None => return self.error(UnterminatedComment::new(Span::new(start, self.offset))),
             }
-        )
+        }
+
+        TokenKind::BlockComment
     }
 
-    fn read_integer(&mut self, first_char: char) -> String {
-        match first_char {
-            ('0'..='9') => {
-                let mut result = vec![];
-                result.push(first_char);
+    //rの後に#が0個以上続き、"が来れば生文字列
+    fn is_raw_string_start(&self) -> bool {
+        let hashes = (0..).take_while(|&n| self.peek_nth_char(n) == Some('#')).count();
+        self.peek_nth_char(hashes) == Some('"')
+    }
 
-                while let Some('0'..='9') | Some('.') = self.peek_char() {
-                    let num = self.next_char().unwrap();
-                    result.push(num);
+    //r"..."やr#"..."#。エスケープを解釈せず、開始と同じ数の#が続く"で閉じる
+    fn read_raw_string(&mut self, start: usize) -> TokenKind {
+        let mut hashes = 0;
+        while self.peek_char() == Some('#') {
+            self.next_char();
+            hashes += 1;
+        }
+        self.next_char();
+
+        let mut str = String::new();
+        loop {
+            match self.next_char() {
+                Some('"') if (0..hashes).all(|n| self.peek_nth_char(n) == Some('#')) => {
+                    for _ in 0..hashes {
+                        self.next_char();
+                    }
+                    break;
+                },
+                Some(ch) => str.push(ch),
+                None => return self.unterminated_string(start),
+            }
+        }
+
+        TokenKind::Literal { kind: LiteralKind::String(str) }
+    }
+
+    //0x/0b/0oの接頭辞、小数、指数、_区切りを読む
+    //.は直後が数字のときだけ小数点とみなす。1.や1..2のように数字の続かない.は不正なリテラルにする
+    fn read_number(&mut self, first_char: char, start: usize) -> TokenKind {
+        let mut literal = String::from(first_char);
+        let radix = match (first_char, self.peek_char()) {
+            ('0', Some('x' | 'X')) => 16,
+            ('0', Some('b' | 'B')) => 2,
+            ('0', Some('o' | 'O')) => 8,
+            _ => 10,
+        };
+
+        let mut is_float = false;
+        let digits = if radix == 10 {
+            literal.push_str(&self.read_digits(10));
+            if self.peek_char() == Some('.') && self.peek_nth_char(1).is_some_and(|ch| ch.is_ascii_digit()) {
+                literal.push(self.next_char().unwrap());
+                literal.push_str(&self.read_digits(10));
+                is_float = true;
+            }
+            if self.is_exponent_start() {
+                literal.push(self.next_char().unwrap());
+                if let Some('+' | '-') = self.peek_char() {
+                    literal.push(self.next_char().unwrap());
                 }
+                literal.push_str(&self.read_digits(10));
+                is_float = true;
+            }
+            literal.clone()
+        } else {
+            literal.push(self.next_char().unwrap());
+            let digits = self.read_digits(radix);
+            literal.push_str(&digits);
+            digits
+        };
 
-                result.iter().collect()
+        //.も指数もなければ整数。i64に収まらなければBigIntにする
+        let digits = digits.replace('_', "");
+        let value = match (valid_separators(&literal, radix), is_float) {
+            (false, _) => None,
+            (true, true) => digits.parse::<f64>().ok().map(LiteralKind::Number),
+            (true, false) => match i64::from_str_radix(&digits, radix) {
+                Ok(n) => Some(LiteralKind::Integer(n)),
+                Err(_) => BigInt::parse(&digits, radix).map(LiteralKind::BigInt),
             },
-            _ => unreachable!(),
+        };
+
+        //1.2.3や12abcのように数値に続く文字もまとめて1つの不正なリテラルにする
+        let mut malformed = false;
+        while let Some(ch) = self.peek_char() {
+            if !(ch.is_alphanumeric() || ch == '_' || ch == '.') {
+                break;
+            }
+            self.next_char();
+            literal.push(ch);
+            malformed = true;
+        }
+
+        match value {
+            Some(kind) if !malformed => TokenKind::Literal { kind },
+            _ => self.error(InvalidNumber::new(literal, Span::new(start, self.offset))),
+        }
+    }
+
+    fn read_digits(&mut self, radix: u32) -> String {
+        let mut digits = String::new();
+        while let Some(ch) = self.peek_char() {
+            if !(ch.is_digit(radix) || ch == '_') {
+                break;
+            }
+            self.next_char();
+            digits.push(ch);
+        }
+
+        digits
+    }
+
+    //eの後に数字か、符号と数字が続けば指数
+    fn is_exponent_start(&self) -> bool {
+        let digit_at = |n| self.peek_nth_char(n).is_some_and(|ch: char| ch.is_ascii_digit());
+        match (self.peek_char(), self.peek_nth_char(1)) {
+            (Some('e' | 'E'), Some('+' | '-')) => digit_at(2),
+            (Some('e' | 'E'), _) => digit_at(1),
+            _ => false,
         }
     }
 
@@ -242,6 +585,7 @@ impl Lexer {
             "let" => TokenKind::Let,
             "else" => TokenKind::Else,
             "for" => TokenKind::For,
+            "in" => TokenKind::In,
             "while" => TokenKind::While,
             "fn" => TokenKind::Fn,
             "return" => TokenKind::Return,
@@ -255,3 +599,56 @@ impl Lexer {
         }
     }
 }
+
+//_は数字と数字の間にだけ置ける
+fn valid_separators(literal: &str, radix: u32) -> bool {
+    let chars = literal.chars().collect::<Vec<char>>();
+    chars.iter().enumerate().all(|(i, &ch)| {
+        ch != '_' || (i > 0 && chars[i - 1].is_digit(radix) && chars.get(i + 1).is_some_and(|ch| ch.is_digit(radix)))
+    })
+}
+
+//前のトークンと同じ行にあるトリビアをtrailingへ移し、残りを次のトークンのleadingとして返す
+//ドキュメントコメントと複数行のブロックコメントは次の宣言の側に付ける
+fn split_trailing(trailing: &mut Vec<Trivia>, trivia: Vec<Trivia>) -> Vec<Trivia> {
+    let mut trivia = trivia.into_iter();
+    let mut leading = vec![];
+
+    for piece in trivia.by_ref() {
+        match piece.kind {
+            TriviaKind::Whitespace => match piece.text.find('\n') {
+                Some(newline) => {
+                    let split = newline + 1;
+                    let span = piece.span;
+                    trailing.push(Trivia {
+                        kind: TriviaKind::Whitespace,
+                        text: piece.text[..split].to_string(),
+                        span: Span::new(span.start, span.start + split),
+                    });
+                    if split < piece.text.len() {
+                        leading.push(Trivia {
+                            kind: TriviaKind::Whitespace,
+                            text: piece.text[split..].to_string(),
+                            span: Span::new(span.start + split, span.end),
+                        });
+                    }
+                    break;
+                },
+                None => trailing.push(piece),
+            },
+            //行コメントは改行まで含むのでそこで行が終わる
+            TriviaKind::LineComment => {
+                trailing.push(piece);
+                break;
+            },
+            TriviaKind::BlockComment if !piece.text.contains('\n') => trailing.push(piece),
+            TriviaKind::BlockComment | TriviaKind::DocComment => {
+                leading.push(piece);
+                break;
+            },
+        }
+    }
+
+    leading.extend(trivia);
+    leading
+}
@@ -1,5 +1,9 @@
 
-use crate::syntax::token::{LiteralKind, Token, TokenKind};
+use crate::{
+    bignum::BigInt,
+    syntax::{span::{Position, SourceMap}, token::{LiteralKind, Token, TokenKind, Trivia, TriviaKind}, Span},
+    test_support::summary,
+};
 
 use super::*;
 
@@ -12,24 +16,33 @@ fn lex_new_line_and_space() {
     let tokens = test_helper("+-*/;()\n<>=   .");
 
     let expect_tokens = vec![
-        Token { token_kind: TokenKind::Plus, pos: 0, line: 1, column: 0 },
-        Token { token_kind: TokenKind::Minus, pos: 1, line: 1, column: 1 },
-        Token { token_kind: TokenKind::Star, pos: 2, line: 1, column: 2 },
-        Token { token_kind: TokenKind::Slash, pos: 3, line: 1, column: 3 },
-        Token { token_kind: TokenKind::Semicolon, pos: 4, line: 1, column: 4 },
-        Token { token_kind: TokenKind::LeftParen, pos: 5, line: 1, column: 5 },
-        Token { token_kind: TokenKind::RightParen, pos: 6, line: 1, column: 6 },
+        Token { token_kind: TokenKind::Plus, span: Span::new(0, 1) },
+        Token { token_kind: TokenKind::Minus, span: Span::new(1, 2) },
+        Token { token_kind: TokenKind::Star, span: Span::new(2, 3) },
+        Token { token_kind: TokenKind::Slash, span: Span::new(3, 4) },
+        Token { token_kind: TokenKind::Semicolon, span: Span::new(4, 5) },
+        Token { token_kind: TokenKind::LeftParen, span: Span::new(5, 6) },
+        Token { token_kind: TokenKind::RightParen, span: Span::new(6, 7) },
         /* 改行 */
-        Token { token_kind: TokenKind::Less, pos: 8, line: 2, column: 0 },
-        Token { token_kind: TokenKind::GreaterEqual, pos: 9, line: 2, column: 1 },
+        Token { token_kind: TokenKind::Less, span: Span::new(8, 9) },
+        Token { token_kind: TokenKind::GreaterEqual, span: Span::new(9, 11) },
         /* スペース×3 */
-        Token { token_kind: TokenKind::Dot, pos: 14, line: 2, column: 6 },
-        Token { token_kind: TokenKind::Eof, pos: 15, line: 2, column: 7 },
+        Token { token_kind: TokenKind::Dot, span: Span::new(14, 15) },
+        Token { token_kind: TokenKind::Eof, span: Span::new(15, 15) },
     ];
 
     assert_eq!(tokens, expect_tokens);
 }
 
+#[test]
+fn lex_starting_at() {
+    //REPLの2行目として読む。位置は前の行を含めたソースの中の位置
+    let (tokens, errors) = Lexer::new("x @").starting_at(9).tokenize();
+
+    assert_eq!(tokens[0], Token { token_kind: TokenKind::Ident("x".to_string()), span: Span::new(9, 10) });
+    assert_eq!(errors[0].diagnostic().label.map(|label| label.span), Some(Span::new(11, 12)));
+}
+
 #[test]
 fn lex_string() {
     let tokens = test_helper(r#""String"+
@@ -38,26 +51,18 @@ fn lex_string() {
     let expect_tokens = vec![
         Token { token_kind: TokenKind::Literal {
             kind: LiteralKind::String("String".to_string()) },
-            pos: 0,
-            line: 1,
-            column: 0,
+            span: Span::new(0, 8),
         },
         Token { token_kind: TokenKind::Plus,
-            pos: 8,
-            line: 1,
-            column: 8,
+            span: Span::new(8, 9),
         },
         /* 改行 */
         Token { token_kind: TokenKind::Literal {
             kind: LiteralKind::String("String2".to_string()) },
-            pos: 10,
-            line: 2,
-            column: 0,
+            span: Span::new(10, 19),
         },
         Token { token_kind: TokenKind::Eof,
-            pos: 19,
-            line: 2,
-            column: 9,
+            span: Span::new(19, 19),
         },
     ];
 
@@ -71,59 +76,43 @@ fn lex_line_comment() {
     let expect_tokens = vec![
         Token {
             token_kind: TokenKind::Literal {
-                kind: LiteralKind::Number(1.0)
+                kind: LiteralKind::Integer(1)
             },
-            pos: 0,
-            line: 1,
-            column: 0,
+            span: Span::new(0, 1),
         },
         Token {
             token_kind: TokenKind::Plus,
-            pos: 2,
-            line: 1,
-            column: 2,
+            span: Span::new(2, 3),
         },
         Token {
             token_kind: TokenKind::Literal {
-                kind: LiteralKind::Number(1.0)
+                kind: LiteralKind::Integer(1)
             },
-            pos: 4,
-            line: 1,
-            column: 4,
+            span: Span::new(4, 5),
         },
         Token {
             token_kind: TokenKind::LineComment,
-            pos: 6,
-            line: 1,
-            column: 6,
+            span: Span::new(6, 22),
         },
         Token {
             token_kind: TokenKind::Literal {
-                kind: LiteralKind::Number(2.0)
+                kind: LiteralKind::Integer(2)
             },
-            pos: 22,
-            line: 2,
-            column: 0,
+            span: Span::new(22, 23),
         },
         Token {
             token_kind: TokenKind::Star,
-            pos: 24,
-            line: 2,
-            column: 2,
+            span: Span::new(24, 25),
         },
         Token {
             token_kind: TokenKind::Literal {
-                kind: LiteralKind::Number(3.0)
+                kind: LiteralKind::Integer(3)
             },
-            pos: 26,
-            line: 2,
-            column: 4,
+            span: Span::new(26, 27),
         },
         Token {
             token_kind: TokenKind::Eof,
-            pos: 27,
-            line: 2,
-            column: 5,
+            span: Span::new(27, 27),
         },
     ];
 
@@ -141,65 +130,45 @@ fn func() {
     let expect_tokens = vec![
         Token {
             token_kind: TokenKind::Fn,
-            pos: 0,
-            line: 1,
-            column: 0,
+            span: Span::new(0, 2),
         },
         Token {
             token_kind: TokenKind::Ident("func".to_string()),
-            pos: 3,
-            line: 1,
-            column: 3,
+            span: Span::new(3, 7),
         },
         Token {
             token_kind: TokenKind::LeftParen,
-            pos: 7,
-            line: 1,
-            column: 7,
+            span: Span::new(7, 8),
         },
         Token {
             token_kind: TokenKind::RightParen,
-            pos: 8,
-            line: 1,
-            column: 8,
+            span: Span::new(8, 9),
         },
         Token {
             token_kind: TokenKind::LeftBrace,
-            pos: 10,
-            line: 1,
-            column: 10,
+            span: Span::new(10, 11),
         },
         Token {
             token_kind: TokenKind::Print,
-            pos: 16,
-            line: 2,
-            column: 4,
+            span: Span::new(16, 21),
         },
         Token {
             token_kind: TokenKind::Literal {
                 kind: LiteralKind::String("Hello, World!".to_string())
             },
-            pos: 22,
-            line: 2,
-            column: 10,
+            span: Span::new(22, 37),
         },
         Token {
             token_kind: TokenKind::Semicolon,
-            pos: 37,
-            line: 2,
-            column: 25,
+            span: Span::new(37, 38),
         },
         Token {
             token_kind: TokenKind::RightBrace,
-            pos: 39,
-            line: 3,
-            column: 0,
+            span: Span::new(39, 40),
         },
         Token {
             token_kind: TokenKind::Eof,
-            pos: 40,
-            line: 3,
-            column: 1,
+            span: Span::new(40, 40),
         },
     ];
 
@@ -216,87 +185,431 @@ let y = x + 1.23;
     let expect_tokens = vec![
         Token {
             token_kind: TokenKind::Let,
-            pos: 0,
-            line: 1,
-            column: 0,
+            span: Span::new(0, 3),
         },
         Token {
             token_kind: TokenKind::Ident("x".to_string()),
-            pos: 4,
-            line: 1,
-            column: 4,
+            span: Span::new(4, 5),
         },
         Token {
             token_kind: TokenKind::Equal,
-            pos: 6,
-            line: 1,
-            column: 6,
+            span: Span::new(6, 7),
         },
         Token {
             token_kind: TokenKind::Literal {
-                kind: LiteralKind::Number(42.0),
+                kind: LiteralKind::Integer(42),
             },
-            pos: 8,
-            line: 1,
-            column: 8,
+            span: Span::new(8, 10),
         },
         Token {
             token_kind: TokenKind::Semicolon,
-            pos: 10,
-            line: 1,
-            column: 10,
+            span: Span::new(10, 11),
         },
         Token {
             token_kind: TokenKind::Let,
-            pos: 12,
-            line: 2,
-            column: 0,
+            span: Span::new(12, 15),
         },
         Token {
             token_kind: TokenKind::Ident("y".to_string()),
-            pos: 16,
-            line: 2,
-            column: 4,
+            span: Span::new(16, 17),
         },
         Token {
             token_kind: TokenKind::Equal,
-            pos: 18,
-            line: 2,
-            column: 6,
+            span: Span::new(18, 19),
         },
         Token {
             token_kind: TokenKind::Ident("x".to_string()),
-            pos: 20,
-            line: 2,
-            column: 8,
+            span: Span::new(20, 21),
         },
         Token {
             token_kind: TokenKind::Plus,
-            pos: 22,
-            line: 2,
-            column: 10,
+            span: Span::new(22, 23),
         },
         Token {
             token_kind: TokenKind::Literal {
                 kind: LiteralKind::Number(1.23),
             },
-            pos: 24,
-            line: 2,
-            column: 12,
+            span: Span::new(24, 28),
         },
         Token {
             token_kind: TokenKind::Semicolon,
-            pos: 28,
-            line: 2,
-            column: 16,
+            span: Span::new(28, 29),
         },
         Token {
             token_kind: TokenKind::Eof,
-            pos: 29,
-            line: 2,
-            column: 17,
+            span: Span::new(29, 29),
         },
     ];
 
     assert_eq!(tokens, expect_tokens);
 }
+
+fn errors_helper(input: &str) -> (Vec<TokenKind>, Vec<String>) {
+    let (tokens, errors) = Lexer::new(input).tokenize();
+    (
+        tokens.into_iter().map(|t| t.token_kind).collect(),
+        errors.iter().map(|e| summary(&e.diagnostic(), input)).collect(),
+    )
+}
+
+#[test]
+fn lex_collects_every_error() {
+    let (tokens, errors) = errors_helper("let a = @;\nlet b = 1.2.3;");
+
+    assert_eq!(errors, vec![
+        "Unexpected character: @ at [1:8]",
+        "Invalid number literal: 1.2.3 at [2:8]",
+    ]);
+    assert_eq!(tokens[3], TokenKind::Error);
+    assert_eq!(tokens[8], TokenKind::Error);
+    assert_eq!(tokens[9], TokenKind::Semicolon);
+    assert_eq!(tokens.last(), Some(&TokenKind::Eof));
+}
+
+#[test]
+fn lex_unterminated_string() {
+    let (tokens, errors) = errors_helper("print 1;\nprint \"abc;");
+
+    assert_eq!(errors, vec!["Unterminated string at [2:6]"]);
+    assert_eq!(tokens[3..], [TokenKind::Print, TokenKind::Error, TokenKind::Eof]);
+}
+
+#[test]
+fn lex_returns_errors() {
+    let errors = Lexer::new("# $").lex().unwrap_err();
+    assert_eq!(errors.len(), 2);
+}
+
+fn string_helper(input: &str) -> String {
+    match &test_helper(input)[0].token_kind {
+        TokenKind::Literal { kind: LiteralKind::String(s) } => s.clone(),
+        kind => panic!("expected string literal, got {:?}", kind),
+    }
+}
+
+#[test]
+fn lex_string_escapes() {
+    assert_eq!(string_helper(r#""a\nb\tc\r\0""#), "a\nb\tc\r\0");
+    assert_eq!(string_helper(r#""\"quoted\" \\ \'""#), "\"quoted\" \\ '");
+    assert_eq!(string_helper(r#""\u{1F600}\u{e9}\u{41}""#), "😀éA");
+}
+
+#[test]
+fn lex_invalid_escapes() {
+    let (tokens, errors) = errors_helper(r#"print "a\qb\u{110000}\u{}\u12"; print 1;"#);
+
+    assert_eq!(errors, vec![
+        r"Invalid escape sequence: \q at [1:8]",
+        r"Invalid escape sequence: \u{110000} at [1:11]",
+        r"Invalid escape sequence: \u{} at [1:21]",
+        r"Invalid escape sequence: \u at [1:25]",
+    ]);
+    //文字列の終わりから字句解析を続ける
+    assert_eq!(tokens, vec![
+        TokenKind::Print,
+        TokenKind::Error,
+        TokenKind::Semicolon,
+        TokenKind::Print,
+        TokenKind::Literal { kind: LiteralKind::Integer(1) },
+        TokenKind::Semicolon,
+        TokenKind::Eof,
+    ]);
+}
+
+#[test]
+fn lex_multi_line_string() {
+    let source = "\"one\ntwo\n\" +\n  x";
+    let tokens = test_helper(source);
+    let source_map = SourceMap::new(source);
+
+    assert_eq!(
+        tokens[0].token_kind,
+        TokenKind::Literal { kind: LiteralKind::String("one\ntwo\n".to_string()) },
+    );
+    assert_eq!(source_map.position(tokens[1].span.start), Position { line: 3, column: 2, utf16_column: 2 });
+    assert_eq!(source_map.position(tokens[2].span.start), Position { line: 4, column: 2, utf16_column: 2 });
+}
+
+#[test]
+fn lex_raw_strings() {
+    assert_eq!(string_helper(r#"r"C:\path\n""#), r"C:\path\n");
+    assert_eq!(string_helper(r###"r#"say "hi""#"###), r#"say "hi""#);
+    assert_eq!(string_helper(r###"r##"a"#b"##"###), r##"a"#b"##);
+    assert_eq!(string_helper("r\"line\nbreak\""), "line\nbreak");
+
+    //"が続かなければ識別子
+    let (tokens, errors) = errors_helper("r + r#");
+    assert_eq!(tokens[..3], [TokenKind::Ident("r".to_string()), TokenKind::Plus, TokenKind::Ident("r".to_string())]);
+    assert_eq!(errors, vec!["Unexpected character: # at [1:5]"]);
+}
+
+#[test]
+fn lex_unterminated_raw_string() {
+    let (_, errors) = errors_helper("let s = r#\"abc\"\n;");
+    assert_eq!(errors, vec!["Unterminated string at [1:8]"]);
+}
+
+#[test]
+fn lex_interpolation() {
+    let (tokens, errors) = errors_helper(r#""a${x}b${ f("}") }c" + 1"#);
+
+    assert!(errors.is_empty());
+    assert_eq!(tokens, vec![
+        TokenKind::StringPart("a".to_string()),
+        TokenKind::Ident("x".to_string()),
+        TokenKind::StringPart("b".to_string()),
+        TokenKind::Ident("f".to_string()),
+        TokenKind::LeftParen,
+        TokenKind::Literal { kind: LiteralKind::String("}".to_string()) },
+        TokenKind::RightParen,
+        TokenKind::StringEnd("c".to_string()),
+        TokenKind::Plus,
+        TokenKind::Literal { kind: LiteralKind::Integer(1) },
+        TokenKind::Eof,
+    ]);
+}
+
+#[test]
+fn lex_interpolation_tracks_braces() {
+    let (tokens, _) = errors_helper(r#""${ { } }x""#);
+    assert_eq!(tokens[1..4], [TokenKind::LeftBrace, TokenKind::RightBrace, TokenKind::StringEnd("x".to_string())]);
+}
+
+#[test]
+fn lex_map_literal() {
+    let (tokens, errors) = errors_helper("#{\"a\": 1}; # {");
+    assert_eq!(errors, vec!["Unexpected character: # at [1:11]"]);
+    assert_eq!(tokens, [
+        TokenKind::HashBrace,
+        TokenKind::Literal { kind: LiteralKind::String("a".to_string()) },
+        TokenKind::Colon,
+        TokenKind::Literal { kind: LiteralKind::Integer(1) },
+        TokenKind::RightBrace,
+        TokenKind::Semicolon,
+        TokenKind::Error,
+        TokenKind::LeftBrace,
+        TokenKind::Eof,
+    ]);
+
+    //補間の中の#{は対応する}まで補間を閉じない
+    let (tokens, _) = errors_helper(r#""${ #{} }x""#);
+    assert_eq!(tokens[1..4], [TokenKind::HashBrace, TokenKind::RightBrace, TokenKind::StringEnd("x".to_string())]);
+}
+
+#[test]
+fn lex_unterminated_interpolation() {
+    let (_, errors) = errors_helper("print 1;\nprint \"a ${b");
+    assert_eq!(errors, vec!["Unterminated string at [2:6]"]);
+
+    //補間の中で始まった文字列が閉じなくても、報告するのは外側の文字列だけ
+    let (_, errors) = errors_helper("print \"x ${ y\";");
+    assert_eq!(errors, vec!["Unterminated string at [1:6]"]);
+}
+
+fn number_helper(input: &str) -> LiteralKind {
+    match &test_helper(input)[0].token_kind {
+        TokenKind::Literal { kind } => kind.clone(),
+        kind => panic!("expected number literal, got {:?}", kind),
+    }
+}
+
+#[test]
+fn lex_number_literals() {
+    assert_eq!(number_helper("0xFF"), LiteralKind::Integer(255));
+    assert_eq!(number_helper("0x1e"), LiteralKind::Integer(30));
+    assert_eq!(number_helper("0b1010"), LiteralKind::Integer(10));
+    assert_eq!(number_helper("0o17"), LiteralKind::Integer(15));
+    assert_eq!(number_helper("1.5e-3"), LiteralKind::Number(0.0015));
+    assert_eq!(number_helper("2E+2"), LiteralKind::Number(200.0));
+    assert_eq!(number_helper("1e3"), LiteralKind::Number(1000.0));
+    assert_eq!(number_helper("1_000_000"), LiteralKind::Integer(1000000));
+    assert_eq!(number_helper("0b1111_0000"), LiteralKind::Integer(240));
+    assert_eq!(number_helper("12.345_6"), LiteralKind::Number(12.3456));
+}
+
+#[test]
+fn lex_integer_literals() {
+    //.も指数もなければ整数
+    assert_eq!(number_helper("3"), LiteralKind::Integer(3));
+    assert_eq!(number_helper("3.0"), LiteralKind::Number(3.0));
+    assert_eq!(number_helper("9223372036854775807"), LiteralKind::Integer(i64::MAX));
+    assert_eq!(number_helper("0x7fff_ffff_ffff_ffff"), LiteralKind::Integer(i64::MAX));
+
+    //i64に収まらなければBigInt
+    assert_eq!(number_helper("9223372036854775808"), LiteralKind::BigInt(BigInt::parse("9223372036854775808", 10).unwrap()));
+    assert_eq!(number_helper("0x1_0000_0000_0000_0000"), LiteralKind::BigInt(BigInt::parse("10000000000000000", 16).unwrap()));
+
+    let (tokens, _) = errors_helper("7 % 2 ~/ 3");
+    assert_eq!(tokens[1], TokenKind::Percent);
+    assert_eq!(tokens[3], TokenKind::TildeSlash);
+}
+
+#[test]
+fn lex_number_dots() {
+    //小数点の後に数字がなければ、続く.や文字ごと1つの不正なリテラルにする
+    let (tokens, errors) = errors_helper("1.;\nprint 1..2;\n1.foo 3.");
+
+    assert_eq!(errors, vec![
+        "Invalid number literal: 1. at [1:0]",
+        "Invalid number literal: 1..2 at [2:6]",
+        "Invalid number literal: 1.foo at [3:0]",
+        "Invalid number literal: 3. at [3:6]",
+    ]);
+    assert_eq!(tokens, vec![
+        TokenKind::Error,
+        TokenKind::Semicolon,
+        TokenKind::Print,
+        TokenKind::Error,
+        TokenKind::Semicolon,
+        TokenKind::Error,
+        TokenKind::Error,
+        TokenKind::Eof,
+    ]);
+}
+
+#[test]
+fn lex_invalid_numbers() {
+    let (tokens, errors) = errors_helper("0x 0b12 1_ 1__0 1e 1e+ 12abc 0x_1;");
+
+    assert_eq!(errors, vec![
+        "Invalid number literal: 0x at [1:0]",
+        "Invalid number literal: 0b12 at [1:3]",
+        "Invalid number literal: 1_ at [1:8]",
+        "Invalid number literal: 1__0 at [1:11]",
+        "Invalid number literal: 1e at [1:16]",
+        "Invalid number literal: 1e at [1:19]",
+        "Invalid number literal: 12abc at [1:23]",
+        "Invalid number literal: 0x_1 at [1:29]",
+    ]);
+    assert_eq!(tokens[6], TokenKind::Plus);
+    assert_eq!(tokens[tokens.len() - 2], TokenKind::Semicolon);
+}
+
+#[test]
+fn lex_block_comments() {
+    let source = "1 /* a /* nested\n */ still comment\n */ + 2;";
+    let tokens = test_helper(source);
+    let kinds = tokens.iter().map(|t| t.token_kind.clone()).collect::<Vec<_>>();
+
+    assert_eq!(kinds, vec![
+        TokenKind::Literal { kind: LiteralKind::Integer(1) },
+        TokenKind::BlockComment,
+        TokenKind::Plus,
+        TokenKind::Literal { kind: LiteralKind::Integer(2) },
+        TokenKind::Semicolon,
+        TokenKind::Eof,
+    ]);
+    //コメント中の改行も行として数える
+    assert_eq!(SourceMap::new(source).position(tokens[2].span.start), Position { line: 3, column: 4, utf16_column: 4 });
+}
+
+#[test]
+fn lex_doc_comments() {
+    let (tokens, _) = errors_helper("/// 説明\n///\n////区切り\n//普通\n///no space\r\nx");
+
+    assert_eq!(tokens, vec![
+        TokenKind::DocComment("説明".to_string()),
+        TokenKind::DocComment(String::new()),
+        TokenKind::LineComment,
+        TokenKind::LineComment,
+        TokenKind::DocComment("no space".to_string()),
+        TokenKind::Ident("x".to_string()),
+        TokenKind::Eof,
+    ]);
+}
+
+#[test]
+fn lex_unterminated_block_comment() {
+    let (tokens, errors) = errors_helper("print 1;\n  /* a /* b */\nprint 2;");
+
+    assert_eq!(errors, vec!["Unterminated block comment at [2:2]"]);
+    assert_eq!(tokens[3..], [TokenKind::Error, TokenKind::Eof]);
+}
+
+fn trivia(pieces: &[Trivia]) -> Vec<(TriviaKind, &str)> {
+    pieces.iter().map(|t| (t.kind, t.text.as_str())).collect()
+}
+
+#[test]
+fn lex_with_trivia() {
+    let source = "let x = 1; // one\n  /* two\n */ /// three\nx; /* four */\n\n";
+    let (tokens, errors) = Lexer::new(source).tokenize_with_trivia();
+    assert!(errors.is_empty());
+
+    let texts = tokens.iter().map(|t| t.text.as_str()).collect::<Vec<_>>();
+    assert_eq!(texts, ["let", "x", "=", "1", ";", "x", ";", ""]);
+
+    //同じ行の行コメントまでが前のトークンのtrailing
+    assert_eq!(trivia(&tokens[4].trailing), [
+        (TriviaKind::Whitespace, " "),
+        (TriviaKind::LineComment, "// one\n"),
+    ]);
+    //複数行のブロックコメントとドキュメントコメントは次のトークンのleading
+    assert_eq!(trivia(&tokens[5].leading), [
+        (TriviaKind::Whitespace, "  "),
+        (TriviaKind::BlockComment, "/* two\n */"),
+        (TriviaKind::Whitespace, " "),
+        (TriviaKind::DocComment, "/// three\n"),
+    ]);
+    //1行のブロックコメントは同じ行に残り、改行1つまでがtrailingになる
+    assert_eq!(trivia(&tokens[6].trailing), [
+        (TriviaKind::Whitespace, " "),
+        (TriviaKind::BlockComment, "/* four */"),
+        (TriviaKind::Whitespace, "\n"),
+    ]);
+    assert_eq!(trivia(&tokens[7].leading), [(TriviaKind::Whitespace, "\n")]);
+
+    //トリビアのspanは元のソースの同じ範囲を指す
+    for token in &tokens {
+        for piece in token.leading.iter().chain(&token.trailing) {
+            assert_eq!(&source[piece.span.start..piece.span.end], piece.text);
+        }
+        assert_eq!(&source[token.token.span.start..token.token.span.end], token.text);
+    }
+}
+
+#[test]
+fn lex_single_eof_after_trailing_whitespace() {
+    let tokens = test_helper("print 1;  \n// end\n");
+    assert_eq!(tokens.iter().filter(|t| t.token_kind == TokenKind::Eof).count(), 1);
+}
+
+#[test]
+fn lex_compound_assignment() {
+    let (tokens, errors) = errors_helper("a += 1 -= b *= c /= d // e\n+ = - =");
+    assert!(errors.is_empty());
+    assert_eq!(tokens, [
+        TokenKind::Ident("a".to_string()),
+        TokenKind::PlusEqual,
+        TokenKind::Literal { kind: LiteralKind::Integer(1) },
+        TokenKind::MinusEqual,
+        TokenKind::Ident("b".to_string()),
+        TokenKind::StarEqual,
+        TokenKind::Ident("c".to_string()),
+        TokenKind::SlashEqual,
+        TokenKind::Ident("d".to_string()),
+        TokenKind::LineComment,
+        TokenKind::Plus,
+        TokenKind::Equal,
+        TokenKind::Minus,
+        TokenKind::Equal,
+        TokenKind::Eof,
+    ]);
+}
+
+#[test]
+fn lex_brackets() {
+    let (tokens, errors) = errors_helper("xs[0] = [];");
+    assert!(errors.is_empty());
+    assert_eq!(tokens, [
+        TokenKind::Ident("xs".to_string()),
+        TokenKind::LeftBracket,
+        TokenKind::Literal { kind: LiteralKind::Integer(0) },
+        TokenKind::RightBracket,
+        TokenKind::Equal,
+        TokenKind::LeftBracket,
+        TokenKind::RightBracket,
+        TokenKind::Semicolon,
+        TokenKind::Eof,
+    ]);
+}
@@ -0,0 +1,307 @@
+use std::{fmt, iter::Peekable, vec::IntoIter};
+
+use crate::{
+    diagnostics::Diagnostic,
+    rloxs_lexer::Lexer,
+    syntax::{token::{TokenWithTrivia, Trivia}, Expr, FnDecl, Span, Stmt, TokenKind},
+};
+
+use super::parser::Parser;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NodeKind {
+    Program,
+    LetDecl,
+    FnDecl,
+    ClassDecl,
+    Block,
+    ExprStmt,
+    PrintStmt,
+    IfStmt,
+    WhileStmt,
+    ForStmt,
+    ForInStmt,
+    ReturnStmt,
+    //構文エラーから回復した範囲。トークンはそのまま残す
+    Error,
+    Assign,
+    Literal,
+    Variable,
+    Binary,
+    Unary,
+    Grouping,
+    Call,
+    Get,
+    Set,
+    This,
+    Super,
+    Interpolation,
+    List,
+    Map,
+    Index,
+    IndexSet,
+}
+
+//ASTと違って括弧やセミコロン、空白、コメントもすべて持つ構文木
+//Displayで書き出すと元のソースと1バイトも違わない
+#[derive(Debug, Clone)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub span: Span,
+    pub children: Vec<SyntaxElement>,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(TokenWithTrivia),
+}
+
+impl SyntaxNode {
+    //1行に1要素ずつ、深さに応じて字下げした木の表示
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        self.dump_into(&mut out, 0);
+        out
+    }
+
+    fn dump_into(&self, out: &mut String, depth: usize) {
+        out.push_str(&format!("{}{:?}@{}..{}\n", "  ".repeat(depth), self.kind, self.span.start, self.span.end));
+
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.dump_into(out, depth + 1),
+                SyntaxElement::Token(token) => {
+                    let indent = "  ".repeat(depth + 1);
+                    for trivia in &token.leading {
+                        dump_trivia(out, &indent, "leading", trivia);
+                    }
+                    let span = token.token.span;
+                    out.push_str(&format!(
+                        "{}{:?}@{}..{} {:?}\n", indent, token.token.token_kind, span.start, span.end, token.text
+                    ));
+                    for trivia in &token.trailing {
+                        dump_trivia(out, &indent, "trailing", trivia);
+                    }
+                },
+            }
+        }
+    }
+}
+
+fn dump_trivia(out: &mut String, indent: &str, side: &str, trivia: &Trivia) {
+    out.push_str(&format!(
+        "{}  {} {:?}@{}..{} {:?}\n", indent, side, trivia.kind, trivia.span.start, trivia.span.end, trivia.text
+    ));
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => write!(f, "{}", node)?,
+                SyntaxElement::Token(token) => {
+                    for trivia in &token.leading {
+                        write!(f, "{}", trivia.text)?;
+                    }
+                    write!(f, "{}", token.text)?;
+                    for trivia in &token.trailing {
+                        write!(f, "{}", trivia.text)?;
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+//字句エラーがあれば構文エラーは報告しない。通常の実行と同じく最初に失敗した段階のエラーを返す
+pub fn parse_cst(source: &str) -> (SyntaxNode, Vec<Diagnostic>) {
+    let (tokens, lexer_errors) = Lexer::new(source).tokenize_with_trivia();
+    let plain = tokens.iter().map(|token| token.token.clone()).collect();
+    let (program, parse_errors) = Parser::new(plain).parse();
+
+    let diagnostics = match lexer_errors.is_empty() {
+        true => parse_errors.iter().map(|e| e.diagnostic()).collect(),
+        false => lexer_errors.iter().map(|e| e.diagnostic()).collect(),
+    };
+
+    let mut builder = Builder { tokens: tokens.into_iter().peekable() };
+    (builder.build_program(&program, source.len()), diagnostics)
+}
+
+//ASTのノードを子の位置でまとめて扱うための参照
+#[derive(Clone, Copy)]
+enum Ast<'a> {
+    Stmt(&'a Stmt),
+    Expr(&'a Expr),
+    Fn(&'a FnDecl),
+}
+
+impl<'a> Ast<'a> {
+    fn span(&self) -> Span {
+        match self {
+            Ast::Stmt(stmt) => stmt.span(),
+            Ast::Expr(expr) => expr.span(),
+            Ast::Fn(decl) => decl.span,
+        }
+    }
+
+    fn kind(&self) -> NodeKind {
+        match self {
+            Ast::Stmt(stmt) => match stmt {
+                Stmt::Expression { .. } => NodeKind::ExprStmt,
+                Stmt::Print { .. } => NodeKind::PrintStmt,
+                Stmt::Let { .. } => NodeKind::LetDecl,
+                Stmt::Fn(_) => NodeKind::FnDecl,
+                Stmt::Class(_) => NodeKind::ClassDecl,
+                Stmt::Block { .. } => NodeKind::Block,
+                Stmt::If { .. } => NodeKind::IfStmt,
+                Stmt::While { .. } => NodeKind::WhileStmt,
+                Stmt::For { .. } => NodeKind::ForStmt,
+                Stmt::ForIn { .. } => NodeKind::ForInStmt,
+                Stmt::Return { .. } => NodeKind::ReturnStmt,
+                Stmt::Error { .. } => NodeKind::Error,
+            },
+            Ast::Expr(expr) => match expr {
+                Expr::Assign { .. } => NodeKind::Assign,
+                Expr::Literal { .. } => NodeKind::Literal,
+                Expr::Variable { .. } => NodeKind::Variable,
+                Expr::BinaryOp { .. } => NodeKind::Binary,
+                Expr::UnaryOp { .. } => NodeKind::Unary,
+                Expr::Grouping { .. } => NodeKind::Grouping,
+                Expr::Call { .. } => NodeKind::Call,
+                Expr::Get { .. } => NodeKind::Get,
+                Expr::Set { .. } => NodeKind::Set,
+                Expr::This { .. } => NodeKind::This,
+                Expr::Super { .. } => NodeKind::Super,
+                Expr::Interpolation { .. } => NodeKind::Interpolation,
+                Expr::List { .. } => NodeKind::List,
+                Expr::Map { .. } => NodeKind::Map,
+                Expr::Index { .. } => NodeKind::Index,
+                Expr::IndexSet { .. } => NodeKind::IndexSet,
+            },
+            Ast::Fn(_) => NodeKind::FnDecl,
+        }
+    }
+
+    //ソース上の順に並べた子ノード
+    fn children(&self) -> Vec<Ast<'a>> {
+        let mut children = vec![];
+
+        match self {
+            Ast::Stmt(stmt) => match stmt {
+                Stmt::Expression { expr, .. } | Stmt::Print { expr, .. } => children.push(Ast::Expr(expr)),
+                Stmt::Let { initializer, .. } => children.extend(initializer.iter().map(Ast::Expr)),
+                Stmt::Fn(decl) => return Ast::Fn(decl).children(),
+                Stmt::Class(decl) => children.extend(decl.methods.iter().map(|method| Ast::Fn(method))),
+                Stmt::Block { stmts, .. } => children.extend(stmts.iter().map(Ast::Stmt)),
+                Stmt::If { condition, then_branch, else_branch, .. } => {
+                    children.push(Ast::Expr(condition));
+                    children.extend(then_branch.iter().map(Ast::Stmt));
+                    children.extend(else_branch.iter().flatten().map(Ast::Stmt));
+                },
+                Stmt::While { condition, body, .. } => {
+                    children.push(Ast::Expr(condition));
+                    children.extend(body.iter().map(Ast::Stmt));
+                },
+                Stmt::For { initializer, condition, increment, body, .. } => {
+                    children.extend(initializer.iter().map(|stmt| Ast::Stmt(stmt)));
+                    children.extend(condition.iter().map(Ast::Expr));
+                    children.extend(increment.iter().map(Ast::Expr));
+                    children.extend(body.iter().map(Ast::Stmt));
+                },
+                Stmt::ForIn { iterable, body, .. } => {
+                    children.push(Ast::Expr(iterable));
+                    children.extend(body.iter().map(Ast::Stmt));
+                },
+                Stmt::Return { value, .. } => children.extend(value.iter().map(Ast::Expr)),
+                Stmt::Error { .. } => {},
+            },
+            Ast::Expr(expr) => match expr {
+                Expr::Assign { expr, .. } | Expr::Grouping { expr, .. } => children.push(Ast::Expr(expr)),
+                Expr::BinaryOp { left, right, .. } => {
+                    children.push(Ast::Expr(left));
+                    children.push(Ast::Expr(right));
+                },
+                Expr::UnaryOp { operand, .. } => children.push(Ast::Expr(operand)),
+                Expr::Call { callee, args, .. } => {
+                    children.push(Ast::Expr(callee));
+                    children.extend(args.iter().map(Ast::Expr));
+                },
+                Expr::Get { object, .. } => children.push(Ast::Expr(object)),
+                Expr::Set { object, value, .. } => {
+                    children.push(Ast::Expr(object));
+                    children.push(Ast::Expr(value));
+                },
+                Expr::Interpolation { parts, .. } | Expr::List { elements: parts, .. } => {
+                    children.extend(parts.iter().map(Ast::Expr))
+                },
+                Expr::Map { entries, .. } => {
+                    for (key, value) in entries {
+                        children.push(Ast::Expr(key));
+                        children.push(Ast::Expr(value));
+                    }
+                },
+                Expr::Index { object, index, .. } => {
+                    children.push(Ast::Expr(object));
+                    children.push(Ast::Expr(index));
+                },
+                Expr::IndexSet { object, index, value, .. } => {
+                    children.push(Ast::Expr(object));
+                    children.push(Ast::Expr(index));
+                    children.push(Ast::Expr(value));
+                },
+                Expr::Literal { .. } | Expr::Variable { .. } | Expr::This { .. } | Expr::Super { .. } => {},
+            },
+            Ast::Fn(decl) => children.extend(decl.body.iter().map(Ast::Stmt)),
+        }
+
+        //forの本体のように位置の順とフィールドの順が違うこともあるので並べ直す
+        children.sort_by_key(|child| child.span().start);
+        children
+    }
+}
+
+//トークン列を先頭から順に、ASTのspanが示すノードへ配っていく
+//どのトークンもちょうど1回だけ木に入るので、書き出せば元のソースに戻る
+struct Builder {
+    tokens: Peekable<IntoIter<TokenWithTrivia>>,
+}
+
+impl Builder {
+    fn build_program(&mut self, program: &[Stmt], len: usize) -> SyntaxNode {
+        let mut children = vec![];
+
+        for stmt in program {
+            self.take_tokens_before(stmt.span().start, &mut children);
+            children.push(SyntaxElement::Node(self.build(Ast::Stmt(stmt))));
+        }
+
+        //残りのトークンとEofはProgramに入れる。末尾の空白とコメントはEofのleadingにある
+        children.extend(self.tokens.by_ref().map(SyntaxElement::Token));
+
+        SyntaxNode { kind: NodeKind::Program, span: Span::new(0, len), children }
+    }
+
+    fn build(&mut self, ast: Ast) -> SyntaxNode {
+        let span = ast.span();
+        let mut children = vec![];
+
+        for child in ast.children() {
+            self.take_tokens_before(child.span().start, &mut children);
+            children.push(SyntaxElement::Node(self.build(child)));
+        }
+        self.take_tokens_before(span.end, &mut children);
+
+        SyntaxNode { kind: ast.kind(), span, children }
+    }
+
+    fn take_tokens_before(&mut self, offset: usize, children: &mut Vec<SyntaxElement>) {
+        while let Some(token) = self.tokens.next_if(|token| {
+            token.token.span.start < offset && token.token.token_kind != TokenKind::Eof
+        }) {
+            children.push(SyntaxElement::Token(token));
+        }
+    }
+}
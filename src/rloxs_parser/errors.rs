@@ -1,16 +1,32 @@
 use std::{error::Error, fmt::Display};
 
-use crate::syntax::TokenKind;
+use crate::{diagnostics::Diagnostic, syntax::{Span, TokenKind}};
 
 #[derive(Debug)]
 pub enum ParseError {
     UnexpectedToken(UnexpectedToken),
+    InvalidAssignmentTarget(InvalidAssignmentTarget),
+}
+
+impl ParseError {
+    pub fn diagnostic(&self) -> Diagnostic {
+        match self {
+            ParseError::UnexpectedToken(e) => e.diagnostic(),
+            ParseError::InvalidAssignmentTarget(e) => e.diagnostic(),
+        }
+    }
+
+    //字句エラーの跡(TokenKind::Error)でつまずいたか。そのエラーは字句解析器が報告済み
+    pub fn is_at_lexer_error(&self) -> bool {
+        matches!(self, ParseError::UnexpectedToken(e) if e.unexpected_token == TokenKind::Error)
+    }
 }
 
 impl Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParseError::UnexpectedToken(e) => write!(f, "{}", e)
+            ParseError::UnexpectedToken(e) => write!(f, "{}", e),
+            ParseError::InvalidAssignmentTarget(e) => write!(f, "{}", e),
         }
     }
 }
@@ -21,46 +37,88 @@ impl Error for ParseError {}
 pub struct UnexpectedToken {
     unexpected_token: TokenKind,
     expected_token: Option<TokenKind>,
-    line: usize,
-    column: usize,
-
+    span: Span,
+    //閉じ括弧が足りないときの対応する開き括弧の位置
+    opener: Option<Span>,
 }
 
 impl UnexpectedToken {
     pub fn new(
     unexpected_token: TokenKind,
     expected_token: Option<TokenKind>,
-    line: usize,
-    column: usize,
+    span: Span,
     ) -> Self {
-        UnexpectedToken { unexpected_token, expected_token, line, column }
+        UnexpectedToken { unexpected_token, expected_token, span, opener: None }
     }
-}
 
-impl Display for UnexpectedToken {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.expected_token {
-            Some(expected_token) => write!(
-                f,
-                "Unexpected token: {:?}, expected: {:?} at [{}:{}]",
+    pub fn opened_at(self, span: Span) -> Self {
+        Self { opener: Some(span), ..self }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let message = match &self.expected_token {
+            Some(expected_token) => format!(
+                "Unexpected token: {}, expected: {}",
                 self.unexpected_token,
                 expected_token,
-                self.line,
-                self.column
             ),
-            None => write!(
-                f,
-                "Unexpected token: {:?} at [{}:{}]",
-                self.unexpected_token,
-                self.line,
-                self.column,
+            None => format!("Unexpected token: {}", self.unexpected_token),
+        };
+        let diagnostic = Diagnostic::new(message, self.span);
+
+        let delimiters = match self.expected_token {
+            Some(TokenKind::RightParen) => Some(("(", ")")),
+            Some(TokenKind::RightBrace) => Some(("{", "}")),
+            Some(TokenKind::RightBracket) => Some(("[", "]")),
+            _ => None,
+        };
+
+        match (self.opener, delimiters) {
+            (Some(span), Some((open, close))) => diagnostic.with_note(
+                format!("expected `{}` to close `{}` opened here", close, open),
+                span,
             ),
+            _ => diagnostic,
         }
     }
 }
 
+impl Display for UnexpectedToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
 impl From<UnexpectedToken> for ParseError {
     fn from(value: UnexpectedToken) -> Self {
         ParseError::UnexpectedToken(value)
     }
-}
\ No newline at end of file
+}
+
+//代入の左辺が変数やプロパティではない。位置は左辺の先頭を指す
+#[derive(Debug)]
+pub struct InvalidAssignmentTarget {
+    span: Span,
+}
+
+impl InvalidAssignmentTarget {
+    pub fn new(span: Span) -> Self {
+        InvalidAssignmentTarget { span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new("Invalid assignment target".to_string(), self.span)
+    }
+}
+
+impl Display for InvalidAssignmentTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<InvalidAssignmentTarget> for ParseError {
+    fn from(value: InvalidAssignmentTarget) -> Self {
+        ParseError::InvalidAssignmentTarget(value)
+    }
+}
@@ -2,6 +2,7 @@
 mod tests;
 
 pub mod parser;
+pub mod cst;
 mod errors;
 
 pub use errors::ParseError;
\ No newline at end of file
@@ -1,24 +1,66 @@
-use crate::syntax::{Expr, Operator, OperatorKind, Token, TokenKind};
-
-use super::errors::{ParseError, UnexpectedToken};
+use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+use crate::syntax::{
+    token::LiteralKind,
+    ClassDecl,
+    Expr,
+    FnDecl,
+    Identifier,
+    Operator,
+    OperatorKind,
+    Span,
+    Stmt,
+    Token,
+    TokenKind,
+};
+
+use super::errors::{InvalidAssignmentTarget, ParseError, UnexpectedToken};
 
 #[derive(Debug)]
 pub struct Parser {
     tokens:  Vec<Token>,
     pos: usize,
+    errors: Vec<ParseError>,
+    //今いるブロックの深さ。回復のときにブロックの}を越えて読み飛ばさないために使う
+    block_depth: usize,
+    //ドキュメントコメントの本文を、その直後のトークンの位置ごとに持つ
+    docs: HashMap<usize, String>,
 }
 
 impl Parser {
+    //コメントはトークン列から取り除く。ドキュメントコメントは宣言から引けるように別に残す
     pub fn new(tokens: Vec<Token>) -> Self {
+        let mut kept = vec![];
+        let mut docs = HashMap::new();
+        let mut lines: Vec<String> = vec![];
+
+        for token in tokens {
+            match token.token_kind {
+                TokenKind::LineComment | TokenKind::BlockComment => {},
+                TokenKind::DocComment(line) => lines.push(line),
+                _ => {
+                    if !lines.is_empty() {
+                        docs.insert(kept.len(), std::mem::take(&mut lines).join("\n"));
+                    }
+                    kept.push(token);
+                },
+            }
+        }
+
         Parser {
-                tokens: tokens
-                    .into_iter()
-                    .filter(|t| t.token_kind != TokenKind::LineComment)
-                    .collect(),
+                tokens: kept,
                 pos: 0,
+                errors: vec![],
+                block_depth: 0,
+                docs,
         }
     }
 
+    //fn・class・letの直前にあったドキュメントコメント。それ以外の場所のものは使わない
+    fn take_doc(&mut self) -> Option<String> {
+        self.docs.remove(&self.pos)
+    }
+
     fn is_at_end(&self) -> bool {
         self.pos >= self.tokens.len()
     }
@@ -27,11 +69,7 @@ impl Parser {
         if !self.is_at_end() {
             self.pos += 1;
         }
-        self.current()
-    }
-
-    fn current(&self) -> &Token {
-        &self.tokens[self.pos]
+        self.peek()
     }
 
     fn previous(&self) -> &Token {
@@ -57,224 +95,437 @@ impl Parser {
                 UnexpectedToken::new(
                 current_token.token_kind.clone(), //←clone()消したい
                 Some(token_kind),
-                current_token.line,
-                current_token.column,
+                current_token.span,
             ))?
         }
     }
 
-
-    pub fn parse_expression(&mut self) -> Result<Expr, ParseError> {
-        self.parse_assignment()
+    //startから直前に読んだトークンまでの範囲
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous().span)
     }
 
-    fn parse_assignment(&mut self) -> Result<Expr, ParseError> {
+    //閉じ括弧が見つからないときは対応する開き括弧の位置もエラーに残す
+    fn eat_closing(&mut self, token_kind: TokenKind, opener: &Token) -> Result<(), ParseError> {
+        self.eat(token_kind).map_err(|e| match e {
+            ParseError::UnexpectedToken(e) => e.opened_at(opener.span).into(),
+            e => e,
+        })
+    }
 
-        if let TokenKind::Ident(ident) = self.peek().token_kind.clone() {
-            self.eat(TokenKind::Ident(ident.clone()))?;
-            match self.peek().token_kind {
-                TokenKind::Equal => {
-                    self.eat(TokenKind::Equal)?;
-
-                    let assign_node = Expr::Assign {
-                        name: ident.clone(),
-                        expr: Box::new(self.logic_or()?),
-                    };
-                    Ok(assign_node)
-                },
-                _ => Ok(self.logic_or()?)
-            }
+    fn eat_ident(&mut self) -> Result<Identifier, ParseError> {
+        let current_token = self.peek().clone();
 
-        }else {
-            Ok(self.logic_or()?)
+        match &current_token.token_kind {
+            TokenKind::Ident(ident) => {
+                self.advance();
+                Ok(token_to_identifier(&current_token, ident.to_string()))
+            },
+            token_kind => Err(
+                UnexpectedToken::new(
+                    token_kind.clone(),
+                    None,
+                    current_token.span,
+            ))?
         }
     }
 
-    fn logic_or(&mut self) -> Result<Expr, ParseError> {
-        let mut node = self.logic_and()?;
+    pub fn parse_program(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let (program, errors) = self.parse();
 
-        'l: loop {
-            match self.peek().token_kind {
-                TokenKind::Or => {
-                    self.eat(TokenKind::Or)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.logic_and()?),
-                    }
-                },
-                _ => break 'l,
-            }
+        match errors.is_empty() {
+            true => Ok(program),
+            false => Err(errors),
         }
-
-        Ok(node)
     }
 
-    fn logic_and(&mut self) -> Result<Expr, ParseError> {
-        let mut node = self.parse_equality()?;
+    //エラーがあっても最後まで読み進め、途中までの構文木と見つかったエラーをすべて返す
+    pub fn parse(&mut self) -> (Vec<Stmt>, Vec<ParseError>) {
+        let mut program = vec![];
 
-        'l: loop {
-            match self.peek().token_kind {
-                TokenKind::And => {
-                    self.eat(TokenKind::And)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.parse_equality()?),
-                    }
-                },
-                _ => break 'l,
-            }
+        while self.peek().token_kind != TokenKind::Eof {
+            program.push(self.parse_declaration_or_recover());
         }
 
-        Ok(node)
+        (program, std::mem::take(&mut self.errors))
     }
 
-    fn parse_equality(&mut self) -> Result<Expr, ParseError> {
-        let mut node = self.parse_comparison()?;
+    //失敗した宣言はエラーを記録してStmt::Errorに置き換える
+    fn parse_declaration_or_recover(&mut self) -> Stmt {
+        let start = self.pos;
+        let span = self.peek().span;
+
+        match self.parse_declaration() {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                if !e.is_at_lexer_error() {
+                    self.errors.push(e);
+                }
+                self.synchronize(start);
+                Stmt::Error { span: self.span_from(span) }
+            },
+        }
+    }
 
-        loop {
-            match self.peek().token_kind {
-                TokenKind::BangEqual => {
-                    self.eat(TokenKind::BangEqual)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.parse_comparison()?)
-                    }
-                },
-                TokenKind::EqualEqual => {
-                    self.eat(TokenKind::EqualEqual)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.parse_comparison()?),
-                    }
-                },
-                _ => break,
-            }
+    //次の文の区切りまでトークンを読み飛ばす。ブロックの中では、そのブロックを閉じる}の手前で止まる
+    fn synchronize(&mut self, start: usize) {
+        //1トークンも進んでいなければ無限ループにならないよう最低1つは捨てる
+        if self.pos == start {
+            self.advance();
         }
 
-        Ok(node)
-    }
+        //失敗した文の中で開いたまま閉じていない{の数
+        let mut open_braces = self.tokens[start..self.pos].iter().fold(0usize, |open, token| {
+            match token.token_kind {
+                TokenKind::LeftBrace | TokenKind::HashBrace => open + 1,
+                TokenKind::RightBrace => open.saturating_sub(1),
+                _ => open,
+            }
+        });
 
-    fn parse_comparison(&mut self) -> Result<Expr, ParseError> {
-        let mut node = self.parse_term()?;
+        //エラーの後で読み飛ばしながら開いた{の数と、最初の{がブロックだったか。
+        //fn f(a b) { ... }のように本体の手前で失敗したときは、本体の中の;やキーワードで止まらない
+        let mut skipped_braces = 0usize;
+        let mut skipped_block = false;
+
+        while self.peek().token_kind != TokenKind::Eof {
+            if skipped_braces == 0 && self.pos > start && self.previous().token_kind == TokenKind::Semicolon {
+                return;
+            }
 
-        loop {
             match self.peek().token_kind {
-                TokenKind::Less => {
-                    self.eat(TokenKind::Less)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.parse_term()?)
+                TokenKind::LeftBrace | TokenKind::HashBrace => {
+                    if skipped_braces == 0 {
+                        skipped_block = self.peek().token_kind == TokenKind::LeftBrace;
                     }
+                    skipped_braces += 1;
+                    self.advance();
                 },
-                TokenKind::LessEqual => {
-                    self.eat(TokenKind::EqualEqual)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.parse_term()?),
+                TokenKind::RightBrace if skipped_braces > 0 => {
+                    skipped_braces -= 1;
+                    self.advance();
+                    //失敗した宣言の本体が閉じたらそこで終わり
+                    if skipped_braces == 0 && skipped_block {
+                        return;
                     }
                 },
-                TokenKind::Greater => {
-                    self.eat(TokenKind::Greater)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.parse_term()?),
-                    }
+                _ if skipped_braces > 0 => {
+                    self.advance();
                 },
-                TokenKind::GreaterEqual => {
-                    self.eat(TokenKind::GreaterEqual)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.parse_term()?),
-                    }
+                TokenKind::RightBrace if open_braces == 0 && self.block_depth > 0 => return,
+                TokenKind::RightBrace => {
+                    open_braces = open_braces.saturating_sub(1);
+                    self.advance();
+                },
+                TokenKind::Let
+                | TokenKind::Fn
+                | TokenKind::Class
+                | TokenKind::If
+                | TokenKind::While
+                | TokenKind::For
+                | TokenKind::Return
+                | TokenKind::Print => return,
+                _ => {
+                    self.advance();
                 },
-                _ => break,
             }
         }
-        Ok(node)
     }
 
-    fn parse_term(&mut self) -> Result<Expr, ParseError> {
-        let mut node = self.parse_factor()?;
+    fn parse_declaration(&mut self) -> Result<Stmt, ParseError> {
+        match self.peek().token_kind {
+            TokenKind::Class => self.parse_class_decl(),
+            TokenKind::Let => self.parse_let_decl(),
+            TokenKind::Fn => Ok(Stmt::Fn(self.parse_fn_decl()?)),
+            _ => self.parse_statement(),
+        }
+    }
 
-        loop {
-            match self.peek().token_kind {
-                TokenKind::Plus => {
-                    self.eat(TokenKind::Plus)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.parse_factor()?)
-                    }
-                },
-                TokenKind::Minus => {
-                    self.eat(TokenKind::Minus)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.parse_factor()?),
-                    }
-                },
-                _ => break,
+    fn parse_let_decl(&mut self) -> Result<Stmt, ParseError> {
+        let doc = self.take_doc();
+        let start = self.peek().span;
+        self.eat(TokenKind::Let)?;
+        let name = self.eat_ident()?;
+
+        let initializer = match self.peek().token_kind {
+            TokenKind::Equal => {
+                self.eat(TokenKind::Equal)?;
+                Some(self.parse_expression()?)
+            },
+            _ => None,
+        };
+
+        self.eat(TokenKind::Semicolon)?;
+        Ok(Stmt::Let { name, initializer, doc, span: self.span_from(start) })
+    }
+
+    fn parse_class_decl(&mut self) -> Result<Stmt, ParseError> {
+        let doc = self.take_doc();
+        let start = self.peek().span;
+        self.eat(TokenKind::Class)?;
+        let name = self.eat_ident()?;
+
+        let superclass = match self.peek().token_kind {
+            TokenKind::Less => {
+                self.eat(TokenKind::Less)?;
+                Some(self.eat_ident()?)
+            },
+            _ => None,
+        };
+
+        let brace = self.peek().clone();
+        self.eat(TokenKind::LeftBrace)?;
+        let mut methods = vec![];
+        while !matches!(self.peek().token_kind, TokenKind::RightBrace | TokenKind::Eof) {
+            methods.push(self.parse_fn_decl()?);
+        }
+        self.eat_closing(TokenKind::RightBrace, &brace)?;
+
+        Ok(Stmt::Class(ClassDecl { name, superclass, methods, doc, span: self.span_from(start) }))
+    }
+
+    fn parse_fn_decl(&mut self) -> Result<Rc<FnDecl>, ParseError> {
+        let doc = self.take_doc();
+        let start = self.peek().span;
+        self.eat(TokenKind::Fn)?;
+        let name = self.eat_ident()?;
+
+        let paren = self.peek().clone();
+        self.eat(TokenKind::LeftParen)?;
+        let mut params = vec![];
+        if self.peek().token_kind != TokenKind::RightParen {
+            params.push(self.eat_ident()?);
+            while self.peek().token_kind == TokenKind::Comma {
+                self.eat(TokenKind::Comma)?;
+                params.push(self.eat_ident()?);
             }
         }
+        self.eat_closing(TokenKind::RightParen, &paren)?;
 
-        Ok(node)
+        let body = self.parse_block()?;
+        Ok(Rc::new(FnDecl { name, params, body, doc, span: self.span_from(start) }))
+    }
+
+    fn parse_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.peek().span;
+
+        match self.peek().token_kind {
+            TokenKind::Print => {
+                self.eat(TokenKind::Print)?;
+                let expr = self.parse_expression()?;
+                self.eat(TokenKind::Semicolon)?;
+                Ok(Stmt::Print { expr, span: self.span_from(start) })
+            },
+            TokenKind::LeftBrace => {
+                let stmts = self.parse_block()?;
+                Ok(Stmt::Block { stmts, span: self.span_from(start) })
+            },
+            TokenKind::If => self.parse_if_stmt(),
+            TokenKind::While => {
+                self.eat(TokenKind::While)?;
+                let condition = self.parse_expression()?;
+                let body = self.parse_block()?;
+                Ok(Stmt::While { condition, body, span: self.span_from(start) })
+            },
+            TokenKind::For => self.parse_for_stmt(),
+            TokenKind::Return => {
+                self.eat(TokenKind::Return)?;
+
+                let value = match self.peek().token_kind {
+                    TokenKind::Semicolon => None,
+                    _ => Some(self.parse_expression()?),
+                };
+
+                self.eat(TokenKind::Semicolon)?;
+                Ok(Stmt::Return { value, span: self.span_from(start) })
+            },
+            _ => {
+                let expr = self.parse_expression()?;
+                self.eat(TokenKind::Semicolon)?;
+                Ok(Stmt::Expression { expr, span: self.span_from(start) })
+            },
+        }
     }
 
-    fn parse_factor(&mut self) -> Result<Expr, ParseError> {
-        let mut node = self.parse_unary()?;
+    fn parse_block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let brace = self.peek().clone();
+        self.eat(TokenKind::LeftBrace)?;
+
+        let mut stmts = vec![];
+        self.block_depth += 1;
+        while !matches!(self.peek().token_kind, TokenKind::RightBrace | TokenKind::Eof) {
+            stmts.push(self.parse_declaration_or_recover());
+        }
+        self.block_depth -= 1;
+
+        self.eat_closing(TokenKind::RightBrace, &brace)?;
+        Ok(stmts)
+    }
+
+    fn parse_if_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.peek().span;
+        self.eat(TokenKind::If)?;
+        let condition = self.parse_expression()?;
+        let then_branch = self.parse_block()?;
+
+        let else_branch = match self.peek().token_kind {
+            TokenKind::Else => {
+                self.eat(TokenKind::Else)?;
+                Some(self.parse_block()?)
+            },
+            _ => None,
+        };
+
+        Ok(Stmt::If { condition, then_branch, else_branch, span: self.span_from(start) })
+    }
+
+    fn parse_for_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.peek().span;
+        self.eat(TokenKind::For)?;
+        if self.peek().token_kind != TokenKind::LeftParen {
+            return self.parse_for_in_stmt(start);
+        }
+        let paren = self.peek().clone();
+        self.eat(TokenKind::LeftParen)?;
+
+        let initializer = match self.peek().token_kind {
+            TokenKind::Semicolon => {
+                self.eat(TokenKind::Semicolon)?;
+                None
+            },
+            TokenKind::Let => Some(Box::new(self.parse_let_decl()?)),
+            _ => {
+                let expr = self.parse_expression()?;
+                self.eat(TokenKind::Semicolon)?;
+                let span = expr.span().to(self.previous().span);
+                Some(Box::new(Stmt::Expression { expr, span }))
+            },
+        };
+
+        let condition = match self.peek().token_kind {
+            TokenKind::Semicolon => None,
+            _ => Some(self.parse_expression()?),
+        };
+        self.eat(TokenKind::Semicolon)?;
+
+        let increment = match self.peek().token_kind {
+            TokenKind::RightParen => None,
+            _ => Some(self.parse_expression()?),
+        };
+        self.eat_closing(TokenKind::RightParen, &paren)?;
+
+        let body = self.parse_block()?;
+        Ok(Stmt::For { initializer, condition, increment, body, span: self.span_from(start) })
+    }
+
+    //forの後に(が来なければfor x in e { ... }
+    fn parse_for_in_stmt(&mut self, start: Span) -> Result<Stmt, ParseError> {
+        let variable = self.eat_ident()?;
+        self.eat(TokenKind::In)?;
+
+        let iterable = self.parse_expression()?;
+        let body = self.parse_block()?;
+
+        Ok(Stmt::ForIn {
+            variable,
+            iterable,
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    pub fn parse_expression(&mut self) -> Result<Expr, ParseError> {
+        self.parse_expr_bp(0)
+    }
+
+    //Pratt構文解析。min_bpより弱く結びつく演算子が来たら呼び出し元に返す
+    fn parse_expr_bp(&mut self, min_bp: u8) -> Result<Expr, ParseError> {
+        let first = self.peek().clone();
+
+        let mut node = match binding_power(&first.token_kind).prefix {
+            Some(r_bp) => {
+                self.advance();
+                let operator = token_to_operator(&first)?;
+                let operand = Box::new(self.parse_expr_bp(r_bp)?);
+                let span = operator.span.to(operand.span());
+                Expr::UnaryOp { operator, operand, span }
+            },
+            None => self.parse_primary()?,
+        };
 
         loop {
-            match self.peek().token_kind {
-                TokenKind::Star => {
-                    self.eat(TokenKind::Star)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.parse_comparison()?)
-                    }
-                },
-                TokenKind::Slash => {
-                    self.eat(TokenKind::Slash)?;
-                    node = Expr::BinaryOp {
-                        left: Box::new(node),
-                        operator: token_to_operator(self.previous())?,
-                        right: Box::new(self.parse_comparison()?),
-                    }
-                },
-                _ => break,
+            let token = self.peek().clone();
+            let power = binding_power(&token.token_kind);
+
+            if let Some(l_bp) = power.postfix {
+                if l_bp < min_bp {
+                    break;
+                }
+                node = self.parse_postfix(node)?;
+                continue;
+            }
+
+            let Some((l_bp, r_bp)) = power.infix else { break };
+            if l_bp < min_bp {
+                break;
             }
+
+            self.advance();
+            let right = self.parse_expr_bp(r_bp)?;
+            node = match token.token_kind {
+                TokenKind::Equal => assignment(node, None, right)?,
+                TokenKind::PlusEqual | TokenKind::MinusEqual | TokenKind::StarEqual | TokenKind::SlashEqual => {
+                    assignment(node, Some(token_to_operator(&token)?), right)?
+                },
+                _ => binary(node, token_to_operator(&token)?, right),
+            };
         }
 
         Ok(node)
     }
 
-    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
-        match self.peek().token_kind {
-            TokenKind::Bang => {
-                self.eat(TokenKind::Bang)?;
-                Ok(Expr::UnaryOp {
-                    operator: token_to_operator(self.previous())?,
-                    operand: Box::new(self.parse_unary()?),
-                })
-            },
-            TokenKind::Minus => {
-                self.eat(TokenKind::Minus)?;
-                Ok(Expr::UnaryOp {
-                    operator: token_to_operator(self.previous())?,
-                    operand: Box::new(self.parse_unary()?),
-                })
-            },
-            _ => self.parse_primary()
+    //呼び出し、プロパティアクセス、添字
+    fn parse_postfix(&mut self, node: Expr) -> Result<Expr, ParseError> {
+        if self.peek().token_kind == TokenKind::Dot {
+            self.eat(TokenKind::Dot)?;
+            let name = self.eat_ident()?;
+            let span = node.span().to(name.span);
+            return Ok(Expr::Get { object: Box::new(node), name, span });
+        }
+
+        if self.peek().token_kind == TokenKind::LeftBracket {
+            let bracket = self.peek().clone();
+            self.eat(TokenKind::LeftBracket)?;
+            let index = Box::new(self.parse_expression()?);
+            self.eat_closing(TokenKind::RightBracket, &bracket)?;
+
+            return Ok(Expr::Index {
+                span: self.span_from(node.span()),
+                object: Box::new(node),
+                index,
+                bracket,
+            });
+        }
+
+        let paren = self.peek().clone();
+        self.eat(TokenKind::LeftParen)?;
+
+        let mut args = vec![];
+        if self.peek().token_kind != TokenKind::RightParen {
+            args.push(self.parse_expression()?);
+            while self.peek().token_kind == TokenKind::Comma {
+                self.eat(TokenKind::Comma)?;
+                args.push(self.parse_expression()?);
+            }
         }
+        self.eat_closing(TokenKind::RightParen, &paren)?;
+
+        Ok(Expr::Call {
+            span: self.span_from(node.span()),
+            callee: Box::new(node),
+            args,
+            paren,
+        })
     }
 
     fn parse_primary(&mut self) -> Result<Expr, ParseError> {
@@ -283,25 +534,78 @@ impl Parser {
         match &current_token.token_kind {
             TokenKind::Literal { kind } => {
                 self.eat(TokenKind::Literal { kind: kind.clone() })?;
-                Ok(Expr::Literal { kind: kind.clone() })
+                Ok(Expr::Literal { kind: kind.clone(), span: current_token.span })
             },
             TokenKind::LeftParen => {
                 self.eat(TokenKind::LeftParen)?;
-                let node = Expr::Grouping(Box::new(self.parse_expression()?));
-                self.eat(TokenKind::RightParen)?;
-                Ok(node)
+                let expr = Box::new(self.parse_expression()?);
+                self.eat_closing(TokenKind::RightParen, &current_token)?;
+                Ok(Expr::Grouping { expr, span: self.span_from(current_token.span) })
+            },
+            TokenKind::Nil => {
+                self.eat(TokenKind::Nil)?;
+                Ok(Expr::Literal { kind: LiteralKind::Nil, span: current_token.span })
+            },
+            TokenKind::This => {
+                self.eat(TokenKind::This)?;
+                Ok(Expr::This {
+                    keyword: token_to_identifier(&current_token, "this".to_string()),
+                    depth: Cell::new(None),
+                })
+            },
+            TokenKind::Super => {
+                self.eat(TokenKind::Super)?;
+                self.eat(TokenKind::Dot)?;
+                let method = self.eat_ident()?;
+                Ok(Expr::Super {
+                    keyword: token_to_identifier(&current_token, "super".to_string()),
+                    depth: Cell::new(None),
+                    span: current_token.span.to(method.span),
+                    method,
+                })
+            },
+            TokenKind::StringPart(_) => self.parse_interpolation(),
+            TokenKind::LeftBracket => {
+                self.eat(TokenKind::LeftBracket)?;
+
+                let mut elements = vec![];
+                if self.peek().token_kind != TokenKind::RightBracket {
+                    elements.push(self.parse_expression()?);
+                    while self.peek().token_kind == TokenKind::Comma {
+                        self.eat(TokenKind::Comma)?;
+                        elements.push(self.parse_expression()?);
+                    }
+                }
+                self.eat_closing(TokenKind::RightBracket, &current_token)?;
+
+                Ok(Expr::List { elements, span: self.span_from(current_token.span) })
+            },
+            TokenKind::HashBrace => {
+                self.eat(TokenKind::HashBrace)?;
+
+                let mut entries = vec![];
+                if self.peek().token_kind != TokenKind::RightBrace {
+                    entries.push(self.parse_map_entry()?);
+                    while self.peek().token_kind == TokenKind::Comma {
+                        self.eat(TokenKind::Comma)?;
+                        entries.push(self.parse_map_entry()?);
+                    }
+                }
+                self.eat_closing(TokenKind::RightBrace, &current_token)?;
+
+                Ok(Expr::Map { entries, span: self.span_from(current_token.span), brace: current_token })
             },
             TokenKind::Ident(ident) => {
                 self.eat(TokenKind::Ident(ident.to_string()))?;
 
-                Ok(Expr::Variable(ident.to_string()))
+                let name = token_to_identifier(&current_token, ident.to_string());
+                Ok(Expr::Variable { span: name.span, name, depth: Cell::new(None) })
             },
             _ => Err(
                 UnexpectedToken::new(
                     current_token.token_kind.clone(),
                     None,
-                    current_token.line,
-                    current_token.column
+                    current_token.span,
                 )
             )?
 
@@ -309,6 +613,121 @@ impl Parser {
     }
 
 
+
+    fn parse_map_entry(&mut self) -> Result<(Expr, Expr), ParseError> {
+        let key = self.parse_expression()?;
+        self.eat(TokenKind::Colon)?;
+        let value = self.parse_expression()?;
+        Ok((key, value))
+    }
+
+    //文字列の部分はExpr::Literalとして式の間に挟む
+    fn parse_interpolation(&mut self) -> Result<Expr, ParseError> {
+        let start = self.peek().span;
+        let mut parts = vec![];
+
+        loop {
+            let token = self.peek().clone();
+            match token.token_kind {
+                TokenKind::StringPart(str) => {
+                    self.advance();
+                    parts.push(Expr::Literal { kind: LiteralKind::String(str), span: token.span });
+                    parts.push(self.parse_expression()?);
+                },
+                TokenKind::StringEnd(str) => {
+                    self.advance();
+                    parts.push(Expr::Literal { kind: LiteralKind::String(str), span: token.span });
+                    break;
+                },
+                token_kind => Err(
+                    UnexpectedToken::new(token_kind, None, token.span)
+                )?,
+            }
+        }
+
+        Ok(Expr::Interpolation { parts, span: self.span_from(start) })
+    }
+}
+
+//演算子の結合力の表。数値が大きいほど強く結びつく
+//段階はebnf.txtのAssignmentからCallまでに対応する
+const ASSIGNMENT: u8 = 1;
+const OR: u8 = 3;
+const AND: u8 = 5;
+const EQUALITY: u8 = 7;
+const COMPARISON: u8 = 9;
+const TERM: u8 = 11;
+const FACTOR: u8 = 13;
+const UNARY: u8 = 15;
+const CALL: u8 = 17;
+
+//前置は右の結合力、中置は(左, 右)、後置は左の結合力
+struct BindingPower {
+    prefix: Option<u8>,
+    infix: Option<(u8, u8)>,
+    postfix: Option<u8>,
+}
+
+fn binding_power(token_kind: &TokenKind) -> BindingPower {
+    let (prefix, infix, postfix) = match token_kind {
+        TokenKind::Equal
+        | TokenKind::PlusEqual
+        | TokenKind::MinusEqual
+        | TokenKind::StarEqual
+        | TokenKind::SlashEqual => (None, right(ASSIGNMENT), None),
+        TokenKind::Or => (None, left(OR), None),
+        TokenKind::And => (None, left(AND), None),
+        TokenKind::EqualEqual | TokenKind::BangEqual => (None, left(EQUALITY), None),
+        TokenKind::Less
+        | TokenKind::LessEqual
+        | TokenKind::Greater
+        | TokenKind::GreaterEqual => (None, left(COMPARISON), None),
+        TokenKind::Plus => (None, left(TERM), None),
+        TokenKind::Minus => (Some(UNARY), left(TERM), None),
+        TokenKind::Star
+        | TokenKind::Slash
+        | TokenKind::TildeSlash
+        | TokenKind::Percent => (None, left(FACTOR), None),
+        TokenKind::Bang => (Some(UNARY), None, None),
+        TokenKind::LeftParen | TokenKind::Dot | TokenKind::LeftBracket => (None, None, Some(CALL)),
+        _ => (None, None, None),
+    };
+
+    BindingPower { prefix, infix, postfix }
+}
+
+//右の結合力を1つ強くすると同じ演算子が続いたとき左から結びつく
+fn left(bp: u8) -> Option<(u8, u8)> {
+    Some((bp, bp + 1))
+}
+
+fn right(bp: u8) -> Option<(u8, u8)> {
+    Some((bp + 1, bp))
+}
+
+//左辺を式として読んでから代入先に変える。書けるのは変数、プロパティ、添字だけ
+fn assignment(target: Expr, operator: Option<Operator>, value: Expr) -> Result<Expr, ParseError> {
+    let span = target.span().to(value.span());
+
+    match target {
+        Expr::Variable { name, depth, span: target_span } => {
+            //変数の読み出しには副作用がないので a += b は a = a + b にしてよい
+            let value = match operator {
+                Some(operator) => {
+                    let variable = Expr::Variable { name: name.clone(), depth: Cell::new(None), span: target_span };
+                    binary(variable, operator, value)
+                },
+                None => value,
+            };
+            Ok(Expr::Assign { name, expr: Box::new(value), depth, span })
+        },
+        //オブジェクトや添字の式は一度だけ評価したいので演算子はSetとIndexSetに持たせる
+        Expr::Get { object, name, .. } => Ok(Expr::Set { object, name, operator, value: Box::new(value), span }),
+        Expr::Index { object, index, bracket, .. } => {
+            Ok(Expr::IndexSet { object, index, operator, value: Box::new(value), bracket, span })
+        },
+        target => Err(InvalidAssignmentTarget::new(target.span()))?,
+    }
 }
 
 fn token_to_operator(token: &Token) -> Result<Operator, ParseError> {
@@ -322,19 +741,29 @@ fn token_to_operator(token: &Token) -> Result<Operator, ParseError> {
         TokenKind::LessEqual => OperatorKind::LessEqual,
         TokenKind::Greater => OperatorKind::Greater,
         TokenKind::GreaterEqual => OperatorKind::GreaterEqual,
-        TokenKind::Plus => OperatorKind::Add,
-        TokenKind::Minus => OperatorKind::Subtract,
-        TokenKind::Star => OperatorKind::Multiply,
-        TokenKind::Slash => OperatorKind::Divide,
+        TokenKind::Plus | TokenKind::PlusEqual => OperatorKind::Add,
+        TokenKind::Minus | TokenKind::MinusEqual => OperatorKind::Subtract,
+        TokenKind::Star | TokenKind::StarEqual => OperatorKind::Multiply,
+        TokenKind::Slash | TokenKind::SlashEqual => OperatorKind::Divide,
+        TokenKind::TildeSlash => OperatorKind::IntDivide,
+        TokenKind::Percent => OperatorKind::Modulo,
         _ => {
             Err(UnexpectedToken::new(
                 token.token_kind.clone(),
                 None,
-                token.line,
-                token.column,
+                token.span,
             ))?
         },
     };
 
-    Ok(Operator { op_kind, pos: token.pos, line: token.line, column: token.column })
-}
\ No newline at end of file
+    Ok(Operator { op_kind, span: token.span })
+}
+
+fn token_to_identifier(token: &Token, name: String) -> Identifier {
+    Identifier { name, span: token.span }
+}
+
+fn binary(left: Expr, operator: Operator, right: Expr) -> Expr {
+    let span = left.span().to(right.span());
+    Expr::BinaryOp { left: Box::new(left), operator, right: Box::new(right), span }
+}
@@ -1,4 +1,8 @@
-use crate::syntax::{token::LiteralKind, Token, TokenKind};
+use crate::{
+    rloxs_lexer::Lexer,
+    syntax::{token::LiteralKind, Expr, Span, Stmt, Token, TokenKind},
+    test_support::summary,
+};
 
 use super::parser::Parser;
 
@@ -7,42 +11,599 @@ fn feature() {
     let tokens = vec![
         Token {
             token_kind: TokenKind::Literal {
-                kind: LiteralKind::Number(1.0)
+                kind: LiteralKind::Integer(1)
             },
-            pos: 0,
-            line: 1,
-            column: 0,
+            span: Span::new(0, 1),
         },
         Token {
             token_kind: TokenKind::Plus,
-            pos: 2,
-            line: 1,
-            column: 2,
+            span: Span::new(2, 3),
         },
         Token {
             token_kind: TokenKind::Literal {
-                kind: LiteralKind::Number(2.0)
+                kind: LiteralKind::Integer(2)
             },
-            pos: 4,
-            line: 1,
-            column: 4,
+            span: Span::new(4, 5),
         },
         Token {
             token_kind: TokenKind::Star,
-            pos: 6,
-            line: 1,
-            column: 6,
+            span: Span::new(6, 7),
         },
         Token {
             token_kind: TokenKind::Literal {
-                kind: LiteralKind::Number(3.0)
+                kind: LiteralKind::Integer(3)
             },
-            pos: 8,
-            line: 1,
-            column: 8,
+            span: Span::new(8, 9),
         },
     ];
 
     let mut parser = Parser::new(tokens);
     let _ast = parser.parse_expression();
-}
\ No newline at end of file
+}
+fn parse_program_helper(input: &str) -> Vec<Stmt> {
+    let tokens = Lexer::new(input).lex().unwrap();
+    Parser::new(tokens).parse_program().unwrap()
+}
+
+#[test]
+fn parse_declarations() {
+    let program = parse_program_helper(r#"
+let x = 1;
+let y;
+fn add(a, b) {
+    return a + b;
+}
+"#);
+
+    assert_eq!(program.len(), 3);
+    assert!(matches!(&program[0], Stmt::Let { name, initializer: Some(_), .. } if name.name == "x"));
+    assert!(matches!(&program[1], Stmt::Let { name, initializer: None, .. } if name.name == "y"));
+
+    match &program[2] {
+        Stmt::Fn(decl) => {
+            assert_eq!(decl.name.name, "add");
+            let params: Vec<&str> = decl.params.iter().map(|p| p.name.as_str()).collect();
+            assert_eq!(params, vec!["a", "b"]);
+            assert!(matches!(&decl.body[..], [Stmt::Return { value: Some(_), span }] if span.start == 38));
+        },
+        stmt => panic!("expected fn declaration, got {:?}", stmt),
+    }
+}
+
+#[test]
+fn parse_control_flow() {
+    let program = parse_program_helper(r#"
+if x { print 1; } else { print 2; }
+while x { x = x - 1; }
+for (let i = 0; i < 3; i = i + 1) { print i; }
+for (;;) {}
+{ let a = 1; }
+"#);
+
+    assert_eq!(program.len(), 5);
+    assert!(matches!(&program[0], Stmt::If { then_branch, else_branch: Some(else_branch), .. }
+        if then_branch.len() == 1 && else_branch.len() == 1));
+    assert!(matches!(&program[1], Stmt::While { body, .. } if body.len() == 1));
+    assert!(matches!(&program[2], Stmt::For {
+        initializer: Some(_),
+        condition: Some(_),
+        increment: Some(_),
+        ..
+    }));
+    assert!(matches!(&program[3], Stmt::For {
+        initializer: None,
+        condition: None,
+        increment: None,
+        ..
+    }));
+    assert!(matches!(&program[4], Stmt::Block { stmts, .. } if stmts.len() == 1));
+}
+
+#[test]
+fn parse_for_in() {
+    let program = parse_program_helper("for x in xs.iter() { print x; }");
+    assert!(matches!(&program[0], Stmt::ForIn { variable, iterable: iterable @ Expr::Call { .. }, body, .. }
+        if variable.name == "x" && iterable.span() == Span::new(9, 18) && body.len() == 1));
+
+    let (_, errors) = parse_errors_helper("for x xs {}\nfor in xs {}");
+    assert_eq!(errors, [
+        "Unexpected token: identifier 'xs', expected: 'in' at [1:6]",
+        "Unexpected token: 'in' at [2:4]",
+    ]);
+}
+
+#[test]
+fn parse_missing_semicolon() {
+    let source = "print 1";
+    let tokens = Lexer::new(source).lex().unwrap();
+    let err = Parser::new(tokens).parse_program().unwrap_err();
+
+    assert_eq!(err.len(), 1);
+    assert_eq!(summary(&err[0].diagnostic(), source), "Unexpected token: end of file, expected: ';' at [1:7]");
+}
+
+#[test]
+fn parse_call_chain() {
+    let program = parse_program_helper("make()(1, 2);");
+
+    match &program[..] {
+        [Stmt::Expression { expr: Expr::Call { callee, args, paren, .. }, .. }] => {
+            assert_eq!(paren.span, Span::new(6, 7));
+            assert_eq!(args.len(), 2);
+            assert!(matches!(callee.as_ref(), Expr::Call { args, .. } if args.is_empty()));
+        },
+        program => panic!("expected call expression, got {:?}", program),
+    }
+}
+
+#[test]
+fn parse_class_declaration() {
+    let program = parse_program_helper(r#"
+class B < A {
+    fn init(x) { this.x = x; }
+    fn get() { return super.get(); }
+}
+"#);
+
+    match &program[..] {
+        [Stmt::Class(decl)] => {
+            assert_eq!(decl.name.name, "B");
+            assert_eq!(decl.superclass.as_ref().map(|s| s.name.as_str()), Some("A"));
+            let methods: Vec<&str> = decl.methods.iter().map(|m| m.name.name.as_str()).collect();
+            assert_eq!(methods, vec!["init", "get"]);
+            assert!(matches!(&decl.methods[0].body[..], [Stmt::Expression { expr: Expr::Set { .. }, .. }]));
+            assert!(matches!(
+                &decl.methods[1].body[..],
+                [Stmt::Return { value: Some(Expr::Call { callee, .. }), .. }]
+                    if matches!(callee.as_ref(), Expr::Super { method, .. } if method.name == "get")
+            ));
+        },
+        program => panic!("expected class declaration, got {:?}", program),
+    }
+}
+
+#[test]
+fn parse_invalid_assignment_target() {
+    let source = "a + b = c;";
+    let tokens = Lexer::new(source).lex().unwrap();
+    let err = Parser::new(tokens).parse_program().unwrap_err();
+
+    assert_eq!(err.len(), 1);
+    assert_eq!(summary(&err[0].diagnostic(), source), "Invalid assignment target at [1:0]");
+    assert_eq!(err[0].diagnostic().label.unwrap().span, Span::new(0, 5));
+
+    let (_, errors) = parse_errors_helper("(a) = 3;
+f() += 1;
+let x = 1 = 2;
+a.b() = c;");
+    assert_eq!(errors, [
+        "Invalid assignment target at [1:0]",
+        "Invalid assignment target at [2:0]",
+        "Invalid assignment target at [3:8]",
+        "Invalid assignment target at [4:0]",
+    ]);
+}
+
+fn parse_errors_helper(input: &str) -> (Vec<Stmt>, Vec<String>) {
+    let tokens = Lexer::new(input).lex().unwrap();
+    let (program, errors) = Parser::new(tokens).parse();
+    (program, errors.iter().map(|e| summary(&e.diagnostic(), input)).collect())
+}
+
+#[test]
+fn parse_reports_every_error() {
+    let (program, errors) = parse_errors_helper(r#"
+let = 1;
+print 2;
+let b = (3;
+print 4
+let c = 5;
+"#);
+
+    assert_eq!(errors, vec![
+        "Unexpected token: '=' at [2:4]",
+        "Unexpected token: ';', expected: ')' at [4:10]",
+        "Unexpected token: 'let', expected: ';' at [6:0]",
+    ]);
+    assert!(matches!(&program[..], [
+        Stmt::Error { .. },
+        Stmt::Print { .. },
+        Stmt::Error { .. },
+        Stmt::Error { .. },
+        Stmt::Let { name, .. },
+    ] if name.name == "c"));
+}
+
+#[test]
+fn parse_recovers_inside_blocks() {
+    let (program, errors) = parse_errors_helper(r#"
+fn f() {
+    let x = ;
+    return 1;
+}
+while x { print +; print x; }
+"#);
+
+    assert_eq!(errors, vec![
+        "Unexpected token: ';' at [3:12]",
+        "Unexpected token: '+' at [6:16]",
+    ]);
+    match &program[..] {
+        [Stmt::Fn(decl), Stmt::While { body, .. }] => {
+            assert!(matches!(&decl.body[..], [Stmt::Error { .. }, Stmt::Return { .. }]));
+            assert!(matches!(&body[..], [Stmt::Error { .. }, Stmt::Print { .. }]));
+        },
+        program => panic!("expected function and while loop, got {:?}", program),
+    }
+}
+
+#[test]
+fn parse_recovery_stops_at_closing_brace() {
+    let (program, errors) = parse_errors_helper("class C { fn m() { return 1 } }");
+
+    assert_eq!(errors, vec!["Unexpected token: '}', expected: ';' at [1:28]"]);
+    match &program[..] {
+        [Stmt::Class(decl)] => assert!(matches!(&decl.methods[0].body[..], [Stmt::Error { .. }])),
+        program => panic!("expected a class, got {:?}", program),
+    }
+
+    let (_, errors) = parse_errors_helper("fn f() { let m = #{1: }; print m; }");
+    assert_eq!(errors, vec!["Unexpected token: '}' at [1:22]"]);
+}
+
+#[test]
+fn parse_recovery_skips_body_after_failed_header() {
+    let (program, errors) = parse_errors_helper("fn f(a b) { print 1; print 2; }");
+    assert_eq!(errors, vec!["Unexpected token: identifier 'b', expected: ')' at [1:7]"]);
+    assert!(matches!(&program[..], [Stmt::Error { .. }]));
+
+    //本体が閉じたら次の宣言から読み直す
+    let (program, errors) = parse_errors_helper("class C < { fn m() { return 1; } }\nprint 2;");
+    assert_eq!(errors, vec!["Unexpected token: '{' at [1:10]"]);
+    assert!(matches!(&program[..], [Stmt::Error { .. }, Stmt::Print { .. }]));
+}
+
+#[test]
+fn parse_recovery_always_makes_progress() {
+    let (program, errors) = parse_errors_helper(") ) let a = 1;");
+
+    assert_eq!(errors, vec!["Unexpected token: ')' at [1:0]"]);
+    assert!(matches!(&program[..], [Stmt::Error { .. }, Stmt::Let { .. }]));
+}
+
+#[test]
+fn parse_node_spans() {
+    let source = "let a = -b + f(1, 2).c;\nif a { print (a); }";
+    let program = parse_program_helper(source);
+    let text = |span: Span| &source[span.start..span.end];
+
+    assert_eq!(text(program[0].span()), "let a = -b + f(1, 2).c;");
+    assert_eq!(text(program[1].span()), "if a { print (a); }");
+
+    let Stmt::Let { initializer: Some(expr), .. } = &program[0] else { panic!("expected let") };
+    let Expr::BinaryOp { left, right, .. } = expr else { panic!("expected binary op") };
+    assert_eq!(text(expr.span()), "-b + f(1, 2).c");
+    assert_eq!(text(left.span()), "-b");
+    assert_eq!(text(right.span()), "f(1, 2).c");
+
+    let Stmt::If { then_branch, .. } = &program[1] else { panic!("expected if") };
+    let Stmt::Print { expr, .. } = &then_branch[0] else { panic!("expected print") };
+    assert_eq!(text(then_branch[0].span()), "print (a);");
+    assert_eq!(text(expr.span()), "(a)");
+}
+
+#[test]
+fn parse_interpolation() {
+    let source = r#"print "a ${b + 1} c";"#;
+    let program = parse_program_helper(source);
+
+    let [Stmt::Print { expr: Expr::Interpolation { parts, span }, .. }] = &program[..] else {
+        panic!("expected interpolation, got {:?}", program)
+    };
+    assert_eq!(&source[span.start..span.end], r#""a ${b + 1} c""#);
+    assert!(matches!(&parts[..], [
+        Expr::Literal { kind: LiteralKind::String(a), .. },
+        Expr::BinaryOp { .. },
+        Expr::Literal { kind: LiteralKind::String(c), .. },
+    ] if a == "a " && c == " c"));
+}
+
+#[test]
+fn parse_empty_interpolation() {
+    let (_, errors) = parse_errors_helper(r#"print "a ${} b";"#);
+    assert_eq!(errors, vec!["Unexpected token: end of string at [1:11]"]);
+}
+
+#[test]
+fn parse_doc_comments() {
+    let program = parse_program_helper(r#"
+/// 挨拶する
+/// 2行目
+fn greet() {}
+
+/// クラス
+class A {
+    /// メソッド
+    fn m() {}
+}
+
+/** 普通のコメント */
+let x = 1 + /// 式の途中は無視する
+    2;
+
+/// 変数
+// 普通のコメントを挟んでもよい
+let y;
+"#);
+
+    let Stmt::Fn(greet) = &program[0] else { panic!("expected fn") };
+    assert_eq!(greet.doc.as_deref(), Some("挨拶する\n2行目"));
+
+    let Stmt::Class(class) = &program[1] else { panic!("expected class") };
+    assert_eq!(class.doc.as_deref(), Some("クラス"));
+    assert_eq!(class.methods[0].doc.as_deref(), Some("メソッド"));
+
+    assert!(matches!(&program[2], Stmt::Let { doc: None, .. }));
+    assert!(matches!(&program[3], Stmt::Let { doc: Some(doc), .. } if doc == "変数"));
+}
+
+#[test]
+fn cst_round_trips_source() {
+    let sources = [
+        "",
+        "   \n\n",
+        "// only a comment",
+        "let x = 1; // one\nprint x + 2;\n",
+        "/// 挨拶する\nfn greet(name) {\n    print \"hello ${name}!\";\n}\n",
+        "class A < B {\n  /* nested /* block */ comment */\n  m() { return super.m() * (this.x ~/ 2); }\n}\r\n",
+        "for (let i = 0; i < 10; i = i + 1) { if (i % 2 == 0) print i; else { print -i; } }",
+        "while (!done)\tdone = check(a, b.c, \"${x + \"${y}\"}\");\n",
+        "let = 1;\nprint (1 + ;\nlet ok = 2;   ",
+        "print \"unterminated\n",
+        "let a = 1 @ 2; /* unterminated",
+        "let 名前 = \"ユニコード\"; // コメント\n",
+        "let xs = [1, [2 , 3],\n  4];\nxs[0] += xs [ -1 ];",
+        "let m = #{ \"a\" : 1,\n  2: #{} };\n{ m[\"a\"] = nil; }",
+        "for  k in m.keys() {\n  print k; // key\n}\n",
+    ];
+
+    for source in sources {
+        let (cst, _) = crate::rloxs_parser::cst::parse_cst(source);
+        assert_eq!(cst.to_string(), source);
+    }
+}
+
+#[test]
+fn cst_keeps_punctuation_in_nodes() {
+    use crate::rloxs_parser::cst::{parse_cst, NodeKind, SyntaxElement};
+
+    let (cst, diagnostics) = parse_cst("print (1 + 2); // done\n");
+    assert!(diagnostics.is_empty());
+    assert_eq!(cst.kind, NodeKind::Program);
+
+    let SyntaxElement::Node(print) = &cst.children[0] else { panic!("expected node") };
+    assert_eq!(print.kind, NodeKind::PrintStmt);
+    assert_eq!(print.to_string(), "print (1 + 2); // done\n");
+
+    let kinds = print.children.iter().map(|child| match child {
+        SyntaxElement::Node(node) => format!("{:?}", node.kind),
+        SyntaxElement::Token(token) => token.text.clone(),
+    }).collect::<Vec<_>>();
+    assert_eq!(kinds, ["print", "Grouping", ";"]);
+
+    let SyntaxElement::Node(grouping) = &print.children[1] else { panic!("expected node") };
+    assert_eq!(grouping.span, Span::new(6, 13));
+    assert!(matches!(&grouping.children[..], [
+        SyntaxElement::Token(_), SyntaxElement::Node(binary), SyntaxElement::Token(_)
+    ] if binary.kind == NodeKind::Binary));
+
+    //末尾のEofはProgram直下に置く
+    assert!(matches!(cst.children.last(), Some(SyntaxElement::Token(t)) if t.token.token_kind == TokenKind::Eof));
+}
+
+#[test]
+fn cst_reports_errors() {
+    let (_, diagnostics) = crate::rloxs_parser::cst::parse_cst("let = 1;");
+    assert_eq!(diagnostics.len(), 1);
+
+    //字句エラーがあれば構文エラーは出さない
+    let (_, diagnostics) = crate::rloxs_parser::cst::parse_cst("let = @;");
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].message.contains("Unexpected character"));
+}
+
+//括弧でくくった前置記法に直して木の形だけを比べる
+fn sexp(expr: &Expr) -> String {
+    match expr {
+        Expr::Assign { name, expr, .. } => format!("(= {} {})", name.name, sexp(expr)),
+        Expr::Literal { kind, .. } => match kind {
+            LiteralKind::Integer(n) => n.to_string(),
+            LiteralKind::Bool(b) => b.to_string(),
+            LiteralKind::Nil => "nil".to_string(),
+            kind => format!("{:?}", kind),
+        },
+        Expr::Variable { name, .. } => name.name.clone(),
+        Expr::BinaryOp { left, operator, right, .. } => {
+            format!("({} {} {})", operator.op_kind, sexp(left), sexp(right))
+        },
+        Expr::UnaryOp { operator, operand, .. } => format!("({} {})", operator.op_kind, sexp(operand)),
+        Expr::Grouping { expr, .. } => format!("(group {})", sexp(expr)),
+        Expr::Call { callee, args, .. } => {
+            let args = args.iter().map(|arg| format!(" {}", sexp(arg))).collect::<String>();
+            format!("(call {}{})", sexp(callee), args)
+        },
+        Expr::Get { object, name, .. } => format!("(. {} {})", sexp(object), name.name),
+        Expr::Set { object, name, operator, value, .. } => {
+            let operator = operator.as_ref().map(|operator| operator.op_kind.to_string()).unwrap_or_default();
+            format!("({}=. {} {} {})", operator, sexp(object), name.name, sexp(value))
+        },
+        Expr::This { .. } => "this".to_string(),
+        Expr::Super { method, .. } => format!("(super {})", method.name),
+        Expr::Interpolation { parts, .. } => {
+            let parts = parts.iter().map(|part| format!(" {}", sexp(part))).collect::<String>();
+            format!("(interp{})", parts)
+        },
+        Expr::List { elements, .. } => {
+            let elements = elements.iter().map(sexp).collect::<Vec<_>>();
+            format!("[{}]", elements.join(" "))
+        },
+        Expr::Map { entries, .. } => {
+            let entries = entries.iter().map(|(key, value)| format!("{}: {}", sexp(key), sexp(value)));
+            format!("#{{{}}}", entries.collect::<Vec<_>>().join(" "))
+        },
+        Expr::Index { object, index, .. } => format!("([] {} {})", sexp(object), sexp(index)),
+        Expr::IndexSet { object, index, operator, value, .. } => {
+            let operator = operator.as_ref().map(|operator| operator.op_kind.to_string()).unwrap_or_default();
+            format!("({}=[] {} {} {})", operator, sexp(object), sexp(index), sexp(value))
+        },
+    }
+}
+
+fn sexp_helper(input: &str) -> String {
+    let tokens = Lexer::new(input).lex().unwrap();
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse_expression().unwrap();
+    assert_eq!(parser.advance().token_kind, TokenKind::Eof, "{} was not fully parsed", input);
+    sexp(&expr)
+}
+
+#[test]
+fn parse_precedence_follows_grammar() {
+    //ebnf.txtの段階を弱い順に、隣り合う段階どうしで両方の並びを確かめる
+    let cases = [
+        ("a = b or c", "(= a (or b c))"),
+        ("a or b and c", "(or a (and b c))"),
+        ("a and b or c", "(or (and a b) c)"),
+        ("a and b == c", "(and a (== b c))"),
+        ("a == b and c", "(and (== a b) c)"),
+        ("a == b < c", "(== a (< b c))"),
+        ("a < b == c", "(== (< a b) c)"),
+        ("a < b + c", "(< a (+ b c))"),
+        ("a + b <= c", "(<= (+ a b) c)"),
+        ("a + b * c", "(+ a (* b c))"),
+        ("a * b - c", "(- (* a b) c)"),
+        ("a * -b", "(* a (- b))"),
+        ("-a * b", "(* (- a) b)"),
+        ("!a.b", "(! (. a b))"),
+        ("-f(x)", "(- (call f x))"),
+        //以前はFactorの右辺がComparisonとして読まれていた
+        ("2 * 3 < 4", "(< (* 2 3) 4)"),
+        ("a % b >= c", "(>= (% a b) c)"),
+        ("a ~/ b != c / d", "(!= (~/ a b) (/ c d))"),
+        ("(a + b) * c", "(* (group (+ a b)) c)"),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(sexp_helper(input), expected, "{}", input);
+    }
+}
+
+#[test]
+fn parse_associativity_follows_grammar() {
+    let cases = [
+        //二項演算子はすべて左結合
+        ("a or b or c", "(or (or a b) c)"),
+        ("a and b and c", "(and (and a b) c)"),
+        ("a == b != c", "(!= (== a b) c)"),
+        ("a < b <= c", "(<= (< a b) c)"),
+        ("a > b >= c", "(>= (> a b) c)"),
+        ("a - b + c", "(+ (- a b) c)"),
+        ("a / b * c", "(* (/ a b) c)"),
+        ("a % b ~/ c", "(~/ (% a b) c)"),
+        //前置演算子は重ねられる
+        ("!!a", "(! (! a))"),
+        ("- -a", "(- (- a))"),
+        //呼び出しとプロパティは左から順につながる
+        ("a.b(c).d", "(. (call (. a b) c) d)"),
+        ("f(a)(b, c)", "(call (call f a) b c)"),
+        //代入は右結合
+        ("a = b = c", "(= a (= b c))"),
+        ("a.b = c.d = e", "(=. a b (=. c d e))"),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(sexp_helper(input), expected, "{}", input);
+    }
+}
+
+#[test]
+fn parse_less_equal() {
+    assert_eq!(sexp_helper("a <= b"), "(<= a b)");
+
+    let source = "!a = b;";
+    let tokens = Lexer::new(source).lex().unwrap();
+    let err = Parser::new(tokens).parse_program().unwrap_err();
+    assert_eq!(summary(&err[0].diagnostic(), source), "Invalid assignment target at [1:0]");
+}
+
+#[test]
+fn parse_assignment_targets() {
+    let cases = [
+        ("a.b.c = 1", "(=. (. a b) c 1)"),
+        ("f().x = y", "(=. (call f) x y)"),
+        ("this.x = 1", "(=. this x 1)"),
+        //変数への複合代入は読み出しと二項演算に展開する
+        ("a += 1", "(= a (+ a 1))"),
+        ("a -= b * c", "(= a (- a (* b c)))"),
+        ("a *= b = 2", "(= a (* a (= b 2)))"),
+        ("a /= 2 or 3", "(= a (/ a (or 2 3)))"),
+        //プロパティへの複合代入は演算子をSetに持つ
+        ("f().x += 1", "(+=. (call f) x 1)"),
+        ("a.b -= c", "(-=. a b c)"),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(sexp_helper(input), expected, "{}", input);
+    }
+}
+
+#[test]
+fn parse_lists_and_indexing() {
+    let cases = [
+        ("[]", "[]"),
+        ("[1, a + b, [c]]", "[1 (+ a b) [c]]"),
+        ("xs[0]", "([] xs 0)"),
+        ("xs[i][j]", "([] ([] xs i) j)"),
+        ("-xs[0]", "(- ([] xs 0))"),
+        ("f()[0].x", "(. ([] (call f) 0) x)"),
+        ("[1, 2][i + 1]", "([] [1 2] (+ i 1))"),
+        ("xs[0] = ys[1] = 2", "(=[] xs 0 (=[] ys 1 2))"),
+        ("xs[i] += 1", "(+=[] xs i 1)"),
+        ("a.b[c] = d", "(=[] (. a b) c d)"),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(sexp_helper(input), expected, "{}", input);
+    }
+
+    let (_, errors) = parse_errors_helper("print [1, 2;\nxs[0;");
+    assert_eq!(errors, [
+        "Unexpected token: ';', expected: ']' at [1:11]",
+        "Unexpected token: ';', expected: ']' at [2:4]",
+    ]);
+}
+
+#[test]
+fn parse_maps() {
+    let cases = [
+        ("#{}", "#{}"),
+        ("#{\"a\": 1, b: c + 1}", "#{String(\"a\"): 1 b: (+ c 1)}"),
+        ("#{1: #{2: 3}}[1][2]", "([] ([] #{1: #{2: 3}} 1) 2)"),
+        ("m[k] = #{k: [1]}", "(=[] m k #{k: [1]})"),
+    ];
+
+    for (input, expected) in cases {
+        assert_eq!(sexp_helper(input), expected, "{}", input);
+    }
+
+    //{だけならブロックのまま
+    let (program, errors) = parse_errors_helper("{ print 1; }");
+    assert!(errors.is_empty());
+    assert!(matches!(program[0], Stmt::Block { .. }));
+
+    let (_, errors) = parse_errors_helper("print #{1 2};\nprint #{1: 2,};");
+    assert_eq!(errors, [
+        "Unexpected token: number 2, expected: ':' at [1:10]",
+        "Unexpected token: '}' at [2:13]",
+    ]);
+}
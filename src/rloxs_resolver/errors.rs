@@ -0,0 +1,242 @@
+use std::{error::Error, fmt::Display};
+
+use crate::{diagnostics::Diagnostic, syntax::Span};
+
+#[derive(Debug)]
+pub enum ResolveError {
+    ReadInOwnInitializer(ReadInOwnInitializer),
+    DuplicateDeclaration(DuplicateDeclaration),
+    ReturnOutsideFunction(ReturnOutsideFunction),
+    ReturnValueFromInitializer(ReturnValueFromInitializer),
+    ThisOutsideClass(ThisOutsideClass),
+    SuperOutsideSubclass(SuperOutsideSubclass),
+    InheritFromSelf(InheritFromSelf),
+}
+
+impl ResolveError {
+    pub fn diagnostic(&self) -> Diagnostic {
+        match self {
+            ResolveError::ReadInOwnInitializer(e) => e.diagnostic(),
+            ResolveError::DuplicateDeclaration(e) => e.diagnostic(),
+            ResolveError::ReturnOutsideFunction(e) => e.diagnostic(),
+            ResolveError::ReturnValueFromInitializer(e) => e.diagnostic(),
+            ResolveError::ThisOutsideClass(e) => e.diagnostic(),
+            ResolveError::SuperOutsideSubclass(e) => e.diagnostic(),
+            ResolveError::InheritFromSelf(e) => e.diagnostic(),
+        }
+    }
+}
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolveError::ReadInOwnInitializer(e) => write!(f, "{}", e),
+            ResolveError::DuplicateDeclaration(e) => write!(f, "{}", e),
+            ResolveError::ReturnOutsideFunction(e) => write!(f, "{}", e),
+            ResolveError::ReturnValueFromInitializer(e) => write!(f, "{}", e),
+            ResolveError::ThisOutsideClass(e) => write!(f, "{}", e),
+            ResolveError::SuperOutsideSubclass(e) => write!(f, "{}", e),
+            ResolveError::InheritFromSelf(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ResolveError {}
+
+#[derive(Debug)]
+pub struct ReadInOwnInitializer {
+    name: String,
+    span: Span,
+}
+
+impl ReadInOwnInitializer {
+    pub fn new(name: String, span: Span) -> Self {
+        Self { name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            format!("Cannot read local variable in its own initializer: {}", self.name),
+            self.span,
+        )
+    }
+}
+
+impl Display for ReadInOwnInitializer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<ReadInOwnInitializer> for ResolveError {
+    fn from(value: ReadInOwnInitializer) -> Self {
+        ResolveError::ReadInOwnInitializer(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct DuplicateDeclaration {
+    name: String,
+    span: Span,
+}
+
+impl DuplicateDeclaration {
+    pub fn new(name: String, span: Span) -> Self {
+        Self { name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            format!("Variable already declared in this scope: {}", self.name),
+            self.span,
+        )
+    }
+}
+
+impl Display for DuplicateDeclaration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<DuplicateDeclaration> for ResolveError {
+    fn from(value: DuplicateDeclaration) -> Self {
+        ResolveError::DuplicateDeclaration(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct ReturnOutsideFunction {
+    span: Span,
+}
+
+impl ReturnOutsideFunction {
+    pub fn new(span: Span) -> Self {
+        Self { span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new("Cannot return from top-level code".to_string(), self.span)
+    }
+}
+
+impl Display for ReturnOutsideFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<ReturnOutsideFunction> for ResolveError {
+    fn from(value: ReturnOutsideFunction) -> Self {
+        ResolveError::ReturnOutsideFunction(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct ReturnValueFromInitializer {
+    span: Span,
+}
+
+impl ReturnValueFromInitializer {
+    pub fn new(span: Span) -> Self {
+        Self { span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new("Cannot return a value from an initializer".to_string(), self.span)
+    }
+}
+
+impl Display for ReturnValueFromInitializer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<ReturnValueFromInitializer> for ResolveError {
+    fn from(value: ReturnValueFromInitializer) -> Self {
+        ResolveError::ReturnValueFromInitializer(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct ThisOutsideClass {
+    span: Span,
+}
+
+impl ThisOutsideClass {
+    pub fn new(span: Span) -> Self {
+        Self { span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new("Cannot use 'this' outside of a class".to_string(), self.span)
+    }
+}
+
+impl Display for ThisOutsideClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<ThisOutsideClass> for ResolveError {
+    fn from(value: ThisOutsideClass) -> Self {
+        ResolveError::ThisOutsideClass(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct SuperOutsideSubclass {
+    span: Span,
+}
+
+impl SuperOutsideSubclass {
+    pub fn new(span: Span) -> Self {
+        Self { span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new("Cannot use 'super' outside of a subclass".to_string(), self.span)
+    }
+}
+
+impl Display for SuperOutsideSubclass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<SuperOutsideSubclass> for ResolveError {
+    fn from(value: SuperOutsideSubclass) -> Self {
+        ResolveError::SuperOutsideSubclass(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct InheritFromSelf {
+    name: String,
+    span: Span,
+}
+
+impl InheritFromSelf {
+    pub fn new(name: String, span: Span) -> Self {
+        Self { name, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(format!("A class cannot inherit from itself: {}", self.name), self.span)
+    }
+}
+
+impl Display for InheritFromSelf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<InheritFromSelf> for ResolveError {
+    fn from(value: InheritFromSelf) -> Self {
+        ResolveError::InheritFromSelf(value)
+    }
+}
@@ -0,0 +1,8 @@
+#[cfg(test)]
+mod tests;
+
+pub mod resolver;
+mod errors;
+
+pub use errors::ResolveError;
+pub use resolver::Resolver;
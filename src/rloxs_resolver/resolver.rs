@@ -0,0 +1,330 @@
+use std::{cell::Cell, collections::HashMap};
+
+use crate::syntax::{ClassDecl, Expr, FnDecl, Identifier, Stmt};
+
+use super::errors::{
+    DuplicateDeclaration,
+    InheritFromSelf,
+    ReadInOwnInitializer,
+    ResolveError,
+    ReturnOutsideFunction,
+    ReturnValueFromInitializer,
+    SuperOutsideSubclass,
+    ThisOutsideClass,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionKind {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassKind {
+    None,
+    Class,
+    Subclass,
+}
+
+//実行前にASTを一度だけ辿り、ローカル変数の参照にスコープの距離を書き込む。
+//スコープの積み方はInterpreterが実行時に作る環境と一致させること
+#[derive(Debug)]
+pub struct Resolver {
+    //変数名 -> 初期化が終わっているか
+    scopes: Vec<HashMap<String, bool>>,
+    function_kind: FunctionKind,
+    class_kind: ClassKind,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![],
+            function_kind: FunctionKind::None,
+            class_kind: ClassKind::None,
+        }
+    }
+
+    pub fn resolve(&mut self, program: &[Stmt]) -> Result<(), ResolveError> {
+        for stmt in program {
+            self.resolve_stmt(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) -> Result<(), ResolveError> {
+        match stmt {
+            Stmt::Expression { expr, .. } | Stmt::Print { expr, .. } => self.resolve_expr(expr)?,
+            Stmt::Let { name, initializer, .. } => {
+                self.declare(name)?;
+                if let Some(initializer) = initializer {
+                    self.resolve_expr(initializer)?;
+                }
+                self.define(name);
+            },
+            Stmt::Fn(decl) => {
+                self.declare(&decl.name)?;
+                //再帰呼び出しできるように本体より先に定義済みにする
+                self.define(&decl.name);
+                self.resolve_function(decl, FunctionKind::Function)?;
+            },
+            Stmt::Class(decl) => self.resolve_class(decl)?,
+            Stmt::Block { stmts, .. } => self.resolve_block(stmts)?,
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                self.resolve_expr(condition)?;
+                self.resolve_block(then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    self.resolve_block(else_branch)?;
+                }
+            },
+            Stmt::While { condition, body, .. } => {
+                self.resolve_expr(condition)?;
+                self.resolve_block(body)?;
+            },
+            Stmt::For { initializer, condition, increment, body, .. } => {
+                //ループ全体のスコープ
+                self.begin_scope();
+                if let Some(initializer) = initializer {
+                    self.resolve_stmt(initializer)?;
+                }
+
+                //反復ごとのスコープ。ループ変数はここへコピーされる
+                self.begin_scope();
+                if let Some(Stmt::Let { name, .. }) = initializer.as_deref() {
+                    self.declare(name)?;
+                    self.define(name);
+                }
+                if let Some(condition) = condition {
+                    self.resolve_expr(condition)?;
+                }
+                self.resolve_block(body)?;
+                if let Some(increment) = increment {
+                    self.resolve_expr(increment)?;
+                }
+                self.end_scope();
+
+                self.end_scope();
+            },
+            Stmt::ForIn { variable, iterable, body, .. } => {
+                self.resolve_expr(iterable)?;
+
+                //反復ごとのスコープ。本体のブロックはその内側に入る
+                self.begin_scope();
+                self.declare(variable)?;
+                self.define(variable);
+                self.resolve_block(body)?;
+                self.end_scope();
+            },
+            Stmt::Return { value, .. } => {
+                if self.function_kind == FunctionKind::None {
+                    Err(ReturnOutsideFunction::new(stmt.span()))?
+                }
+
+                if let Some(value) = value {
+                    if self.function_kind == FunctionKind::Initializer {
+                        Err(ReturnValueFromInitializer::new(stmt.span()))?
+                    }
+                    self.resolve_expr(value)?;
+                }
+            },
+            Stmt::Error { .. } => {},
+        }
+
+        Ok(())
+    }
+
+    fn resolve_block(&mut self, stmts: &[Stmt]) -> Result<(), ResolveError> {
+        self.begin_scope();
+        let result = self.resolve(stmts);
+        self.end_scope();
+        result
+    }
+
+    fn resolve_function(&mut self, decl: &FnDecl, kind: FunctionKind) -> Result<(), ResolveError> {
+        let enclosing_kind = std::mem::replace(&mut self.function_kind, kind);
+
+        self.begin_scope();
+        let result = self.resolve_params_and_body(decl);
+        self.end_scope();
+
+        self.function_kind = enclosing_kind;
+        result
+    }
+
+    fn resolve_params_and_body(&mut self, decl: &FnDecl) -> Result<(), ResolveError> {
+        for param in &decl.params {
+            self.declare(param)?;
+            self.define(param);
+        }
+
+        //関数本体は引数と同じスコープで実行される
+        self.resolve(&decl.body)
+    }
+
+    fn resolve_class(&mut self, decl: &ClassDecl) -> Result<(), ResolveError> {
+        self.declare(&decl.name)?;
+        self.define(&decl.name);
+
+        let enclosing_kind = std::mem::replace(&mut self.class_kind, ClassKind::Class);
+        let result = self.resolve_class_body(decl);
+        self.class_kind = enclosing_kind;
+        result
+    }
+
+    fn resolve_class_body(&mut self, decl: &ClassDecl) -> Result<(), ResolveError> {
+        if let Some(superclass) = &decl.superclass {
+            if superclass.name == decl.name.name {
+                Err(InheritFromSelf::new(superclass.name.clone(), superclass.span))?
+            }
+
+            self.class_kind = ClassKind::Subclass;
+
+            self.begin_scope();
+            self.define_keyword("super");
+        }
+
+        self.begin_scope();
+        self.define_keyword("this");
+
+        let mut result = Ok(());
+        for method in &decl.methods {
+            let kind = match method.name.name.as_str() {
+                "init" => FunctionKind::Initializer,
+                _ => FunctionKind::Method,
+            };
+
+            result = self.resolve_function(method, kind);
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.end_scope();
+        if decl.superclass.is_some() {
+            self.end_scope();
+        }
+
+        result
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) -> Result<(), ResolveError> {
+        match expr {
+            Expr::Literal { .. } => {},
+            Expr::Variable { name, depth, .. } => {
+                if let Some(false) = self.scopes.last().and_then(|scope| scope.get(&name.name)) {
+                    Err(ReadInOwnInitializer::new(name.name.clone(), name.span))?
+                }
+
+                self.resolve_local(name, depth);
+            },
+            Expr::Assign { name, expr, depth, .. } => {
+                self.resolve_expr(expr)?;
+                self.resolve_local(name, depth);
+            },
+            Expr::BinaryOp { left, right, .. } => {
+                self.resolve_expr(left)?;
+                self.resolve_expr(right)?;
+            },
+            Expr::UnaryOp { operand, .. } => self.resolve_expr(operand)?,
+            Expr::Grouping { expr, .. } => self.resolve_expr(expr)?,
+            Expr::Call { callee, args, .. } => {
+                self.resolve_expr(callee)?;
+                for arg in args {
+                    self.resolve_expr(arg)?;
+                }
+            },
+            Expr::Get { object, .. } => self.resolve_expr(object)?,
+            Expr::Set { object, value, .. } => {
+                self.resolve_expr(value)?;
+                self.resolve_expr(object)?;
+            },
+            Expr::This { keyword, depth } => {
+                if self.class_kind == ClassKind::None {
+                    Err(ThisOutsideClass::new(keyword.span))?
+                }
+
+                self.resolve_local(keyword, depth);
+            },
+            Expr::Super { keyword, depth, .. } => {
+                if self.class_kind != ClassKind::Subclass {
+                    Err(SuperOutsideSubclass::new(keyword.span))?
+                }
+
+                self.resolve_local(keyword, depth);
+            },
+            Expr::Interpolation { parts, .. } | Expr::List { elements: parts, .. } => {
+                for part in parts {
+                    self.resolve_expr(part)?;
+                }
+            },
+            Expr::Map { entries, .. } => {
+                for (key, value) in entries {
+                    self.resolve_expr(key)?;
+                    self.resolve_expr(value)?;
+                }
+            },
+            Expr::Index { object, index, .. } => {
+                self.resolve_expr(object)?;
+                self.resolve_expr(index)?;
+            },
+            Expr::IndexSet { object, index, value, .. } => {
+                self.resolve_expr(object)?;
+                self.resolve_expr(index)?;
+                self.resolve_expr(value)?;
+            },
+        }
+
+        Ok(())
+    }
+
+    //内側のスコープから探し、見つかった距離を書き込む。見つからなければグローバル
+    fn resolve_local(&self, name: &Identifier, depth: &Cell<Option<usize>>) {
+        let found = self.scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.name));
+
+        depth.set(found);
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    //トップレベルは再定義を許すため、重複はローカルスコープだけで検出する
+    fn declare(&mut self, name: &Identifier) -> Result<(), ResolveError> {
+        if let Some(scope) = self.scopes.last_mut() {
+            if scope.contains_key(&name.name) {
+                Err(DuplicateDeclaration::new(name.name.clone(), name.span))?
+            }
+            scope.insert(name.name.clone(), false);
+        }
+
+        Ok(())
+    }
+
+    fn define(&mut self, name: &Identifier) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.name.clone(), true);
+        }
+    }
+
+    fn define_keyword(&mut self, keyword: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(keyword.to_string(), true);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{rloxs_lexer::Lexer, rloxs_parser::parser::Parser, syntax::{Expr, Stmt}, test_support::summary};
+
+use super::*;
+
+fn parse_helper(input: &str) -> Vec<Stmt> {
+    let tokens = Lexer::new(input).lex().unwrap();
+    Parser::new(tokens).parse_program().unwrap()
+}
+
+fn test_helper(input: &str) -> Result<Vec<Stmt>, ResolveError> {
+    let program = parse_helper(input);
+    Resolver::new().resolve(&program)?;
+    Ok(program)
+}
+
+fn error_helper(input: &str) -> String {
+    summary(&test_helper(input).unwrap_err().diagnostic(), input)
+}
+
+#[test]
+fn resolve_depths() {
+    let program = test_helper(r#"
+let global = 1;
+{
+    let outer = 2;
+    {
+        print outer + global;
+    }
+}
+"#).unwrap();
+
+    let Stmt::Block { stmts: outer, .. } = &program[1] else { panic!("expected block") };
+    let Stmt::Block { stmts: inner, .. } = &outer[1] else { panic!("expected block") };
+    let Stmt::Print { expr: Expr::BinaryOp { left, right, .. }, .. } = &inner[0] else { panic!("expected print") };
+
+    assert!(matches!(left.as_ref(), Expr::Variable { name, depth, .. } if name.name == "outer" && depth.get() == Some(1)));
+    assert!(matches!(right.as_ref(), Expr::Variable { name, depth, .. } if name.name == "global" && depth.get().is_none()));
+}
+
+#[test]
+fn resolve_function_params_and_closures() {
+    let program = test_helper(r#"
+fn outer(a) {
+    fn inner() {
+        a = a + 1;
+        return a;
+    }
+    return inner;
+}
+"#).unwrap();
+
+    let Stmt::Fn(outer) = &program[0] else { panic!("expected fn") };
+    let Stmt::Fn(inner) = &outer.body[0] else { panic!("expected fn") };
+    let Stmt::Expression { expr: Expr::Assign { depth, expr, .. }, .. } = &inner.body[0] else { panic!("expected assign") };
+
+    assert_eq!(depth.get(), Some(1));
+    assert!(matches!(expr.as_ref(), Expr::BinaryOp { left, .. }
+        if matches!(left.as_ref(), Expr::Variable { depth, .. } if depth.get() == Some(1))));
+}
+
+#[test]
+fn resolve_read_in_own_initializer() {
+    assert_eq!(
+        error_helper("{ let a = a; }"),
+        "Cannot read local variable in its own initializer: a at [1:10]"
+    );
+
+    //グローバルは外側の同名変数を参照しないため対象外
+    assert!(test_helper("let a = 1; let a = a;").is_ok());
+}
+
+#[test]
+fn resolve_duplicate_declaration() {
+    assert_eq!(
+        error_helper("{ let a = 1; let a = 2; }"),
+        "Variable already declared in this scope: a at [1:17]"
+    );
+    assert_eq!(
+        error_helper("fn f(a, a) {}"),
+        "Variable already declared in this scope: a at [1:8]"
+    );
+
+    //シャドーイングとトップレベルでの再定義は許可する
+    assert!(test_helper("let a = 1; let a = 2; { let a = 3; { let a = 4; } }").is_ok());
+}
+
+#[test]
+fn resolve_invalid_return() {
+    assert_eq!(error_helper("return 1;"), "Cannot return from top-level code at [1:0]");
+    assert_eq!(
+        error_helper("class A { fn init() { return 1; } }"),
+        "Cannot return a value from an initializer at [1:22]"
+    );
+
+    assert!(test_helper("class A { fn init() { return; } }").is_ok());
+}
+
+#[test]
+fn resolve_invalid_this_and_super() {
+    assert_eq!(error_helper("print this;"), "Cannot use 'this' outside of a class at [1:6]");
+    assert_eq!(error_helper("fn f() { return this; }"), "Cannot use 'this' outside of a class at [1:16]");
+    assert_eq!(error_helper("super.f();"), "Cannot use 'super' outside of a subclass at [1:0]");
+    assert_eq!(
+        error_helper("class A { fn f() { super.f(); } }"),
+        "Cannot use 'super' outside of a subclass at [1:19]"
+    );
+    assert_eq!(error_helper("class A < A {}"), "A class cannot inherit from itself: A at [1:10]");
+}
@@ -0,0 +1,198 @@
+use std::rc::Rc;
+
+use crate::{rloxs_eval::value::Value, syntax::Span};
+
+use super::object::FunctionProto;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum OpCode {
+    ///定数プールの値を積む。オペランド: 定数番号(u16)
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    ///スタックの一番上の値をもう1つ積む
+    Dup,
+    ///上から2つの値を同じ順でもう1組積む
+    Dup2,
+    ///オペランド: スロット番号(u8)
+    GetLocal,
+    SetLocal,
+    ///オペランド: 名前番号(u16)
+    GetGlobal,
+    DefineGlobal,
+    SetGlobal,
+    ///オペランド: upvalue番号(u8)
+    GetUpvalue,
+    SetUpvalue,
+    ///オペランド: 名前番号(u16)
+    GetProperty,
+    SetProperty,
+    GetSuper,
+
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    IntDivide,
+    Modulo,
+    Not,
+    Negate,
+
+    Print,
+    ///オペランド: 前方へのオフセット(u16)
+    Jump,
+    JumpIfFalse,
+    ///オペランド: 後方へのオフセット(u16)
+    Loop,
+    ///オペランド: 引数の数(u8)
+    Call,
+    ///オペランド: 関数番号(u16)、続いてupvalueごとに (is_local(u8), index(u8))
+    Closure,
+    CloseUpvalue,
+    Return,
+    ///オペランド: 名前番号(u16)、スーパークラスの有無(u8)
+    Class,
+    ///オペランド: 名前番号(u16)
+    Method,
+    ///スタック上の値を文字列にして連結する。オペランド: 値の数(u8)
+    Interpolate,
+    ///スタック上の値を順に並べたリストを作る。オペランド: 要素の数(u16)
+    BuildList,
+    GetIndex,
+    SetIndex,
+    ///スタック上にキーと値を交互に積んだ組からマップを作る。オペランド: 組の数(u16)
+    BuildMap,
+    ///スタックトップの値をそのiterメソッドに置き換える
+    GetIter,
+}
+
+impl TryFrom<u8> for OpCode {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        const OPCODES: [OpCode; 47] = [
+            OpCode::Constant,
+            OpCode::Nil,
+            OpCode::True,
+            OpCode::False,
+            OpCode::Pop,
+            OpCode::Dup,
+            OpCode::Dup2,
+            OpCode::GetLocal,
+            OpCode::SetLocal,
+            OpCode::GetGlobal,
+            OpCode::DefineGlobal,
+            OpCode::SetGlobal,
+            OpCode::GetUpvalue,
+            OpCode::SetUpvalue,
+            OpCode::GetProperty,
+            OpCode::SetProperty,
+            OpCode::GetSuper,
+            OpCode::Equal,
+            OpCode::NotEqual,
+            OpCode::Greater,
+            OpCode::GreaterEqual,
+            OpCode::Less,
+            OpCode::LessEqual,
+            OpCode::Add,
+            OpCode::Subtract,
+            OpCode::Multiply,
+            OpCode::Divide,
+            OpCode::IntDivide,
+            OpCode::Modulo,
+            OpCode::Not,
+            OpCode::Negate,
+            OpCode::Print,
+            OpCode::Jump,
+            OpCode::JumpIfFalse,
+            OpCode::Loop,
+            OpCode::Call,
+            OpCode::Closure,
+            OpCode::CloseUpvalue,
+            OpCode::Return,
+            OpCode::Class,
+            OpCode::Method,
+            OpCode::Interpolate,
+            OpCode::BuildList,
+            OpCode::GetIndex,
+            OpCode::SetIndex,
+            OpCode::BuildMap,
+            OpCode::GetIter,
+        ];
+
+        OPCODES.get(value as usize).copied().ok_or(value)
+    }
+}
+
+//同じ位置が続く命令は1つのエントリにまとめる(ランレングス)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SpanEntry {
+    offset: usize,
+    span: Span,
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Value>,
+    pub names: Vec<String>,
+    pub functions: Vec<Rc<FunctionProto>>,
+    spans: Vec<SpanEntry>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&mut self, byte: u8, span: Span) {
+        if self.spans.last().map(|entry| entry.span) != Some(span) {
+            self.spans.push(SpanEntry { offset: self.code.len(), span });
+        }
+
+        self.code.push(byte);
+    }
+
+    //offsetの命令に対応するソース上の範囲
+    pub fn span(&self, offset: usize) -> Span {
+        let index = self.spans.partition_point(|entry| entry.offset <= offset);
+        match index.checked_sub(1).map(|i| self.spans[i]) {
+            Some(entry) => entry.span,
+            None => Span::default(),
+        }
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    //同じ名前は使い回す
+    pub fn add_name(&mut self, name: &str) -> usize {
+        match self.names.iter().position(|n| n == name) {
+            Some(index) => index,
+            None => {
+                self.names.push(name.to_string());
+                self.names.len() - 1
+            },
+        }
+    }
+
+    pub fn add_function(&mut self, function: Rc<FunctionProto>) -> usize {
+        self.functions.push(function);
+        self.functions.len() - 1
+    }
+}
@@ -0,0 +1,792 @@
+use std::rc::Rc;
+
+use crate::{
+    rloxs_eval::{iter::{HAS_NEXT, NEXT}, value::Value},
+    syntax::{ClassDecl, Expr, FnDecl, Identifier, OperatorKind, Span, Stmt},
+};
+
+use super::{
+    chunk::{Chunk, OpCode},
+    errors::{CodegenError, LimitExceeded},
+    object::FunctionProto,
+};
+
+const MAX_LOCALS: usize = 256;
+const MAX_UPVALUES: usize = 256;
+const MAX_ARGS: usize = 255;
+const MAX_INTERPOLATION_PARTS: usize = 255;
+const MAX_LIST_ELEMENTS: usize = u16::MAX as usize;
+const MAX_MAP_ENTRIES: usize = u16::MAX as usize;
+const MAX_POOL_SIZE: usize = u16::MAX as usize + 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionKind {
+    Script,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Debug)]
+struct Local {
+    name: String,
+    depth: usize,
+    //クロージャに捕捉されたらスコープを抜けるときにupvalueへ移す
+    is_captured: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct UpvalueDesc {
+    //trueなら外側の関数のローカル、falseなら外側の関数のupvalueを指す
+    is_local: bool,
+    index: u8,
+}
+
+//コンパイル中の関数ごとの状態
+#[derive(Debug)]
+struct FnState {
+    name: String,
+    arity: usize,
+    kind: FunctionKind,
+    chunk: Chunk,
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueDesc>,
+    scope_depth: usize,
+}
+
+impl FnState {
+    fn new(name: &str, arity: usize, kind: FunctionKind) -> Self {
+        //スロット0は呼び出された値自身。メソッドではthisになる
+        let slot_zero = match kind {
+            FunctionKind::Method | FunctionKind::Initializer => "this",
+            FunctionKind::Script | FunctionKind::Function => "",
+        };
+
+        Self {
+            name: name.to_string(),
+            arity,
+            kind,
+            chunk: Chunk::new(),
+            locals: vec![Local { name: slot_zero.to_string(), depth: 0, is_captured: false }],
+            upvalues: vec![],
+            scope_depth: 0,
+        }
+    }
+}
+
+//ASTをバイトコードへ変換する。
+//スコープの作り方はtree-walker(とresolver)に合わせ、同じプログラムが同じ結果になるようにする
+#[derive(Debug)]
+pub struct Compiler {
+    states: Vec<FnState>,
+    //これから書き込む命令に対応するソース上の位置
+    span: Span,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self { states: vec![], span: Span::default() }
+    }
+
+    pub fn compile(&mut self, program: &[Stmt]) -> Result<Rc<FunctionProto>, CodegenError> {
+        self.states.push(FnState::new("script", 0, FunctionKind::Script));
+
+        let result = self.compile_stmts(program);
+        let function = self.finish_function();
+        result?;
+
+        Ok(Rc::new(function))
+    }
+
+    fn state(&mut self) -> &mut FnState {
+        self.states.last_mut().expect("compiler has no function state")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state().chunk
+    }
+
+    fn set_position(&mut self, span: Span) {
+        self.span = span;
+    }
+
+    fn limit_error(&self, what: &'static str, limit: usize) -> CodegenError {
+        LimitExceeded::new(what, limit, self.span).into()
+    }
+
+    fn emit_byte(&mut self, byte: u8) {
+        let span = self.span;
+        self.chunk().write(byte, span);
+    }
+
+    fn emit_op(&mut self, op: OpCode) {
+        self.emit_byte(op as u8);
+    }
+
+    fn emit_op_u8(&mut self, op: OpCode, operand: u8) {
+        self.emit_op(op);
+        self.emit_byte(operand);
+    }
+
+    fn emit_op_u16(&mut self, op: OpCode, operand: u16) {
+        self.emit_op(op);
+        for byte in operand.to_be_bytes() {
+            self.emit_byte(byte);
+        }
+    }
+
+    fn pool_index(&self, index: usize, what: &'static str) -> Result<u16, CodegenError> {
+        u16::try_from(index).map_err(|_| self.limit_error(what, MAX_POOL_SIZE))
+    }
+
+    fn emit_constant(&mut self, value: Value) -> Result<(), CodegenError> {
+        let index = self.chunk().add_constant(value);
+        let index = self.pool_index(index, "constants")?;
+        self.emit_op_u16(OpCode::Constant, index);
+        Ok(())
+    }
+
+    fn name_index(&mut self, name: &str) -> Result<u16, CodegenError> {
+        let index = self.chunk().add_name(name);
+        self.pool_index(index, "names")
+    }
+
+    //ジャンプ先が決まったらpatch_jumpで書き換える
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit_op_u16(op, u16::MAX);
+        self.chunk().code.len() - 2
+    }
+
+    fn patch_jump(&mut self, operand: usize) -> Result<(), CodegenError> {
+        let jump = self.chunk().code.len() - operand - 2;
+        let jump = u16::try_from(jump).map_err(|_| self.limit_error("bytes to jump over", u16::MAX as usize))?;
+
+        let [high, low] = jump.to_be_bytes();
+        let code = &mut self.chunk().code;
+        code[operand] = high;
+        code[operand + 1] = low;
+        Ok(())
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) -> Result<(), CodegenError> {
+        self.emit_op(OpCode::Loop);
+        let offset = self.chunk().code.len() - loop_start + 2;
+        let offset = u16::try_from(offset).map_err(|_| self.limit_error("bytes in loop body", u16::MAX as usize))?;
+
+        for byte in offset.to_be_bytes() {
+            self.emit_byte(byte);
+        }
+        Ok(())
+    }
+
+    fn finish_function(&mut self) -> FunctionProto {
+        match self.state().kind {
+            FunctionKind::Initializer => self.emit_op_u8(OpCode::GetLocal, 0),
+            _ => self.emit_op(OpCode::Nil),
+        }
+        self.emit_op(OpCode::Return);
+
+        let state = self.states.pop().expect("compiler has no function state");
+        FunctionProto {
+            name: state.name,
+            arity: state.arity,
+            upvalue_count: state.upvalues.len(),
+            chunk: state.chunk,
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.state().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.state().scope_depth -= 1;
+        let depth = self.state().scope_depth;
+        self.emit_scope_exit(depth);
+
+        let locals = &mut self.state().locals;
+        while locals.last().is_some_and(|local| local.depth > depth) {
+            locals.pop();
+        }
+    }
+
+    //depthより深いローカルを捨てる命令を書く。コンパイラ側の状態は変えない
+    fn emit_scope_exit(&mut self, depth: usize) {
+        let ops = self.state().locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth > depth)
+            .map(|local| if local.is_captured { OpCode::CloseUpvalue } else { OpCode::Pop })
+            .collect::<Vec<_>>();
+
+        for op in ops {
+            self.emit_op(op);
+        }
+    }
+
+    fn add_local(&mut self, name: &str) -> Result<(), CodegenError> {
+        if self.state().locals.len() >= MAX_LOCALS {
+            Err(self.limit_error("local variables", MAX_LOCALS))?
+        }
+
+        let depth = self.state().scope_depth;
+        self.state().locals.push(Local { name: name.to_string(), depth, is_captured: false });
+        Ok(())
+    }
+
+    //スタックトップの値を変数として定義する
+    fn define_variable(&mut self, name: &Identifier) -> Result<(), CodegenError> {
+        self.set_position(name.span);
+
+        if self.state().scope_depth > 0 {
+            self.add_local(&name.name)
+        } else {
+            let index = self.name_index(&name.name)?;
+            self.emit_op_u16(OpCode::DefineGlobal, index);
+            Ok(())
+        }
+    }
+
+    fn resolve_local(&self, level: usize, name: &str) -> Option<usize> {
+        self.states[level].locals.iter().rposition(|local| local.name == name)
+    }
+
+    fn resolve_upvalue(&mut self, level: usize, name: &str) -> Result<Option<u8>, CodegenError> {
+        if level == 0 {
+            return Ok(None);
+        }
+
+        if let Some(slot) = self.resolve_local(level - 1, name) {
+            self.states[level - 1].locals[slot].is_captured = true;
+            return self.add_upvalue(level, UpvalueDesc { is_local: true, index: slot as u8 }).map(Some);
+        }
+
+        match self.resolve_upvalue(level - 1, name)? {
+            Some(index) => self.add_upvalue(level, UpvalueDesc { is_local: false, index }).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn add_upvalue(&mut self, level: usize, upvalue: UpvalueDesc) -> Result<u8, CodegenError> {
+        if let Some(index) = self.states[level].upvalues.iter().position(|u| *u == upvalue) {
+            return Ok(index as u8);
+        }
+
+        if self.states[level].upvalues.len() >= MAX_UPVALUES {
+            Err(self.limit_error("closure variables", MAX_UPVALUES))?
+        }
+
+        let upvalues = &mut self.states[level].upvalues;
+        upvalues.push(upvalue);
+        Ok((upvalues.len() - 1) as u8)
+    }
+
+    fn named_variable(&mut self, name: &str, assign: bool) -> Result<(), CodegenError> {
+        let level = self.states.len() - 1;
+
+        if let Some(slot) = self.resolve_local(level, name) {
+            let op = if assign { OpCode::SetLocal } else { OpCode::GetLocal };
+            self.emit_op_u8(op, slot as u8);
+        } else if let Some(index) = self.resolve_upvalue(level, name)? {
+            let op = if assign { OpCode::SetUpvalue } else { OpCode::GetUpvalue };
+            self.emit_op_u8(op, index);
+        } else {
+            let op = if assign { OpCode::SetGlobal } else { OpCode::GetGlobal };
+            let index = self.name_index(name)?;
+            self.emit_op_u16(op, index);
+        }
+
+        Ok(())
+    }
+
+    fn compile_stmts(&mut self, stmts: &[Stmt]) -> Result<(), CodegenError> {
+        for stmt in stmts {
+            self.compile_stmt(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    fn compile_block(&mut self, stmts: &[Stmt]) -> Result<(), CodegenError> {
+        self.begin_scope();
+        self.compile_stmts(stmts)?;
+        self.end_scope();
+        Ok(())
+    }
+
+    fn compile_stmt(&mut self, stmt: &Stmt) -> Result<(), CodegenError> {
+        match stmt {
+            Stmt::Expression { expr, .. } => {
+                self.compile_expr(expr)?;
+                self.emit_op(OpCode::Pop);
+            },
+            Stmt::Print { expr, .. } => {
+                self.compile_expr(expr)?;
+                self.emit_op(OpCode::Print);
+            },
+            Stmt::Let { name, initializer, .. } => {
+                match initializer {
+                    Some(expr) => self.compile_expr(expr)?,
+                    None => self.emit_op(OpCode::Nil),
+                }
+                self.define_variable(name)?;
+            },
+            Stmt::Fn(decl) => {
+                //再帰呼び出しできるようにローカルは本体より先に宣言する
+                if self.state().scope_depth > 0 {
+                    self.set_position(decl.name.span);
+                    self.add_local(&decl.name.name)?;
+                    self.compile_function(decl, FunctionKind::Function)?;
+                } else {
+                    self.compile_function(decl, FunctionKind::Function)?;
+                    self.define_variable(&decl.name)?;
+                }
+            },
+            Stmt::Class(decl) => self.compile_class(decl)?,
+            Stmt::Block { stmts, .. } => self.compile_block(stmts)?,
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                self.compile_expr(condition)?;
+                let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit_op(OpCode::Pop);
+                self.compile_block(then_branch)?;
+
+                let else_jump = self.emit_jump(OpCode::Jump);
+                self.patch_jump(then_jump)?;
+                self.emit_op(OpCode::Pop);
+                if let Some(else_branch) = else_branch {
+                    self.compile_block(else_branch)?;
+                }
+                self.patch_jump(else_jump)?;
+            },
+            Stmt::While { condition, body, .. } => {
+                let loop_start = self.chunk().code.len();
+                self.compile_expr(condition)?;
+                let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit_op(OpCode::Pop);
+                self.compile_block(body)?;
+                self.emit_loop(loop_start)?;
+
+                self.patch_jump(exit_jump)?;
+                self.emit_op(OpCode::Pop);
+            },
+            Stmt::For { initializer, condition, increment, body, .. } => {
+                self.compile_for(initializer.as_deref(), condition.as_ref(), increment.as_ref(), body)?;
+            },
+            Stmt::ForIn { variable, iterable, body, .. } => {
+                self.compile_for_in(variable, iterable, body)?;
+            },
+            Stmt::Return { value, span } => {
+                self.set_position(*span);
+                match (value, self.state().kind) {
+                    //initからのreturnは常にthisを返す(値付きのreturnはresolverが弾く)
+                    (_, FunctionKind::Initializer) => self.emit_op_u8(OpCode::GetLocal, 0),
+                    (Some(expr), _) => self.compile_expr(expr)?,
+                    (None, _) => self.emit_op(OpCode::Nil),
+                }
+                self.emit_op(OpCode::Return);
+            },
+            Stmt::Error { .. } => {},
+        }
+
+        Ok(())
+    }
+
+    //tree-walkerと同じく、ループ変数は反復ごとのスロットへコピーして使う。
+    //反復の終わりに外側のスロットへ書き戻し、次の反復用にコピーし直してからインクリメントする
+    fn compile_for(
+        &mut self,
+        initializer: Option<&Stmt>,
+        condition: Option<&Expr>,
+        increment: Option<&Expr>,
+        body: &[Stmt],
+    ) -> Result<(), CodegenError> {
+        self.begin_scope();
+
+        let loop_var = match initializer {
+            Some(Stmt::Let { name, .. }) => Some(name),
+            _ => None,
+        };
+
+        if let Some(initializer) = initializer {
+            self.compile_stmt(initializer)?;
+        }
+
+        let loop_depth = self.state().scope_depth;
+        let loop_slot = self.state().locals.len().checked_sub(1);
+
+        //反復ごとのスコープ
+        self.begin_scope();
+        if let Some(name) = loop_var {
+            self.set_position(name.span);
+            self.named_variable(&name.name, false)?;
+            self.add_local(&name.name)?;
+        }
+
+        //最初の反復ではインクリメントを飛ばす
+        let mut skip_increment = None;
+        let loop_start = match increment {
+            Some(increment) => {
+                skip_increment = Some(self.emit_jump(OpCode::Jump));
+                let loop_start = self.chunk().code.len();
+                self.compile_expr(increment)?;
+                self.emit_op(OpCode::Pop);
+                loop_start
+            },
+            None => self.chunk().code.len(),
+        };
+
+        if let Some(skip_increment) = skip_increment {
+            self.patch_jump(skip_increment)?;
+        }
+
+        let exit_jump = match condition {
+            Some(condition) => {
+                self.compile_expr(condition)?;
+                let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit_op(OpCode::Pop);
+                Some(exit_jump)
+            },
+            None => None,
+        };
+
+        self.compile_block(body)?;
+
+        if let (Some(name), Some(loop_slot)) = (loop_var, loop_slot) {
+            self.set_position(name.span);
+            self.named_variable(&name.name, false)?;
+            self.emit_op_u8(OpCode::SetLocal, loop_slot as u8);
+            self.emit_op(OpCode::Pop);
+
+            //捕捉されていればここで閉じ、同じスロットに次の反復の変数を積む
+            self.emit_scope_exit(loop_depth);
+            self.emit_op_u8(OpCode::GetLocal, loop_slot as u8);
+        }
+        self.emit_loop(loop_start)?;
+
+        if let Some(exit_jump) = exit_jump {
+            self.patch_jump(exit_jump)?;
+            self.emit_op(OpCode::Pop);
+        }
+
+        self.end_scope();
+        self.end_scope();
+        Ok(())
+    }
+
+    //反復プロトコル(rloxs_eval::iterを参照)をバイトコードに展開する。
+    //反復子は名前のないローカルに置き、ループ変数は反復ごとのスコープに積む
+    fn compile_for_in(
+        &mut self,
+        variable: &Identifier,
+        iterable: &Expr,
+        body: &[Stmt],
+    ) -> Result<(), CodegenError> {
+        self.begin_scope();
+
+        let span = iterable.span();
+        self.compile_expr(iterable)?;
+        self.set_position(span);
+        self.emit_op(OpCode::GetIter);
+        self.emit_op_u8(OpCode::Call, 0);
+        //空の名前は識別子として書けないので、本体から参照されることはない
+        self.add_local("")?;
+        let iterator_slot = self.state().locals.len() - 1;
+
+        let loop_start = self.chunk().code.len();
+        self.emit_protocol_call(iterator_slot, HAS_NEXT)?;
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+
+        //反復ごとのスコープ
+        self.begin_scope();
+        self.emit_protocol_call(iterator_slot, NEXT)?;
+        self.set_position(variable.span);
+        self.add_local(&variable.name)?;
+        self.compile_block(body)?;
+        self.end_scope();
+        self.set_position(span);
+        self.emit_loop(loop_start)?;
+
+        self.patch_jump(exit_jump)?;
+        self.emit_op(OpCode::Pop);
+
+        self.end_scope();
+        Ok(())
+    }
+
+    //反復子のメソッドを引数なしで呼び、結果をスタックに積む
+    fn emit_protocol_call(&mut self, iterator_slot: usize, method: &str) -> Result<(), CodegenError> {
+        self.emit_op_u8(OpCode::GetLocal, iterator_slot as u8);
+        let index = self.name_index(method)?;
+        self.emit_op_u16(OpCode::GetProperty, index);
+        self.emit_op_u8(OpCode::Call, 0);
+        Ok(())
+    }
+
+    fn compile_function(&mut self, decl: &FnDecl, kind: FunctionKind) -> Result<(), CodegenError> {
+        self.set_position(decl.name.span);
+        if decl.params.len() > MAX_ARGS {
+            Err(self.limit_error("parameters", MAX_ARGS))?
+        }
+
+        self.states.push(FnState::new(&decl.name.name, decl.params.len(), kind));
+        //関数本体は引数と同じスコープで実行される
+        self.begin_scope();
+
+        let result = self.compile_params_and_body(decl);
+        let upvalues = self.state().upvalues.clone();
+        let function = self.finish_function();
+        result?;
+
+        self.set_position(decl.name.span);
+        let index = self.chunk().add_function(Rc::new(function));
+        let index = self.pool_index(index, "functions")?;
+        self.emit_op_u16(OpCode::Closure, index);
+        for upvalue in upvalues {
+            self.emit_byte(upvalue.is_local as u8);
+            self.emit_byte(upvalue.index);
+        }
+
+        Ok(())
+    }
+
+    fn compile_params_and_body(&mut self, decl: &FnDecl) -> Result<(), CodegenError> {
+        for param in &decl.params {
+            self.set_position(param.span);
+            self.add_local(&param.name)?;
+        }
+
+        self.compile_stmts(&decl.body)
+    }
+
+    fn compile_class(&mut self, decl: &ClassDecl) -> Result<(), CodegenError> {
+        if let Some(superclass) = &decl.superclass {
+            self.set_position(superclass.span);
+            self.named_variable(&superclass.name, false)?;
+        }
+
+        let name = self.name_index(&decl.name.name)?;
+        self.emit_op_u16(OpCode::Class, name);
+        self.emit_byte(decl.superclass.is_some() as u8);
+        self.define_variable(&decl.name)?;
+
+        //メソッドからスーパークラスをsuperで参照できるようにスコープを挟む
+        if let Some(superclass) = &decl.superclass {
+            self.begin_scope();
+            self.set_position(superclass.span);
+            self.named_variable(&superclass.name, false)?;
+            self.add_local("super")?;
+        }
+
+        self.named_variable(&decl.name.name, false)?;
+        for method in &decl.methods {
+            let kind = match method.name.name.as_str() {
+                "init" => FunctionKind::Initializer,
+                _ => FunctionKind::Method,
+            };
+            self.compile_function(method, kind)?;
+
+            let name = self.name_index(&method.name.name)?;
+            self.emit_op_u16(OpCode::Method, name);
+        }
+        self.emit_op(OpCode::Pop);
+
+        if decl.superclass.is_some() {
+            self.end_scope();
+        }
+
+        Ok(())
+    }
+
+    fn compile_expr(&mut self, expr: &Expr) -> Result<(), CodegenError> {
+        match expr {
+            Expr::Literal { kind, .. } => match Value::from(kind) {
+                Value::Nil => self.emit_op(OpCode::Nil),
+                Value::Bool(true) => self.emit_op(OpCode::True),
+                Value::Bool(false) => self.emit_op(OpCode::False),
+                value => self.emit_constant(value)?,
+            },
+            Expr::Grouping { expr, .. } => self.compile_expr(expr)?,
+            Expr::Variable { name, .. } => {
+                self.set_position(name.span);
+                self.named_variable(&name.name, false)?;
+            },
+            Expr::Assign { name, expr, .. } => {
+                self.compile_expr(expr)?;
+                self.set_position(name.span);
+                self.named_variable(&name.name, true)?;
+            },
+            Expr::UnaryOp { operator, operand, .. } => {
+                self.compile_expr(operand)?;
+                self.set_position(operator.span);
+                match operator.op_kind {
+                    OperatorKind::Not => self.emit_op(OpCode::Not),
+                    _ => self.emit_op(OpCode::Negate),
+                }
+            },
+            Expr::BinaryOp { left, operator, right, .. } => {
+                self.compile_expr(left)?;
+
+                match operator.op_kind {
+                    //短絡評価: 左辺の値で結果が決まれば右辺を飛ばす
+                    OperatorKind::And => {
+                        let end_jump = self.emit_jump(OpCode::JumpIfFalse);
+                        self.emit_op(OpCode::Pop);
+                        self.compile_expr(right)?;
+                        self.patch_jump(end_jump)?;
+                    },
+                    OperatorKind::Or => {
+                        let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+                        let end_jump = self.emit_jump(OpCode::Jump);
+                        self.patch_jump(else_jump)?;
+                        self.emit_op(OpCode::Pop);
+                        self.compile_expr(right)?;
+                        self.patch_jump(end_jump)?;
+                    },
+                    op_kind => {
+                        self.compile_expr(right)?;
+                        self.set_position(operator.span);
+                        self.emit_op(binary_opcode(op_kind));
+                    },
+                }
+            },
+            Expr::Call { callee, args, paren, .. } => {
+                self.compile_expr(callee)?;
+                for arg in args {
+                    self.compile_expr(arg)?;
+                }
+
+                self.set_position(paren.span);
+                if args.len() > MAX_ARGS {
+                    Err(self.limit_error("arguments", MAX_ARGS))?
+                }
+                self.emit_op_u8(OpCode::Call, args.len() as u8);
+            },
+            Expr::Get { object, name, .. } => {
+                self.compile_expr(object)?;
+                self.set_position(name.span);
+                let index = self.name_index(&name.name)?;
+                self.emit_op_u16(OpCode::GetProperty, index);
+            },
+            Expr::Set { object, name, operator, value, .. } => {
+                self.compile_expr(object)?;
+                let index = self.name_index(&name.name)?;
+
+                match operator {
+                    //インスタンスを複製して今の値を読み、オブジェクトの式は1回だけ評価する
+                    Some(operator) => {
+                        self.emit_op(OpCode::Dup);
+                        self.set_position(name.span);
+                        self.emit_op_u16(OpCode::GetProperty, index);
+                        self.compile_expr(value)?;
+                        self.set_position(operator.span);
+                        self.emit_op(binary_opcode(operator.op_kind));
+                    },
+                    None => self.compile_expr(value)?,
+                }
+
+                self.set_position(name.span);
+                self.emit_op_u16(OpCode::SetProperty, index);
+            },
+            Expr::This { keyword, .. } => {
+                self.set_position(keyword.span);
+                self.named_variable("this", false)?;
+            },
+            Expr::Super { keyword, method, .. } => {
+                self.set_position(keyword.span);
+                self.named_variable("this", false)?;
+                self.named_variable("super", false)?;
+
+                self.set_position(method.span);
+                let index = self.name_index(&method.name)?;
+                self.emit_op_u16(OpCode::GetSuper, index);
+            },
+            Expr::Interpolation { parts, .. } => {
+                for part in parts {
+                    self.compile_expr(part)?;
+                }
+
+                if parts.len() > MAX_INTERPOLATION_PARTS {
+                    Err(self.limit_error("interpolation parts", MAX_INTERPOLATION_PARTS))?
+                }
+                self.emit_op_u8(OpCode::Interpolate, parts.len() as u8);
+            },
+            Expr::List { elements, .. } => {
+                for element in elements {
+                    self.compile_expr(element)?;
+                }
+
+                if elements.len() > MAX_LIST_ELEMENTS {
+                    Err(self.limit_error("list elements", MAX_LIST_ELEMENTS))?
+                }
+                self.emit_op_u16(OpCode::BuildList, elements.len() as u16);
+            },
+            Expr::Map { entries, brace, .. } => {
+                for (key, value) in entries {
+                    self.compile_expr(key)?;
+                    self.compile_expr(value)?;
+                }
+
+                if entries.len() > MAX_MAP_ENTRIES {
+                    Err(self.limit_error("map entries", MAX_MAP_ENTRIES))?
+                }
+                self.set_position(brace.span);
+                self.emit_op_u16(OpCode::BuildMap, entries.len() as u16);
+            },
+            Expr::Index { object, index, bracket, .. } => {
+                self.compile_expr(object)?;
+                self.compile_expr(index)?;
+                self.set_position(bracket.span);
+                self.emit_op(OpCode::GetIndex);
+            },
+            Expr::IndexSet { object, index, operator, value, bracket, .. } => {
+                self.compile_expr(object)?;
+                self.compile_expr(index)?;
+
+                match operator {
+                    //リストと添字を複製して今の値を読み、どちらの式も1回だけ評価する
+                    Some(operator) => {
+                        self.emit_op(OpCode::Dup2);
+                        self.set_position(bracket.span);
+                        self.emit_op(OpCode::GetIndex);
+                        self.compile_expr(value)?;
+                        self.set_position(operator.span);
+                        self.emit_op(binary_opcode(operator.op_kind));
+                    },
+                    None => self.compile_expr(value)?,
+                }
+
+                self.set_position(bracket.span);
+                self.emit_op(OpCode::SetIndex);
+            },
+        }
+
+        Ok(())
+    }
+}
+
+fn binary_opcode(op_kind: OperatorKind) -> OpCode {
+    match op_kind {
+        OperatorKind::Add => OpCode::Add,
+        OperatorKind::Subtract => OpCode::Subtract,
+        OperatorKind::Multiply => OpCode::Multiply,
+        OperatorKind::Divide => OpCode::Divide,
+        OperatorKind::IntDivide => OpCode::IntDivide,
+        OperatorKind::Modulo => OpCode::Modulo,
+        OperatorKind::Equal => OpCode::Equal,
+        OperatorKind::NotEqual => OpCode::NotEqual,
+        OperatorKind::Greater => OpCode::Greater,
+        OperatorKind::GreaterEqual => OpCode::GreaterEqual,
+        OperatorKind::Less => OpCode::Less,
+        OperatorKind::LessEqual => OpCode::LessEqual,
+        OperatorKind::And | OperatorKind::Or | OperatorKind::Not => {
+            unreachable!("{} is not a strict binary operator", op_kind)
+        },
+    }
+}
@@ -0,0 +1,56 @@
+use std::{error::Error, fmt::Display};
+
+use crate::{diagnostics::Diagnostic, syntax::Span};
+
+#[derive(Debug)]
+pub enum CodegenError {
+    LimitExceeded(LimitExceeded),
+}
+
+impl CodegenError {
+    pub fn diagnostic(&self) -> Diagnostic {
+        match self {
+            CodegenError::LimitExceeded(e) => e.diagnostic(),
+        }
+    }
+}
+
+impl Display for CodegenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodegenError::LimitExceeded(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for CodegenError {}
+
+//バイトコードのオペランドに収まらない数の定数・変数などを使ったときのエラー
+#[derive(Debug)]
+pub struct LimitExceeded {
+    what: &'static str,
+    limit: usize,
+    span: Span,
+}
+
+impl LimitExceeded {
+    pub fn new(what: &'static str, limit: usize, span: Span) -> Self {
+        Self { what, limit, span }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(format!("Too many {} (limit {})", self.what, self.limit), self.span)
+    }
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+impl From<LimitExceeded> for CodegenError {
+    fn from(value: LimitExceeded) -> Self {
+        CodegenError::LimitExceeded(value)
+    }
+}
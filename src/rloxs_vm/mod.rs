@@ -0,0 +1,12 @@
+#[cfg(test)]
+mod tests;
+
+pub mod chunk;
+pub mod compiler;
+pub mod object;
+pub mod vm;
+mod errors;
+
+pub use compiler::Compiler;
+pub use errors::CodegenError;
+pub use vm::Vm;
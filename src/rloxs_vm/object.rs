@@ -0,0 +1,56 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::rloxs_eval::{class::Instance, value::Value};
+
+use super::chunk::Chunk;
+
+//コンパイル済みの関数本体。実行時にはClosureに包んで使う
+#[derive(Debug)]
+pub struct FunctionProto {
+    pub name: String,
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+}
+
+//スタック上にある間はOpen、スコープを抜けたらClosedに値を移す
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+pub struct Closure {
+    pub function: Rc<FunctionProto>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+impl Closure {
+    pub fn new(function: Rc<FunctionProto>, upvalues: Vec<Rc<RefCell<Upvalue>>>) -> Self {
+        Self { function, upvalues }
+    }
+}
+
+//upvalueは自分自身を含み得るので中身までは出力しない
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.function.name)
+    }
+}
+
+pub struct BoundMethod {
+    pub receiver: Rc<RefCell<Instance>>,
+    pub method: Rc<Closure>,
+}
+
+impl BoundMethod {
+    pub fn new(receiver: Rc<RefCell<Instance>>, method: Rc<Closure>) -> Self {
+        Self { receiver, method }
+    }
+}
+
+impl fmt::Debug for BoundMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.method.function.name)
+    }
+}
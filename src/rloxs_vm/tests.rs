@@ -0,0 +1,143 @@
+use std::{rc::Rc, thread};
+
+use crate::{
+    rloxs_eval::{eval::MAX_CALL_DEPTH, value::Value, EvalError, Interpreter},
+    rloxs_lexer::Lexer,
+    rloxs_parser::parser::Parser,
+    rloxs_resolver::Resolver,
+    syntax::Span,
+    test_support::{summary, SharedBuffer},
+};
+
+use super::{chunk::{Chunk, OpCode}, *};
+
+fn compile_helper(input: &str) -> Result<Rc<object::FunctionProto>, CodegenError> {
+    let tokens = Lexer::new(input).lex().unwrap();
+    let program = Parser::new(tokens).parse_program().unwrap();
+    Compiler::new().compile(&program)
+}
+
+fn run_with(vm: &mut Vm, input: &str) -> Result<(), EvalError> {
+    vm.interpret(compile_helper(input).unwrap())
+}
+
+#[test]
+fn opcode_round_trip() {
+    for byte in 0..=OpCode::GetIter as u8 {
+        assert_eq!(OpCode::try_from(byte).unwrap() as u8, byte);
+    }
+    assert_eq!(OpCode::try_from(OpCode::GetIter as u8 + 1), Err(OpCode::GetIter as u8 + 1));
+}
+
+#[test]
+fn chunk_span_table() {
+    let mut chunk = Chunk::new();
+    chunk.write(OpCode::Nil as u8, Span::new(0, 3));
+    chunk.write(OpCode::Nil as u8, Span::new(0, 3));
+    chunk.write(OpCode::Pop as u8, Span::new(8, 9));
+    chunk.write(OpCode::Return as u8, Span::new(11, 17));
+
+    assert_eq!(chunk.span(0), Span::new(0, 3));
+    assert_eq!(chunk.span(1), Span::new(0, 3));
+    assert_eq!(chunk.span(2), Span::new(8, 9));
+    assert_eq!(chunk.span(3), Span::new(11, 17));
+}
+
+#[test]
+fn compile_reuses_names_and_emits_constants() {
+    let script = compile_helper("let a = 1; a = a + 2;").unwrap();
+    let chunk = &script.chunk;
+
+    assert_eq!(chunk.names, vec!["a".to_string()]);
+    assert_eq!(chunk.constants, vec![Value::Integer(1), Value::Integer(2)]);
+    assert_eq!(chunk.code[0], OpCode::Constant as u8);
+    assert_eq!(*chunk.code.last().unwrap(), OpCode::Return as u8);
+}
+
+#[test]
+fn compile_too_many_locals() {
+    let locals = (0..300).map(|i| format!("let v{} = {};", i, i)).collect::<String>();
+    let source = format!("{{ {} }}", locals);
+    let diagnostic = compile_helper(&source).unwrap_err().diagnostic();
+
+    assert_eq!(diagnostic.message, "Too many local variables (limit 256)");
+    let span = diagnostic.label.unwrap().span;
+    assert_eq!(&source[span.start..span.end], "v255");
+}
+
+#[test]
+fn runtime_error_position_from_span_table() {
+    let source = "let a = 1;\nlet b = a +\n  nil;";
+    let mut vm = Vm::new(Interpreter::with_output(Box::new(SharedBuffer::default())));
+    let err = run_with(&mut vm, source).unwrap_err();
+    assert_eq!(summary(&err.diagnostic(), source), "Mismatched types for '+': int, nil at [2:10]");
+}
+
+#[test]
+fn invalid_opcode_is_an_error() {
+    let mut chunk = Chunk::new();
+    chunk.write(OpCode::Nil as u8, Span::new(0, 3));
+    chunk.write(OpCode::GetIter as u8 + 1, Span::new(8, 9));
+    let script = object::FunctionProto { name: String::new(), arity: 0, upvalue_count: 0, chunk };
+
+    let mut vm = Vm::new(Interpreter::with_output(Box::new(SharedBuffer::default())));
+    let err = vm.interpret(Rc::new(script)).unwrap_err();
+    let diagnostic = err.diagnostic();
+    assert_eq!(diagnostic.message, format!("Internal error: invalid opcode {} at offset 1", OpCode::GetIter as u8 + 1));
+    assert_eq!(diagnostic.label.map(|label| label.span), Some(Span::new(8, 9)));
+}
+
+#[test]
+fn globals_persist_between_scripts() {
+    let output = SharedBuffer::default();
+    let mut vm = Vm::new(Interpreter::with_output(Box::new(output.clone())));
+
+    run_with(&mut vm, "let x = 1; fn get() { return x; }").unwrap();
+    run_with(&mut vm, "x = x + 1;").unwrap();
+    //エラーの後も状態が壊れていないこと
+    assert!(run_with(&mut vm, "{ let y = 1; print missing; }").is_err());
+    run_with(&mut vm, "print get();").unwrap();
+
+    assert_eq!(output.take(), "2\n");
+}
+
+#[test]
+fn stack_overflow_on_both_backends() {
+    let source = "fn f(n) { return f(n + 1); } f(0);";
+
+    //tree-walkerはRustのスタックを使うのでmainと同じく大きなスタックで実行する
+    let handle = thread::Builder::new()
+        .stack_size(256 * 1024 * 1024)
+        .spawn(move || {
+            let tokens = Lexer::new(source).lex().unwrap();
+            let program = Parser::new(tokens).parse_program().unwrap();
+            Resolver::new().resolve(&program).unwrap();
+
+            let tree = Interpreter::with_output(Box::new(SharedBuffer::default())).interpret(&program);
+            let mut vm = Vm::new(Interpreter::with_output(Box::new(SharedBuffer::default())));
+            let vm = vm.interpret(Compiler::new().compile(&program).unwrap());
+
+            (summary(&tree.unwrap_err().diagnostic(), source), summary(&vm.unwrap_err().diagnostic(), source))
+        })
+        .unwrap();
+
+    let (tree, vm) = handle.join().unwrap();
+    assert_eq!(tree, "Stack overflow at [1:18]");
+    assert_eq!(vm, tree);
+}
+
+#[test]
+fn call_depth_limit_is_exact() {
+    let output = SharedBuffer::default();
+    let mut vm = Vm::new(Interpreter::with_output(Box::new(output.clone())));
+
+    let program = format!(
+        "fn f(n) {{ if (n > 1) {{ return f(n - 1); }} return n; }} print f({});",
+        MAX_CALL_DEPTH,
+    );
+    run_with(&mut vm, &program).unwrap();
+    assert_eq!(output.take(), "1\n");
+
+    let program = format!("print f({});", MAX_CALL_DEPTH + 1);
+    assert!(run_with(&mut vm, &program).is_err());
+}
@@ -0,0 +1,573 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    rloxs_eval::{
+        class::{Class, Instance},
+        eval::MAX_CALL_DEPTH,
+        gc::GcRef,
+        iter::ITER,
+        map::build_map,
+        native::{builtin_methods, call_bound_native, call_native, BoundNative, NativeFunction},
+        ops::{eval_binary, eval_unary, get_index, set_index},
+        value::Value,
+        EvalError,
+        Interpreter,
+    },
+    rloxs_eval::errors::{
+        ArityMismatch,
+        InvalidOpcode,
+        InvalidSuperclass,
+        NotAnInstance,
+        NotCallable,
+        NotIterable,
+        StackOverflow,
+        UndeclaredAssignment,
+        UndefinedProperty,
+        UndefinedVariable,
+    },
+    syntax::{Operator, OperatorKind, Span},
+};
+
+use super::{
+    chunk::{Chunk, OpCode},
+    object::{BoundMethod, Closure, FunctionProto, Upvalue},
+};
+
+#[derive(Debug)]
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    //この関数のスロット0のスタック上の位置
+    base: usize,
+}
+
+impl CallFrame {
+    fn new(closure: Rc<Closure>, base: usize) -> Self {
+        Self { closure, ip: 0, base }
+    }
+
+    fn chunk(&self) -> &Chunk {
+        &self.closure.function.chunk
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let byte = self.closure.function.chunk.code[self.ip];
+        self.ip += 1;
+        byte
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        let value = self.closure.function.chunk.read_u16(self.ip);
+        self.ip += 2;
+        value
+    }
+
+    fn read_name(&mut self) -> String {
+        let index = self.read_u16() as usize;
+        self.chunk().names[index].clone()
+    }
+}
+
+//バイトコードを実行するスタックマシン。
+//グローバル変数と出力先はtree-walkerのInterpreterと共有する
+pub struct Vm {
+    host: Interpreter,
+    stack: Vec<Value>,
+    //呼び出し元のフレーム。実行中のフレームはrunのローカル変数で持つ
+    frames: Vec<CallFrame>,
+    //まだスタック上にある変数を指すupvalue。スロット順に並べる
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+impl Vm {
+    pub fn new(host: Interpreter) -> Self {
+        Self {
+            host,
+            stack: vec![],
+            frames: vec![],
+            open_upvalues: vec![],
+        }
+    }
+
+    pub fn host(&self) -> &Interpreter {
+        &self.host
+    }
+
+    pub fn interpret(&mut self, script: Rc<FunctionProto>) -> Result<(), EvalError> {
+        let closure = Rc::new(Closure::new(script, vec![]));
+        self.track(GcRef::Closure(closure.clone()));
+        self.stack.push(Value::Closure(closure.clone()));
+
+        let result = self.run(CallFrame::new(closure, 0));
+        if result.is_err() {
+            //エラーで中断したときはREPLの次の行のために状態を捨てる
+            self.stack.clear();
+            self.frames.clear();
+            self.open_upvalues.clear();
+        }
+
+        result
+    }
+
+    fn run(&mut self, mut frame: CallFrame) -> Result<(), EvalError> {
+        loop {
+            let offset = frame.ip;
+            let byte = frame.read_byte();
+            let span = frame.chunk().span(offset);
+            let op = match OpCode::try_from(byte) {
+                Ok(op) => op,
+                Err(byte) => Err(InvalidOpcode::new(byte, offset, span))?,
+            };
+
+            match op {
+                OpCode::Constant => {
+                    let index = frame.read_u16() as usize;
+                    self.push(frame.chunk().constants[index].clone());
+                },
+                OpCode::Nil => self.push(Value::Nil),
+                OpCode::True => self.push(Value::Bool(true)),
+                OpCode::False => self.push(Value::Bool(false)),
+                OpCode::Pop => {
+                    self.pop();
+                },
+                OpCode::Dup => self.push(self.peek(0).clone()),
+                OpCode::Dup2 => {
+                    let (below, top) = (self.peek(1).clone(), self.peek(0).clone());
+                    self.push(below);
+                    self.push(top);
+                },
+                OpCode::GetLocal => {
+                    let slot = frame.base + frame.read_byte() as usize;
+                    self.push(self.stack[slot].clone());
+                },
+                OpCode::SetLocal => {
+                    let slot = frame.base + frame.read_byte() as usize;
+                    self.stack[slot] = self.peek(0).clone();
+                },
+                OpCode::GetGlobal => {
+                    let name = frame.read_name();
+                    let value = self.host.globals().borrow().get(&name);
+                    match value {
+                        Some(value) => self.push(value),
+                        None => Err(UndefinedVariable::new(name, span))?,
+                    }
+                },
+                OpCode::DefineGlobal => {
+                    let name = frame.read_name();
+                    let value = self.pop();
+                    self.host.globals().borrow_mut().define(&name, value);
+                },
+                OpCode::SetGlobal => {
+                    let name = frame.read_name();
+                    let value = self.peek(0).clone();
+                    if !self.host.globals().borrow_mut().assign(&name, value) {
+                        Err(UndeclaredAssignment::new(name, span))?
+                    }
+                },
+                OpCode::GetUpvalue => {
+                    let index = frame.read_byte() as usize;
+                    let value = match &*frame.closure.upvalues[index].borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.push(value);
+                },
+                OpCode::SetUpvalue => {
+                    let index = frame.read_byte() as usize;
+                    let value = self.peek(0).clone();
+                    match &mut *frame.closure.upvalues[index].borrow_mut() {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                },
+                OpCode::GetProperty => {
+                    let name = frame.read_name();
+                    let object = self.pop();
+                    let value = self.get_member(object, name, span)?;
+                    self.push(value);
+                },
+                //反復プロトコル(rloxs_eval::iterを参照)の入口。iterを持たない値はNotIterable
+                OpCode::GetIter => {
+                    let object = self.pop();
+                    let type_name = object.type_name();
+                    let iter = self
+                        .get_member(object, ITER.to_string(), span)
+                        .map_err(|_| NotIterable::new(type_name, span))?;
+                    self.push(iter);
+                },
+                OpCode::SetProperty => {
+                    let name = frame.read_name();
+                    let value = self.pop();
+                    let instance = match self.pop() {
+                        Value::Instance(instance) => instance,
+                        value => Err(NotAnInstance::new(value.type_name(), span))?,
+                    };
+
+                    instance.borrow_mut().fields.insert(name, value.clone());
+                    self.push(value);
+                },
+                OpCode::GetSuper => {
+                    let name = frame.read_name();
+                    let (Value::Class(superclass), Value::Instance(instance)) = (self.pop(), self.pop()) else {
+                        unreachable!("super and this are only defined by class declarations")
+                    };
+
+                    match find_method(&superclass, &name) {
+                        Some(method) => {
+                            let bound = self.new_bound_method(instance, method);
+                            self.push(bound);
+                        },
+                        None => Err(UndefinedProperty::new(name, span))?,
+                    }
+                },
+                OpCode::Equal
+                | OpCode::NotEqual
+                | OpCode::Greater
+                | OpCode::GreaterEqual
+                | OpCode::Less
+                | OpCode::LessEqual
+                | OpCode::Add
+                | OpCode::Subtract
+                | OpCode::Multiply
+                | OpCode::Divide
+                | OpCode::IntDivide
+                | OpCode::Modulo => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let operator = Operator { op_kind: binary_operator(op), span };
+                    self.push(eval_binary(&operator, left, right)?);
+                },
+                OpCode::Not | OpCode::Negate => {
+                    let op_kind = match op {
+                        OpCode::Not => OperatorKind::Not,
+                        _ => OperatorKind::Subtract,
+                    };
+                    let operand = self.pop();
+                    let operator = Operator { op_kind, span };
+                    self.push(eval_unary(&operator, operand)?);
+                },
+                OpCode::Print => {
+                    let value = self.pop();
+                    writeln!(self.host.output(), "{}", value).unwrap();
+                },
+                OpCode::Interpolate => {
+                    let count = frame.read_byte() as usize;
+                    let parts = self.stack.split_off(self.stack.len() - count);
+                    let str = parts.iter().map(|part| part.to_string()).collect::<String>();
+                    self.push(Value::String(str));
+                },
+                OpCode::BuildList => {
+                    let count = frame.read_u16() as usize;
+                    let elements = self.stack.split_off(self.stack.len() - count);
+                    let list = Rc::new(RefCell::new(elements));
+                    self.track(GcRef::List(list.clone()));
+                    self.push(Value::List(list));
+                },
+                OpCode::BuildMap => {
+                    let count = frame.read_u16() as usize;
+                    let pairs = self.stack.split_off(self.stack.len() - count * 2);
+                    let map = Rc::new(RefCell::new(build_map(&pairs, span)?));
+                    self.track(GcRef::Map(map.clone()));
+                    self.push(Value::Map(map));
+                },
+                OpCode::GetIndex => {
+                    let index = self.pop();
+                    let object = self.pop();
+                    self.push(get_index(&object, &index, span)?);
+                },
+                OpCode::SetIndex => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let object = self.pop();
+                    set_index(&object, &index, value.clone(), span)?;
+                    self.push(value);
+                },
+                OpCode::Jump => {
+                    let jump = frame.read_u16() as usize;
+                    frame.ip += jump;
+                },
+                OpCode::JumpIfFalse => {
+                    let jump = frame.read_u16() as usize;
+                    if !self.peek(0).is_truthy() {
+                        frame.ip += jump;
+                    }
+                },
+                OpCode::Loop => {
+                    let jump = frame.read_u16() as usize;
+                    frame.ip -= jump;
+                },
+                OpCode::Call => {
+                    let arg_count = frame.read_byte() as usize;
+                    let callee = self.peek(arg_count).clone();
+                    if let Some(callee_frame) = self.call_value(callee, arg_count, span)? {
+                        self.frames.push(std::mem::replace(&mut frame, callee_frame));
+                    }
+                },
+                OpCode::Closure => {
+                    let index = frame.read_u16() as usize;
+                    let function = frame.chunk().functions[index].clone();
+
+                    let mut upvalues = Vec::with_capacity(function.upvalue_count);
+                    for _ in 0..function.upvalue_count {
+                        let is_local = frame.read_byte() == 1;
+                        let index = frame.read_byte() as usize;
+                        let upvalue = match is_local {
+                            true => self.capture_upvalue(frame.base + index),
+                            false => frame.closure.upvalues[index].clone(),
+                        };
+                        upvalues.push(upvalue);
+                    }
+
+                    let closure = Rc::new(Closure::new(function, upvalues));
+                    self.track(GcRef::Closure(closure.clone()));
+                    self.push(Value::Closure(closure));
+                },
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                },
+                OpCode::Return => {
+                    let result = self.pop();
+                    self.close_upvalues(frame.base);
+                    self.stack.truncate(frame.base);
+
+                    match self.frames.pop() {
+                        Some(caller) => {
+                            frame = caller;
+                            self.push(result);
+                        },
+                        //スクリプト全体の終わり
+                        None => return Ok(()),
+                    }
+                },
+                OpCode::Class => {
+                    let name = frame.read_name();
+                    let has_superclass = frame.read_byte() == 1;
+
+                    let superclass = match has_superclass {
+                        true => match self.pop() {
+                            Value::Class(class) => Some(class),
+                            value => Err(InvalidSuperclass::new(value.type_name(), span))?,
+                        },
+                        false => None,
+                    };
+                    let class = Rc::new(Class::new(name, superclass));
+                    self.track(GcRef::Class(class.clone()));
+                    self.push(Value::Class(class));
+                },
+                OpCode::Method => {
+                    let name = frame.read_name();
+                    let method = self.pop();
+                    match self.peek(0) {
+                        Value::Class(class) => class.add_method(&name, method),
+                        _ => unreachable!("methods are only compiled inside class declarations"),
+                    }
+                },
+            }
+        }
+    }
+
+    //新しく作ったオブジェクトをホストのGCに登録し、閾値を超えていれば回収する
+    fn track(&mut self, object: GcRef) {
+        let heap = self.host.heap_mut();
+        heap.register(object);
+        if heap.should_collect() {
+            self.collect_garbage();
+        }
+    }
+
+    //VMのルートはグローバル、スタック、呼び出し元のフレーム、開いているupvalue。
+    //実行中のフレームはrunのローカル変数から参照されているのでGCがルートとして扱う
+    pub fn collect_garbage(&mut self) {
+        let mut roots = vec![GcRef::Environment(self.host.globals().clone())];
+        roots.extend(self.stack.iter().filter_map(GcRef::from_value));
+        roots.extend(self.frames.iter().map(|frame| GcRef::Closure(frame.closure.clone())));
+        roots.extend(self.open_upvalues.iter().map(|upvalue| GcRef::Upvalue(upvalue.clone())));
+
+        self.host.heap_mut().collect(roots);
+    }
+
+    //インスタンスのプロパティか、組み込みの型のメソッド。フィールドがメソッドより優先される
+    fn get_member(
+        &mut self,
+        object: Value,
+        name: String,
+        span: Span,
+    ) -> Result<Value, EvalError> {
+        match object {
+            Value::Instance(instance) => {
+                let field = instance.borrow().fields.get(&name).cloned();
+                match field {
+                    Some(value) => Ok(value),
+                    None => {
+                        let class = instance.borrow().class.clone();
+                        match find_method(&class, &name) {
+                            Some(method) => Ok(self.new_bound_method(instance, method)),
+                            None => Err(UndefinedProperty::new(name, span))?,
+                        }
+                    },
+                }
+            },
+            value => match builtin_methods(&value) {
+                Some(methods) => match methods(&name) {
+                    Some(method) => Ok(self.new_bound_native(value, method)),
+                    None => Err(UndefinedProperty::new(name, span))?,
+                },
+                None => Err(NotAnInstance::new(value.type_name(), span))?,
+            },
+        }
+    }
+
+    fn new_bound_method(&mut self, receiver: Rc<RefCell<Instance>>, method: Rc<Closure>) -> Value {
+        let bound = Rc::new(BoundMethod::new(receiver, method));
+        self.track(GcRef::BoundMethod(bound.clone()));
+        Value::BoundMethod(bound)
+    }
+
+    fn new_bound_native(&mut self, receiver: Value, native: NativeFunction) -> Value {
+        let bound = Rc::new(BoundNative::new(receiver, native));
+        self.track(GcRef::BoundNative(bound.clone()));
+        Value::BoundNative(bound)
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    //呼び出し先がバイトコードの関数なら新しいフレームを返す
+    fn call_value(
+        &mut self,
+        callee: Value,
+        arg_count: usize,
+        span: Span,
+    ) -> Result<Option<CallFrame>, EvalError> {
+        let slot = self.stack.len() - 1 - arg_count;
+
+        match callee {
+            Value::Closure(closure) => {
+                let name = closure.function.name.clone();
+                self.call_closure(closure, name, arg_count, span).map(Some)
+            },
+            Value::BoundMethod(bound) => {
+                self.stack[slot] = Value::Instance(bound.receiver.clone());
+                let name = bound.method.function.name.clone();
+                self.call_closure(bound.method.clone(), name, arg_count, span).map(Some)
+            },
+            //クラスを呼び出すときの引数の数はinitに従う
+            Value::Class(class) => {
+                let instance = Rc::new(RefCell::new(Instance::new(class.clone())));
+                self.stack[slot] = Value::Instance(instance.clone());
+                self.track(GcRef::Instance(instance));
+
+                match find_method(&class, "init") {
+                    Some(init) => self.call_closure(init, class.name.clone(), arg_count, span).map(Some),
+                    None if arg_count != 0 => {
+                        Err(ArityMismatch::new(class.name.clone(), 0, arg_count, span))?
+                    },
+                    None => Ok(None),
+                }
+            },
+            Value::Native(native) => {
+                let args = self.stack.split_off(slot + 1);
+                let result = call_native(&mut self.host, &native, &args, span)?;
+                self.stack.truncate(slot);
+                self.push(result);
+                Ok(None)
+            },
+            Value::BoundNative(bound) => {
+                let args = self.stack.split_off(slot + 1);
+                let result = call_bound_native(&mut self.host, &bound, &args, span)?;
+                self.stack.truncate(slot);
+                self.push(result);
+                Ok(None)
+            },
+            callee => Err(NotCallable::new(callee.type_name(), span))?,
+        }
+    }
+
+    fn call_closure(
+        &mut self,
+        closure: Rc<Closure>,
+        name: String,
+        arg_count: usize,
+        span: Span,
+    ) -> Result<CallFrame, EvalError> {
+        if closure.function.arity != arg_count {
+            Err(ArityMismatch::new(name, closure.function.arity, arg_count, span))?
+        }
+
+        //framesにはスクリプトを含む呼び出し元が入っているので、その数が関数呼び出しのネストの深さになる
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            Err(StackOverflow::new(span))?
+        }
+
+        let base = self.stack.len() - 1 - arg_count;
+        Ok(CallFrame::new(closure, base))
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let position = self.open_upvalues.partition_point(|upvalue| open_slot(upvalue) < slot);
+
+        if let Some(upvalue) = self.open_upvalues.get(position) {
+            if open_slot(upvalue) == slot {
+                return upvalue.clone();
+            }
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        self.open_upvalues.insert(position, upvalue.clone());
+        self.track(GcRef::Upvalue(upvalue.clone()));
+        upvalue
+    }
+
+    //from以降のスロットを指すupvalueに値を移す
+    fn close_upvalues(&mut self, from: usize) {
+        let position = self.open_upvalues.partition_point(|upvalue| open_slot(upvalue) < from);
+
+        for upvalue in self.open_upvalues.drain(position..) {
+            let slot = open_slot(&upvalue);
+            *upvalue.borrow_mut() = Upvalue::Closed(self.stack[slot].clone());
+        }
+    }
+}
+
+fn open_slot(upvalue: &RefCell<Upvalue>) -> usize {
+    match &*upvalue.borrow() {
+        Upvalue::Open(slot) => *slot,
+        Upvalue::Closed(_) => unreachable!("closed upvalues are removed from the open list"),
+    }
+}
+
+//VMで定義したクラスのメソッドは常にClosure
+fn find_method(class: &Class, name: &str) -> Option<Rc<Closure>> {
+    match class.find_method(name)? {
+        Value::Closure(closure) => Some(closure),
+        _ => None,
+    }
+}
+
+fn binary_operator(op: OpCode) -> OperatorKind {
+    match op {
+        OpCode::Equal => OperatorKind::Equal,
+        OpCode::NotEqual => OperatorKind::NotEqual,
+        OpCode::Greater => OperatorKind::Greater,
+        OpCode::GreaterEqual => OperatorKind::GreaterEqual,
+        OpCode::Less => OperatorKind::Less,
+        OpCode::LessEqual => OperatorKind::LessEqual,
+        OpCode::Add => OperatorKind::Add,
+        OpCode::Subtract => OperatorKind::Subtract,
+        OpCode::Multiply => OperatorKind::Multiply,
+        OpCode::Divide => OperatorKind::Divide,
+        OpCode::IntDivide => OperatorKind::IntDivide,
+        OpCode::Modulo => OperatorKind::Modulo,
+        op => unreachable!("{:?} is not a binary operator", op),
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt::Write;
+
+use super::stmt::{FnDecl, Stmt};
+
+const INDENT: &str = "    ";
+
+//トップレベルの宣言をドキュメントコメントと一緒に一覧にする。--docの出力
+//宣言ごとに空行で区切る
+pub fn render_docs(program: &[Stmt]) -> String {
+    program.iter()
+        .filter_map(render_stmt)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_stmt(stmt: &Stmt) -> Option<String> {
+    let mut out = String::new();
+
+    match stmt {
+        Stmt::Fn(decl) => write_fn(&mut out, decl, 0),
+        Stmt::Class(decl) => {
+            let superclass = match &decl.superclass {
+                Some(superclass) => format!(" < {}", superclass.name),
+                None => String::new(),
+            };
+            write_entry(&mut out, &format!("class {}{}", decl.name.name, superclass), &decl.doc, 0);
+            for method in &decl.methods {
+                write_fn(&mut out, method, 1);
+            }
+        },
+        Stmt::Let { name, doc, .. } => write_entry(&mut out, &format!("let {}", name.name), doc, 0),
+        _ => return None,
+    }
+
+    Some(out)
+}
+
+fn write_fn(out: &mut String, decl: &FnDecl, depth: usize) {
+    let params = decl.params.iter().map(|param| param.name.as_str()).collect::<Vec<_>>();
+    write_entry(out, &format!("fn {}({})", decl.name.name, params.join(", ")), &decl.doc, depth);
+}
+
+fn write_entry(out: &mut String, signature: &str, doc: &Option<String>, depth: usize) {
+    let indent = INDENT.repeat(depth);
+    writeln!(out, "{}{}", indent, signature).unwrap();
+
+    for line in doc.iter().flat_map(|doc| doc.lines()) {
+        writeln!(out, "{}{}{}", indent, INDENT, line).unwrap();
+    }
+}
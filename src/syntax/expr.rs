@@ -1,24 +1,78 @@
-use super::token::LiteralKind;
+use std::{cell::Cell, fmt};
 
+use super::{span::Span, token::{LiteralKind, Token}};
+
+//depthはresolverが書き込むスコープの距離。Noneならグローバル変数
+//spanはノード全体のソース上の範囲
 #[derive(Debug)]
 pub enum Expr {
-    Assign{ name: String, expr: Box<Expr>},
-    Literal { kind: LiteralKind},
-    Variable(String),
-    BinaryOp { left: Box<Expr>, operator: Operator, right: Box<Expr>},
-    UnaryOp { operator: Operator, operand: Box<Expr>},
-    Grouping( Box<Expr>),
+    Assign{ name: Identifier, expr: Box<Expr>, depth: Cell<Option<usize>>, span: Span },
+    Literal { kind: LiteralKind, span: Span },
+    Variable { name: Identifier, depth: Cell<Option<usize>>, span: Span },
+    BinaryOp { left: Box<Expr>, operator: Operator, right: Box<Expr>, span: Span },
+    UnaryOp { operator: Operator, operand: Box<Expr>, span: Span },
+    Grouping { expr: Box<Expr>, span: Span },
+    //parenは(のトークン。呼び出しの実行時エラーはここを指す
+    Call { callee: Box<Expr>, args: Vec<Expr>, paren: Token, span: Span },
+    Get { object: Box<Expr>, name: Identifier, span: Span },
+    //operatorは複合代入(+=など)の演算子。現在の値とvalueを計算してから代入する
+    Set { object: Box<Expr>, name: Identifier, operator: Option<Operator>, value: Box<Expr>, span: Span },
+    //thisとsuperはキーワードを変数として引く
+    This { keyword: Identifier, depth: Cell<Option<usize>> },
+    Super { keyword: Identifier, method: Identifier, depth: Cell<Option<usize>>, span: Span },
+    //文字列の部分と${}の式を順に並べたもの。各値はprintと同じ表記で連結する
+    Interpolation { parts: Vec<Expr>, span: Span },
+    List { elements: Vec<Expr>, span: Span },
+    //キーと値の組をソースの順に持つ。braceは#{のトークン
+    Map { entries: Vec<(Expr, Expr)>, brace: Token, span: Span },
+    //bracketは[のトークン
+    Index { object: Box<Expr>, index: Box<Expr>, bracket: Token, span: Span },
+    IndexSet {
+        object: Box<Expr>,
+        index: Box<Expr>,
+        operator: Option<Operator>,
+        value: Box<Expr>,
+        bracket: Token,
+        span: Span,
+    },
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Assign { span, .. }
+            | Expr::Literal { span, .. }
+            | Expr::Variable { span, .. }
+            | Expr::BinaryOp { span, .. }
+            | Expr::UnaryOp { span, .. }
+            | Expr::Grouping { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Get { span, .. }
+            | Expr::Set { span, .. }
+            | Expr::Super { span, .. }
+            | Expr::Interpolation { span, .. }
+            | Expr::List { span, .. }
+            | Expr::Map { span, .. }
+            | Expr::Index { span, .. }
+            | Expr::IndexSet { span, .. } => *span,
+            Expr::This { keyword, .. } => keyword.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Identifier {
+    pub name: String,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Operator {
     pub op_kind: OperatorKind,
-    pub pos: usize,
-    pub line: usize,
-    pub column: usize,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OperatorKind {
     // Arithmetic operators
     ///"+"
@@ -29,6 +83,10 @@ pub enum OperatorKind {
     Multiply,
     ///"/"
     Divide,
+    ///"~/" 整数除算
+    IntDivide,
+    ///"%"
+    Modulo,
 
     // Comparison operators
     ///"=="
@@ -53,4 +111,27 @@ pub enum OperatorKind {
     // Unary operators
     ///"!"
     Not,
-}
\ No newline at end of file
+}
+
+impl fmt::Display for OperatorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            OperatorKind::Add => "+",
+            OperatorKind::Subtract => "-",
+            OperatorKind::Multiply => "*",
+            OperatorKind::Divide => "/",
+            OperatorKind::IntDivide => "~/",
+            OperatorKind::Modulo => "%",
+            OperatorKind::Equal => "==",
+            OperatorKind::NotEqual => "!=",
+            OperatorKind::Greater => ">",
+            OperatorKind::GreaterEqual => ">=",
+            OperatorKind::Less => "<",
+            OperatorKind::LessEqual => "<=",
+            OperatorKind::And => "and",
+            OperatorKind::Or => "or",
+            OperatorKind::Not => "!",
+        };
+        write!(f, "{}", symbol)
+    }
+}
@@ -1,8 +1,19 @@
+#[cfg(test)]
+mod tests;
+
 pub mod token;
 pub mod expr;
+pub mod stmt;
+pub mod span;
+pub mod doc;
 
 pub use token::Token;
 pub use token::TokenKind;
 pub use expr::Expr;
+pub use expr::Identifier;
 pub use expr::Operator;
 pub use expr::OperatorKind;
+pub use stmt::Stmt;
+pub use stmt::FnDecl;
+pub use stmt::ClassDecl;
+pub use span::Span;
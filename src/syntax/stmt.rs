@@ -0,0 +1,65 @@
+use std::rc::Rc;
+
+use super::{expr::{Expr, Identifier}, span::Span};
+
+#[derive(Debug)]
+pub enum Stmt {
+    Expression { expr: Expr, span: Span },
+    Print { expr: Expr, span: Span },
+    Let { name: Identifier, initializer: Option<Expr>, doc: Option<String>, span: Span },
+    Fn(Rc<FnDecl>),
+    Class(ClassDecl),
+    Block { stmts: Vec<Stmt>, span: Span },
+    If { condition: Expr, then_branch: Vec<Stmt>, else_branch: Option<Vec<Stmt>>, span: Span },
+    While { condition: Expr, body: Vec<Stmt>, span: Span },
+    For {
+        initializer: Option<Box<Stmt>>,
+        condition: Option<Expr>,
+        increment: Option<Expr>,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+    ForIn { variable: Identifier, iterable: Expr, body: Vec<Stmt>, span: Span },
+    Return { value: Option<Expr>, span: Span },
+    //構文エラーから回復した箇所。エラーのあるプログラムは実行されない
+    Error { span: Span },
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Fn(decl) => decl.span,
+            Stmt::Class(decl) => decl.span,
+            Stmt::Expression { span, .. }
+            | Stmt::Print { span, .. }
+            | Stmt::Let { span, .. }
+            | Stmt::Block { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::For { span, .. }
+            | Stmt::ForIn { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Error { span } => *span,
+        }
+    }
+}
+
+//関数値がクロージャとして本体を共有できるようにRcで持つ
+//docは直前の///コメントを改行でつないだもの
+#[derive(Debug)]
+pub struct FnDecl {
+    pub name: Identifier,
+    pub params: Vec<Identifier>,
+    pub body: Vec<Stmt>,
+    pub doc: Option<String>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct ClassDecl {
+    pub name: Identifier,
+    pub superclass: Option<Identifier>,
+    pub methods: Vec<Rc<FnDecl>>,
+    pub doc: Option<String>,
+    pub span: Span,
+}
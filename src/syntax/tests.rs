@@ -0,0 +1,90 @@
+use crate::{rloxs_lexer::Lexer, rloxs_parser::parser::Parser};
+
+use super::{doc::render_docs, span::{Position, SourceMap, Span}, token::LiteralKind, TokenKind};
+
+#[test]
+fn span_to_covers_both() {
+    assert_eq!(Span::new(4, 6).to(Span::new(0, 2)), Span::new(0, 6));
+    assert_eq!(Span::new(0, 9).to(Span::new(3, 4)), Span::new(0, 9));
+}
+
+#[test]
+fn source_map_position() {
+    let source = "let a = 1;\nlet é = \"😀\";\n";
+    let source_map = SourceMap::new(source);
+
+    assert_eq!(source_map.position(0), Position { line: 1, column: 0, utf16_column: 0 });
+    assert_eq!(source_map.position(11), Position { line: 2, column: 0, utf16_column: 0 });
+
+    //"é"は2バイト、"😀"は4バイトでUTF-16では2単位
+    let semicolon = source.rfind(';').unwrap();
+    assert_eq!(source_map.position(semicolon), Position { line: 2, column: 11, utf16_column: 12 });
+    assert_eq!(source_map.position(source.len()), Position { line: 3, column: 0, utf16_column: 0 });
+}
+
+#[test]
+fn source_map_line_text() {
+    let source_map = SourceMap::new("a\r\nb\n\nc");
+
+    assert_eq!(source_map.line_text(1), Some("a"));
+    assert_eq!(source_map.line_text(2), Some("b"));
+    assert_eq!(source_map.line_text(3), Some(""));
+    assert_eq!(source_map.line_text(4), Some("c"));
+    assert_eq!(source_map.line_text(0), None);
+    assert_eq!(source_map.line_text(5), None);
+}
+
+#[test]
+fn token_kind_display() {
+    let kinds = [
+        (TokenKind::HashBrace, "'#{'"),
+        (TokenKind::While, "'while'"),
+        (TokenKind::Ident("x".to_string()), "identifier 'x'"),
+        (TokenKind::Literal { kind: LiteralKind::Number(1.0) }, "number 1.0"),
+        (TokenKind::Literal { kind: LiteralKind::String("a\"b".to_string()) }, "string \"a\\\"b\""),
+        (TokenKind::Literal { kind: LiteralKind::Bool(false) }, "'false'"),
+        (TokenKind::StringPart("a".to_string()), "interpolated string"),
+        (TokenKind::Eof, "end of file"),
+    ];
+
+    for (kind, expected) in kinds {
+        assert_eq!(kind.to_string(), expected);
+    }
+}
+
+#[test]
+fn render_docs_lists_declarations() {
+    let source = r#"
+/// 2つの数を足す。
+///
+/// 整数どうしなら結果も整数。
+fn add(a, b) { return a + b; }
+
+print 1;
+
+class Point < Shape {
+    /// 座標を受け取る
+    fn init(x, y) {}
+    fn norm() {}
+}
+
+/// 原点
+let origin;
+"#;
+    let program = Parser::new(Lexer::new(source).lex().unwrap()).parse_program().unwrap();
+
+    assert_eq!(render_docs(&program), concat!(
+        "fn add(a, b)\n",
+        "    2つの数を足す。\n",
+        "    \n",
+        "    整数どうしなら結果も整数。\n",
+        "\n",
+        "class Point < Shape\n",
+        "    fn init(x, y)\n",
+        "        座標を受け取る\n",
+        "    fn norm()\n",
+        "\n",
+        "let origin\n",
+        "    原点\n",
+    ));
+}
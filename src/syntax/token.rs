@@ -1,8 +1,16 @@
 use std::fmt;
 
+use crate::bignum::BigInt;
+
+use super::span::Span;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum LiteralKind {
+    Nil,
     Bool(bool),
+    Integer(i64),
+    //i64に収まらない整数リテラル
+    BigInt(BigInt),
     Number(f64),
     String(String),
 }
@@ -14,13 +22,17 @@ pub enum TokenKind {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
+    Colon,
     Dot,
     Minus,
     Plus,
     Semicolon,
     Slash,
     Star,
+    Percent,
 
     // One or two character tokens
     Bang,
@@ -31,10 +43,20 @@ pub enum TokenKind {
     GreaterEqual,
     Less,
     LessEqual,
+    TildeSlash,
+    //マップリテラルの開き括弧#{
+    HashBrace,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
 
     // Literals
     Ident(String),
     Literal { kind: LiteralKind },
+    //"a${x}b${y}c"はStringPart("a"), x, StringPart("b"), y, StringEnd("c")と並ぶ
+    StringPart(String),
+    StringEnd(String),
 
     // Keywords
     And,
@@ -42,6 +64,7 @@ pub enum TokenKind {
     Else,
     Fn,
     For,
+    In,
     Nil,
     If,
     Print,
@@ -53,24 +76,122 @@ pub enum TokenKind {
     While,
 
     LineComment,
+    BlockComment,
+    //本文は///と直後の空白1つを除いたもの
+    DocComment(String),
+
+    //字句エラーの位置に置かれるトークン。エラーはLexerが別に記録する
+    Error,
 
     Eof,
 }
 
+//エラーメッセージ向けの表記。記号とキーワードは'}'のように綴りを引用符で囲む
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lexeme = match self {
+            TokenKind::LeftParen => "(",
+            TokenKind::RightParen => ")",
+            TokenKind::LeftBrace => "{",
+            TokenKind::RightBrace => "}",
+            TokenKind::LeftBracket => "[",
+            TokenKind::RightBracket => "]",
+            TokenKind::Comma => ",",
+            TokenKind::Colon => ":",
+            TokenKind::Dot => ".",
+            TokenKind::Minus => "-",
+            TokenKind::Plus => "+",
+            TokenKind::Semicolon => ";",
+            TokenKind::Slash => "/",
+            TokenKind::Star => "*",
+            TokenKind::Percent => "%",
+            TokenKind::Bang => "!",
+            TokenKind::BangEqual => "!=",
+            TokenKind::Equal => "=",
+            TokenKind::EqualEqual => "==",
+            TokenKind::Greater => ">",
+            TokenKind::GreaterEqual => ">=",
+            TokenKind::Less => "<",
+            TokenKind::LessEqual => "<=",
+            TokenKind::TildeSlash => "~/",
+            TokenKind::HashBrace => "#{",
+            TokenKind::PlusEqual => "+=",
+            TokenKind::MinusEqual => "-=",
+            TokenKind::StarEqual => "*=",
+            TokenKind::SlashEqual => "/=",
+            TokenKind::And => "and",
+            TokenKind::Class => "class",
+            TokenKind::Else => "else",
+            TokenKind::Fn => "fn",
+            TokenKind::For => "for",
+            TokenKind::In => "in",
+            TokenKind::Nil => "nil",
+            TokenKind::If => "if",
+            TokenKind::Print => "print",
+            TokenKind::Or => "or",
+            TokenKind::Return => "return",
+            TokenKind::Super => "super",
+            TokenKind::This => "this",
+            TokenKind::Let => "let",
+            TokenKind::While => "while",
+            TokenKind::Ident(name) => return write!(f, "identifier '{}'", name),
+            TokenKind::Literal { kind } => return match kind {
+                LiteralKind::Nil => write!(f, "'nil'"),
+                LiteralKind::Bool(b) => write!(f, "'{}'", b),
+                LiteralKind::Integer(n) => write!(f, "number {}", n),
+                LiteralKind::BigInt(n) => write!(f, "number {}", n),
+                LiteralKind::Number(n) => write!(f, "number {:?}", n),
+                LiteralKind::String(s) => write!(f, "string {:?}", s),
+            },
+            TokenKind::StringPart(_) => return write!(f, "interpolated string"),
+            TokenKind::StringEnd(_) => return write!(f, "end of string"),
+            TokenKind::LineComment | TokenKind::BlockComment => return write!(f, "comment"),
+            TokenKind::DocComment(_) => return write!(f, "doc comment"),
+            TokenKind::Error => return write!(f, "invalid token"),
+            TokenKind::Eof => return write!(f, "end of file"),
+        };
+        write!(f, "'{}'", lexeme)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token {
     pub token_kind: TokenKind,
-    pub pos: usize,
-    pub line: usize,
-    pub column: usize,
+    pub span: Span,
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "[{}:{}] {:?}",
-            self.line, self.column, self.token_kind
+            "[{}..{}] {:?}",
+            self.span.start, self.span.end, self.token_kind
         )
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TriviaKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+    DocComment,
+}
+
+//構文上の意味を持たないソースの断片。改行は空白に含める
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+//前後のトリビアを付けたトークン。leading、text、trailingの順に並べると元のソースに戻る
+//trailingはトークンと同じ行の行末(改行を含む)まで。それ以降は次のトークンのleadingになる
+#[derive(Debug, PartialEq, Clone)]
+pub struct TokenWithTrivia {
+    pub token: Token,
+    pub text: String,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
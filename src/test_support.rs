@@ -0,0 +1,35 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use crate::{diagnostics::Diagnostic, syntax::span::SourceMap};
+
+//printの出力をテストで読むためのバッファ
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    //ここまでの出力を取り出して空にする
+    pub fn take(&self) -> String {
+        String::from_utf8(std::mem::take(&mut *self.0.borrow_mut())).unwrap()
+    }
+}
+
+//診断を「メッセージ at [行:列]」の一行にする。位置はソースから求める
+pub fn summary(diagnostic: &Diagnostic, source: &str) -> String {
+    match diagnostic.label {
+        Some(label) => {
+            let position = SourceMap::new(source).position(label.span.start);
+            format!("{} at [{}:{}]", diagnostic.message, position.line, position.column)
+        },
+        None => diagnostic.message.clone(),
+    }
+}